@@ -0,0 +1,244 @@
+use crate::producer::build_kafka_config;
+use crate::ssl::SslConfig;
+
+use futures::executor::block_on;
+use rdkafka::admin::{
+    AdminClient, AdminOptions, AlterConfig, ConfigSource, NewTopic, ResourceSpecifier,
+    TopicReplication, TopicResult,
+};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::error::{KafkaError, KafkaResult};
+use rdkafka::types::RDKafkaRespErr;
+use std::time::Duration;
+
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+const OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
+const UNKNOWN_TOPIC: RDKafkaRespErr = RDKafkaRespErr::RD_KAFKA_RESP_ERR_UNKNOWN_TOPIC_OR_PART;
+const TOPIC_EXISTS: RDKafkaRespErr = RDKafkaRespErr::RD_KAFKA_RESP_ERR_TOPIC_ALREADY_EXISTS;
+
+/// Name, partitions, replication and config overrides of a topic to create
+#[derive(Clone)]
+pub struct TopicConfig {
+    name: String,
+    partitions: i32,
+    replication_factor: i32,
+    configs: Vec<(String, String)>,
+}
+
+impl TopicConfig {
+    /// A partition and a replica by default
+    pub fn new(name: &str) -> TopicConfig {
+        TopicConfig {
+            name: name.to_string(),
+            partitions: 1,
+            replication_factor: 1,
+            configs: Vec::new(),
+        }
+    }
+
+    pub fn partitions(mut self, partitions: i32) -> TopicConfig {
+        self.partitions = partitions;
+        self
+    }
+
+    pub fn replication_factor(mut self, replication_factor: i32) -> TopicConfig {
+        self.replication_factor = replication_factor;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets a topic config, e.g. `cleanup.policy` or `retention.ms`
+    pub fn set(mut self, key: &str, value: &str) -> TopicConfig {
+        self.configs.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+pub struct TopicDescription {
+    pub name: String,
+    pub partitions: Vec<PartitionDescription>,
+    /// Configs set on the topic, without defaults of brokers
+    pub configs: Vec<(String, String)>,
+}
+
+pub struct PartitionDescription {
+    pub id: i32,
+    pub leader: i32,
+    pub replicas: Vec<i32>,
+    /// In-sync replicas
+    pub isr: Vec<i32>,
+}
+
+/// Lists, describes, creates and deletes topics and alters their configs
+pub struct TopicAdmin {
+    client: AdminClient<DefaultClientContext>,
+}
+
+impl TopicAdmin {
+    pub fn new(hosts: &str, ssl: Option<&SslConfig>) -> KafkaResult<TopicAdmin> {
+        Ok(TopicAdmin {
+            client: build_kafka_config(hosts, ssl).create()?,
+        })
+    }
+
+    /// Names of topics, sorted
+    pub fn list_topics(&self) -> KafkaResult<Vec<String>> {
+        let metadata = self.client.inner().fetch_metadata(None, METADATA_TIMEOUT)?;
+        let mut topics: Vec<String> = metadata
+            .topics()
+            .iter()
+            .map(|topic| topic.name().to_string())
+            .collect();
+        topics.sort();
+        Ok(topics)
+    }
+
+    pub fn describe_topic(&self, topic: &str) -> KafkaResult<TopicDescription> {
+        let metadata = self
+            .client
+            .inner()
+            .fetch_metadata(Some(topic), METADATA_TIMEOUT)?;
+        let topic_metadata = metadata
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic)
+            .ok_or_else(|| KafkaError::MetadataFetch(UNKNOWN_TOPIC.into()))?;
+        if let Some(error) = topic_metadata.error() {
+            return Err(KafkaError::MetadataFetch(error.into()));
+        }
+
+        let mut partitions: Vec<PartitionDescription> = topic_metadata
+            .partitions()
+            .iter()
+            .map(|partition| PartitionDescription {
+                id: partition.id(),
+                leader: partition.leader(),
+                replicas: partition.replicas().to_vec(),
+                isr: partition.isr().to_vec(),
+            })
+            .collect();
+        partitions.sort_by_key(|partition| partition.id);
+
+        Ok(TopicDescription {
+            name: topic.to_string(),
+            partitions,
+            configs: self.topic_configs(topic)?,
+        })
+    }
+
+    pub fn create_topic(&self, config: &TopicConfig) -> KafkaResult<()> {
+        let new_topic = config.configs.iter().fold(
+            NewTopic::new(
+                &config.name,
+                config.partitions,
+                TopicReplication::Fixed(config.replication_factor),
+            ),
+            |new_topic, (key, value)| new_topic.set(key, value),
+        );
+        check_topic_results(block_on(
+            self.client.create_topics(&[new_topic], &options()),
+        ))
+    }
+
+    /// Creates the topic unless it exists. Returns whether it was created,
+    /// not if another client created it meanwhile
+    pub fn create_topic_if_missing(&self, config: &TopicConfig) -> KafkaResult<bool> {
+        if self.list_topics()?.contains(&config.name) {
+            return Ok(false);
+        }
+        match self.create_topic(config) {
+            Ok(()) => Ok(true),
+            Err(KafkaError::AdminOp(code)) if code == TOPIC_EXISTS.into() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn delete_topic(&self, topic: &str) -> KafkaResult<()> {
+        check_topic_results(block_on(self.client.delete_topics(&[topic], &options())))
+    }
+
+    /// Sets and deletes config overrides of the topic, keeping the other ones.
+    /// Brokers replace all overrides of a topic on altering, so the current ones are sent too.
+    /// Values of sensitive overrides are not described, so they have to be set or deleted.
+    pub fn alter_topic_config(
+        &self,
+        topic: &str,
+        set: &[(String, String)],
+        delete: &[String],
+    ) -> KafkaResult<()> {
+        let is_altered =
+            |key: &String| delete.contains(key) || set.iter().any(|(set_key, _)| set_key == key);
+
+        let mut configs = Vec::new();
+        let mut sensitive = Vec::new();
+        for (key, value) in self.topic_overrides(topic)? {
+            match value {
+                _ if is_altered(&key) => {}
+                Some(value) => configs.push((key, value)),
+                None => sensitive.push(key),
+            }
+        }
+        if !sensitive.is_empty() {
+            return Err(KafkaError::AdminOpCreation(format!(
+                "sensitive config overrides {} of topic {} would be dropped, set or delete them too",
+                sensitive.join(", "),
+                topic
+            )));
+        }
+        configs.extend(set.iter().cloned());
+
+        let alter_config = configs.iter().fold(
+            AlterConfig::new(ResourceSpecifier::Topic(topic)),
+            |alter_config, (key, value)| alter_config.set(key, value),
+        );
+        for result in block_on(self.client.alter_configs(&[alter_config], &options()))? {
+            result.map_err(|(_, code)| KafkaError::AdminOp(code))?;
+        }
+        Ok(())
+    }
+
+    /// Configs set on the topic, sorted by name, without sensitive ones
+    fn topic_configs(&self, topic: &str) -> KafkaResult<Vec<(String, String)>> {
+        Ok(self
+            .topic_overrides(topic)?
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect())
+    }
+
+    /// Configs set on the topic, sorted by name. Values of sensitive ones are not present
+    fn topic_overrides(&self, topic: &str) -> KafkaResult<Vec<(String, Option<String>)>> {
+        let mut configs = Vec::new();
+        for result in block_on(
+            self.client
+                .describe_configs(&[ResourceSpecifier::Topic(topic)], &options()),
+        )? {
+            let resource = result.map_err(KafkaError::AdminOp)?;
+            configs.extend(
+                resource
+                    .entries
+                    .into_iter()
+                    .filter(|entry| entry.source == ConfigSource::DynamicTopic)
+                    .map(|entry| (entry.name, entry.value)),
+            );
+        }
+        configs.sort();
+        Ok(configs)
+    }
+}
+
+fn options() -> AdminOptions {
+    AdminOptions::new()
+        .request_timeout(Some(OPERATION_TIMEOUT))
+        .operation_timeout(Some(OPERATION_TIMEOUT))
+}
+
+fn check_topic_results(results: KafkaResult<Vec<TopicResult>>) -> KafkaResult<()> {
+    for result in results? {
+        result.map_err(|(_, code)| KafkaError::AdminOp(code))?;
+    }
+    Ok(())
+}
@@ -1,6 +1,7 @@
-use crate::context::AppCtx;
 use crate::error::CliError;
+use crate::named;
 use crate::registry;
+use crate::wire;
 use avro_rs::schema::UnionSchema;
 
 use avro_rs::types::Value as AvroValue;
@@ -16,32 +17,190 @@ pub fn parse_schema(raw_schema: &str) -> Result<Schema, CliError> {
     Schema::parse_str(raw_schema).map_err(|e| e.into())
 }
 
-pub fn get_registered_schema(ctx: &AppCtx) -> Result<(u32, Schema), CliError> {
-    let subject = registry::get_subject(ctx.kafka_ctx.topic.as_str());
-    let registry_client = registry::RegistryClient::new(ctx)?;
+/// Combines raw schemas, which may refer to each other's named types, into one.
+/// The root is either the named type picked by fullname or the one nothing refers to.
+/// A single schema is returned as is.
+pub fn combine_schemas(
+    raw_schemas: &[String],
+    root: Option<&str>,
+) -> Result<Option<String>, CliError> {
+    match (raw_schemas, root) {
+        ([], _) => Ok(None),
+        ([raw_schema], None) => Ok(Some(raw_schema.clone())),
+        _ => {
+            let schemas = raw_schemas
+                .iter()
+                .map(|raw| serde_json::from_str(raw))
+                .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+            match root {
+                Some(name) => named::select(&schemas, name),
+                None => named::combine(&schemas),
+            }
+            .map(|schema| Some(schema.to_string()))
+        }
+    }
+}
+
+/// Parse Avro schema which refers to named types defined in dependencies.
+pub fn parse_schema_with_references(
+    raw_schema: &str,
+    dependencies: &[String],
+) -> Result<Schema, CliError> {
+    parse_schema(&standalone_schema(raw_schema, dependencies)?)
+}
 
-    let (id, raw_schema) = match &ctx.avro_ctx.schema {
-        Some(raw_schema) => registry_client
-            .register_schema(&subject, &raw_schema)
-            .map(|id| (id, raw_schema.to_string())),
-        None => registry_client.get_schema_by_subject(&subject),
-    }?;
+/// Inlines named types of dependencies the schema refers to
+pub(crate) fn standalone_schema(
+    raw_schema: &str,
+    dependencies: &[String],
+) -> Result<String, CliError> {
+    if dependencies.is_empty() {
+        return Ok(raw_schema.to_string());
+    }
 
-    parse_schema(&raw_schema).map(|s| (id, s))
+    let schema: JsonValue = serde_json::from_str(raw_schema)?;
+    let dependencies = dependencies
+        .iter()
+        .map(|raw| serde_json::from_str(raw))
+        .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+
+    Ok(named::inline_references(&schema, &dependencies)?.to_string())
 }
 
-pub fn encode(value: AvroValue, schema: &Schema) -> AvroResult<Vec<u8>> {
-    avro_rs::to_avro_datum(schema, value)
+/// Fetches a registered schema by id, together with the schemas it refers to
+pub fn get_schema_by_id(
+    registry_client: &registry::RegistryClient,
+    id: registry::SchemaId,
+) -> Result<Schema, CliError> {
+    let registered = registry_client.get_schema_by_id(id)?;
+    let dependencies = registry_client.resolve_references(&registered.references)?;
+    parse_schema_with_references(&registered.schema, &dependencies)
+}
+
+/// Decodes messages of any wire format, which is detected by the message header unless set.
+/// Writer schemas are fetched by id once and cached, single-object encoded messages
+/// are looked up by fingerprint among local writer schemas.
+pub struct Decoder {
+    registry_client: Option<registry::RegistryClient>,
+    schemas: HashMap<wire::Header, Schema>,
+    /// Schema decoded values are resolved to. Values stay in the writer schema if not present
+    reader_schema: Option<Schema>,
+    /// Wire format of messages, detected by their headers if not present
+    wire_format: Option<wire::WireFormat>,
+}
+
+impl Decoder {
+    /// Ids are looked up with the registry client, if any, fingerprints among the writer schemas
+    pub fn new(
+        registry_client: Option<registry::RegistryClient>,
+        writer_schemas: &[String],
+        reader_schema: Option<Schema>,
+    ) -> Result<Self, CliError> {
+        let mut schemas = HashMap::new();
+        for raw_schema in writer_schemas {
+            let fingerprint = wire::fingerprint(raw_schema)?;
+            schemas.insert(
+                wire::Header::Fingerprint(fingerprint),
+                parse_schema(raw_schema)?,
+            );
+        }
+        // raw datums are written with the only local schema
+        if let [raw_schema] = writer_schemas {
+            schemas.insert(wire::Header::Raw, parse_schema(raw_schema)?);
+        }
+
+        Ok(Decoder {
+            registry_client,
+            schemas,
+            reader_schema,
+            wire_format: None,
+        })
+    }
+
+    /// Splits messages as the format tells instead of detecting it
+    pub fn wire_format(mut self, wire_format: wire::WireFormat) -> Self {
+        self.wire_format = Some(wire_format);
+        self
+    }
+
+    /// Returns the decoded value and the header of its writer schema.
+    /// The header is `None` when the value is resolved to the reader schema.
+    pub fn decode(
+        &mut self,
+        message: &[u8],
+    ) -> Result<(Option<wire::Header>, AvroValue), CliError> {
+        let (header, mut datum) = match self.wire_format {
+            Some(wire_format) => wire::split_as(message, wire_format)?,
+            None => wire::split(
+                message,
+                self.registry_client
+                    .as_ref()
+                    .map(|registry_client| registry_client.registry_type()),
+            )?,
+        };
+
+        self.writer_schema(header)?;
+        let value = avro_rs::from_avro_datum(
+            &self.schemas[&header],
+            &mut datum,
+            self.reader_schema.as_ref(),
+        )?;
+
+        match self.reader_schema {
+            Some(_) => Ok((None, value)),
+            None => Ok((Some(header), value)),
+        }
+    }
+
+    /// Schema of decoded values: the reader schema, or the writer schema of the header
+    pub fn value_schema(&mut self, header: Option<wire::Header>) -> Result<&Schema, CliError> {
+        match header {
+            Some(header) => self.writer_schema(header),
+            None => Ok(self.reader_schema.as_ref().expect("reader schema expected")),
+        }
+    }
+
+    fn writer_schema(&mut self, header: wire::Header) -> Result<&Schema, CliError> {
+        if !self.schemas.contains_key(&header) {
+            let schema = match (header, &self.registry_client, &self.reader_schema) {
+                (wire::Header::Fingerprint(fingerprint), _, _) => {
+                    return Err(CliError::Schema(format!(
+                        "no writer schema with fingerprint {:016x}",
+                        fingerprint
+                    )))
+                }
+                // a raw datum is read as written with the reader schema
+                (wire::Header::Raw, _, Some(reader_schema)) => reader_schema.clone(),
+                (wire::Header::Raw, _, None) => {
+                    return Err(CliError::Schema(
+                        "message has no schema header, a single writer schema or a reader schema expected"
+                            .to_string(),
+                    ))
+                }
+                (header, Some(registry_client), _) => get_schema_by_id(
+                    registry_client,
+                    header.schema_id().expect("schema id expected"),
+                )?,
+                (header, None, _) => {
+                    return Err(CliError::Schema(format!(
+                        "message refers to schema id {}, --registry-url expected",
+                        header.schema_id().expect("schema id expected")
+                    )))
+                }
+            };
+            self.schemas.insert(header, schema);
+        }
+        Ok(&self.schemas[&header])
+    }
 }
 
-pub fn encode_with_schema_id(
-    value: AvroValue,
-    schema: &Schema,
-    schema_id: u32,
-) -> AvroResult<Vec<u8>> {
-    avro_rs::to_avro_datum(schema, value).map(|bytes| registry::append_schema_id(schema_id, bytes))
+/// Encodes a value into an Avro datum without any header
+pub fn encode(value: AvroValue, schema: &Schema) -> AvroResult<Vec<u8>> {
+    avro_rs::to_avro_datum(schema, value)
 }
 
+/// Maps plain JSON onto the schema. Union values take the best fitting variant,
+/// missing record fields are mapped as `null`.
 pub fn map_with_schema(json: &JsonValue, schema: &SchemaType) -> Result<AvroValue, CliError> {
     match (schema, json) {
         (SchemaType::Null, JsonValue::Null) => Ok(AvroValue::Null),
@@ -52,10 +211,11 @@ pub fn map_with_schema(json: &JsonValue, schema: &SchemaType) -> Result<AvroValu
         (SchemaType::Long, JsonValue::Number(ref n)) if n.is_i64() => {
             Ok(AvroValue::Long(n.as_i64().unwrap()))
         }
-        (SchemaType::Float, JsonValue::Number(ref n)) if n.is_f64() => {
+        // integers are promoted to floating point
+        (SchemaType::Float, JsonValue::Number(ref n)) => {
             Ok(AvroValue::Float(n.as_f64().unwrap() as f32))
         }
-        (SchemaType::Double, JsonValue::Number(ref n)) if n.is_f64() => {
+        (SchemaType::Double, JsonValue::Number(ref n)) => {
             Ok(AvroValue::Double(n.as_f64().unwrap()))
         }
         (SchemaType::String, JsonValue::String(s)) => Ok(AvroValue::String(s.clone())),
@@ -122,31 +282,160 @@ pub fn map_with_schema(json: &JsonValue, schema: &SchemaType) -> Result<AvroValu
     }
 }
 
-//TODO: find another way to get the schema
-fn get_suitable_type_from_union<'s, 'v>(
+/// Converts an Avro value into plain JSON of the same shape `map_with_schema` accepts.
+/// Unions are unwrapped, enums become their symbols and bytes become ISO-8859-1 strings.
+pub fn to_json(value: &AvroValue) -> JsonValue {
+    match value {
+        AvroValue::Null => JsonValue::Null,
+        AvroValue::Boolean(b) => JsonValue::Bool(*b),
+        AvroValue::Int(n) => JsonValue::from(*n),
+        AvroValue::Long(n) => JsonValue::from(*n),
+        AvroValue::Float(n) => JsonValue::from(*n),
+        AvroValue::Double(n) => JsonValue::from(*n),
+        AvroValue::Bytes(bytes) | AvroValue::Fixed(_, bytes) => {
+            JsonValue::String(bytes.iter().map(|b| *b as char).collect())
+        }
+        AvroValue::String(s) => JsonValue::String(s.clone()),
+        AvroValue::Enum(_, symbol) => JsonValue::String(symbol.clone()),
+        AvroValue::Union(inner) => to_json(inner),
+        AvroValue::Array(items) => JsonValue::Array(items.iter().map(to_json).collect()),
+        AvroValue::Map(items) => JsonValue::Object(
+            items
+                .iter()
+                .map(|(key, value)| (key.clone(), to_json(value)))
+                .collect(),
+        ),
+        AvroValue::Record(fields) => JsonValue::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), to_json(value)))
+                .collect(),
+        ),
+        AvroValue::Date(n) | AvroValue::TimeMillis(n) => JsonValue::from(*n),
+        AvroValue::TimeMicros(n)
+        | AvroValue::TimestampMillis(n)
+        | AvroValue::TimestampMicros(n) => JsonValue::from(*n),
+        AvroValue::Uuid(uuid) => JsonValue::String(uuid.to_string()),
+        other => JsonValue::String(format!("{:?}", other)),
+    }
+}
+
+fn get_suitable_type_from_union<'s>(
     union_schema: &'s UnionSchema,
-    json_value: &'v JsonValue,
+    json_value: &JsonValue,
 ) -> Option<SchemaType<'s>> {
-    match json_value {
-        Value::Null => union_schema.find_schema(&AvroValue::Null).map(|(_, s)| s),
-        Value::Bool(_b) => union_schema
-            .find_schema(&AvroValue::Boolean(false))
-            .map(|(_, s)| s),
-        Value::Number(_n) => union_schema
-            .find_schema(&AvroValue::Long(0))
-            .or_else(|| union_schema.find_schema(&AvroValue::Int(0)))
-            .or_else(|| union_schema.find_schema(&AvroValue::Float(0f32)))
-            .or_else(|| union_schema.find_schema(&AvroValue::Double(0f64)))
-            .map(|(_, s)| s),
-        Value::String(ref _s) => union_schema
-            .find_schema(&AvroValue::String(String::new()))
-            .map(|(_, s)| s),
-        Value::Array(_items) => union_schema
-            .find_schema(&AvroValue::Array(Vec::new()))
-            .map(|(_, s)| s),
-        Value::Object(_map) => union_schema
-            .find_schema(&AvroValue::Record(Vec::new()))
-            .or_else(|| union_schema.find_schema(&AvroValue::Map(HashMap::new())))
-            .map(|(_, s)| s),
+    union_schema
+        .iter_variants()
+        .filter_map(|variant| union_preference(&variant, json_value).map(|p| (p, variant)))
+        .min_by_key(|(preference, _)| *preference)
+        .map(|(_, variant)| variant)
+}
+
+/// How well a union variant suits a JSON value. The lower, the better.
+fn union_preference(schema: &SchemaType, json_value: &JsonValue) -> Option<u8> {
+    match (schema, json_value) {
+        (SchemaType::Null, Value::Null) => Some(0),
+        (SchemaType::Boolean, Value::Bool(_)) => Some(0),
+        (SchemaType::Long, Value::Number(n)) if n.is_i64() => Some(0),
+        (SchemaType::Int, Value::Number(n)) if n.is_i64() => Some(1),
+        (SchemaType::Double, Value::Number(_)) => Some(2),
+        (SchemaType::Float, Value::Number(_)) => Some(3),
+        (SchemaType::String, Value::String(_)) => Some(0),
+        (SchemaType::Enum(enum_schema), Value::String(s)) => enum_schema
+            .iter_symbols()
+            .find(|symbol| *symbol == s)
+            .map(|_| 1),
+        (SchemaType::Array(_), Value::Array(_)) => Some(0),
+        (SchemaType::Record(_), Value::Object(_)) => Some(0),
+        (SchemaType::Map(_), Value::Object(_)) => Some(1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn map(json: JsonValue, raw_schema: &str) -> Result<AvroValue, CliError> {
+        let schema = parse_schema(raw_schema).unwrap();
+        map_with_schema(&json, &schema.root())
+    }
+
+    fn union(value: AvroValue) -> AvroValue {
+        AvroValue::Union(Box::new(value))
+    }
+
+    #[test]
+    fn promotes_integers_to_floating_point() {
+        assert_eq!(
+            map(json!(1), r#""double""#).unwrap(),
+            AvroValue::Double(1.0)
+        );
+        assert_eq!(map(json!(2), r#""float""#).unwrap(), AvroValue::Float(2.0));
+        assert_eq!(map(json!(3), r#""long""#).unwrap(), AvroValue::Long(3));
+        assert!(matches!(
+            map(json!(1.5), r#""long""#),
+            Err(CliError::Mapping(_, _))
+        ));
+        assert!(matches!(
+            map(json!(1.5), r#""int""#),
+            Err(CliError::Mapping(_, _))
+        ));
+    }
+
+    #[test]
+    fn maps_unions_with_the_best_variant() {
+        let numbers = r#"["null", "float", "double", "int", "long"]"#;
+        assert_eq!(map(json!(null), numbers).unwrap(), union(AvroValue::Null));
+        assert_eq!(map(json!(1), numbers).unwrap(), union(AvroValue::Long(1)));
+        assert_eq!(
+            map(json!(1.5), numbers).unwrap(),
+            union(AvroValue::Double(1.5))
+        );
+        assert_eq!(
+            map(json!(1), r#"["float", "int"]"#).unwrap(),
+            union(AvroValue::Int(1))
+        );
+
+        let strings = r#"["null", {"type": "enum", "name": "E", "symbols": ["A"]}, "string"]"#;
+        assert_eq!(
+            map(json!("A"), strings).unwrap(),
+            union(AvroValue::String("A".to_string()))
+        );
+        let symbols = r#"["null", {"type": "enum", "name": "E", "symbols": ["A"]}]"#;
+        assert_eq!(
+            map(json!("A"), symbols).unwrap(),
+            union(AvroValue::Enum(0, "A".to_string()))
+        );
+        assert!(matches!(
+            map(json!("B"), symbols),
+            Err(CliError::Mapping(_, _))
+        ));
+
+        let objects = r#"[{"type": "map", "values": "int"}, {"type": "record", "name": "R", "fields": [{"name": "a", "type": "int"}]}]"#;
+        assert_eq!(
+            map(json!({"a": 1}), objects).unwrap(),
+            union(AvroValue::Record(vec![(
+                "a".to_string(),
+                AvroValue::Int(1)
+            )]))
+        );
+    }
+
+    #[test]
+    fn maps_missing_fields_as_null() {
+        let record = r#"{"type": "record", "name": "R", "fields": [{"name": "a", "type": ["null", "int"]}]}"#;
+        assert_eq!(
+            map(json!({}), record).unwrap(),
+            AvroValue::Record(vec![("a".to_string(), union(AvroValue::Null))])
+        );
+
+        let required =
+            r#"{"type": "record", "name": "R", "fields": [{"name": "a", "type": "int"}]}"#;
+        assert!(matches!(
+            map(json!({}), required),
+            Err(CliError::Mapping(_, _))
+        ));
     }
 }
@@ -0,0 +1,253 @@
+use crate::named;
+
+use md5::{Digest, Md5};
+use serde_json::{Map, Value as JsonValue};
+use sha2::Sha256;
+
+/// Attributes go in this order, the rest follow alphabetically
+const ORDER: [&str; 7] = [
+    "name", "type", "fields", "symbols", "items", "values", "size",
+];
+const CRC_64_AVRO_EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+
+/// Parsing Canonical Form of a schema, as defined by the Avro specification
+pub fn canonical_form(schema: &JsonValue) -> String {
+    Form { strip: true }.walk(schema, None).to_string()
+}
+
+/// Schema with fullnames and a fixed order of attributes. Unlike the canonical form,
+/// docs, defaults, aliases and logical types are kept.
+pub fn normalize(schema: &JsonValue) -> JsonValue {
+    Form { strip: false }.walk(schema, None)
+}
+
+/// CRC-64-AVRO (Rabin) fingerprint
+pub fn fingerprint64(bytes: &[u8]) -> u64 {
+    let mut table = [0u64; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut fp = i as u64;
+        for _ in 0..8 {
+            fp = (fp >> 1) ^ (CRC_64_AVRO_EMPTY & 0u64.wrapping_sub(fp & 1));
+        }
+        *entry = fp;
+    }
+
+    bytes.iter().fold(CRC_64_AVRO_EMPTY, |fp, byte| {
+        (fp >> 8) ^ table[((fp ^ *byte as u64) & 0xff) as usize]
+    })
+}
+
+pub fn md5_fingerprint(bytes: &[u8]) -> String {
+    format!("{:x}", Md5::digest(bytes))
+}
+
+pub fn sha256_fingerprint(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+struct Form {
+    /// Keep only attributes of the canonical form
+    strip: bool,
+}
+
+impl Form {
+    fn walk(&self, schema: &JsonValue, namespace: Option<&str>) -> JsonValue {
+        match schema {
+            JsonValue::String(name) if named::is_keyword(name) => schema.clone(),
+            JsonValue::String(name) => JsonValue::String(named::fullname(name, namespace)),
+            JsonValue::Array(variants) => JsonValue::Array(
+                variants
+                    .iter()
+                    .map(|variant| self.walk(variant, namespace))
+                    .collect(),
+            ),
+            JsonValue::Object(object) => self.walk_object(object, namespace),
+            _ => schema.clone(),
+        }
+    }
+
+    fn walk_object(&self, object: &Map<String, JsonValue>, namespace: Option<&str>) -> JsonValue {
+        let (fullname, inner_namespace) = match named::definition_name(object, namespace) {
+            Some((fullname, inner_namespace)) => (Some(fullname), inner_namespace),
+            None => (None, namespace.map(|ns| ns.to_string())),
+        };
+        let inner_namespace = inner_namespace.as_deref();
+
+        let mut result = Map::new();
+        for (key, value) in object {
+            let value = match key.as_str() {
+                "name" => match fullname {
+                    Some(ref fullname) => JsonValue::String(fullname.clone()),
+                    None => value.clone(),
+                },
+                "namespace" => continue,
+                "type" | "items" | "values" => self.walk(value, inner_namespace),
+                "fields" => JsonValue::Array(
+                    value
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|field| self.walk_field(field, inner_namespace))
+                        .collect(),
+                ),
+                "symbols" | "size" => value.clone(),
+                _ if self.strip => continue,
+                _ => value.clone(),
+            };
+            result.insert(key.clone(), value);
+        }
+
+        // `{"type": T}` is just T
+        match (result.len(), result.get("type")) {
+            (1, Some(schema_type)) => schema_type.clone(),
+            _ => ordered(result),
+        }
+    }
+
+    fn walk_field(&self, field: &JsonValue, namespace: Option<&str>) -> JsonValue {
+        let object = match field.as_object() {
+            Some(object) => object,
+            None => return field.clone(),
+        };
+
+        let mut result = Map::new();
+        for (key, value) in object {
+            let value = match key.as_str() {
+                "name" => value.clone(),
+                "type" => self.walk(value, namespace),
+                _ if self.strip => continue,
+                _ => value.clone(),
+            };
+            result.insert(key.clone(), value);
+        }
+        ordered(result)
+    }
+}
+
+fn ordered(mut object: Map<String, JsonValue>) -> JsonValue {
+    let mut result = Map::new();
+    for key in ORDER.iter() {
+        if let Some(value) = object.remove(*key) {
+            result.insert(key.to_string(), value);
+        }
+    }
+
+    let mut rest: Vec<(String, JsonValue)> = object.into_iter().collect();
+    rest.sort_by(|(a, _), (b, _)| a.cmp(b));
+    result.extend(rest);
+    JsonValue::Object(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reduces_primitives_to_their_names() {
+        assert_eq!(canonical_form(&json!("int")), r#""int""#);
+        assert_eq!(canonical_form(&json!({"type": "int"})), r#""int""#);
+        assert_eq!(
+            canonical_form(&json!({"type": "long", "logicalType": "timestamp-millis"})),
+            r#""long""#
+        );
+        assert_eq!(
+            canonical_form(&json!(["null", {"type": "string"}])),
+            r#"["null","string"]"#
+        );
+    }
+
+    #[test]
+    fn folds_namespaces_into_fullnames() {
+        let schema = json!({
+            "type": "record",
+            "name": "R",
+            "namespace": "x.y",
+            "fields": [
+                {"name": "e", "type": {"type": "enum", "name": "E", "symbols": ["A"]}},
+                {"name": "f", "type": "E"},
+                {"name": "g", "type": {"type": "fixed", "name": "a.F", "size": 1}},
+                {"name": "h", "type": "a.F"}
+            ]
+        });
+        assert_eq!(
+            canonical_form(&schema),
+            concat!(
+                r#"{"name":"x.y.R","type":"record","fields":["#,
+                r#"{"name":"e","type":{"name":"x.y.E","type":"enum","symbols":["A"]}},"#,
+                r#"{"name":"f","type":"x.y.E"},"#,
+                r#"{"name":"g","type":{"name":"a.F","type":"fixed","size":1}},"#,
+                r#"{"name":"h","type":"a.F"}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn orders_and_strips_attributes() {
+        let schema = json!({
+            "fields": [{"default": 0, "type": "int", "doc": "count", "name": "n", "order": "descending"}],
+            "aliases": ["S"],
+            "doc": "stats",
+            "type": "record",
+            "name": "R"
+        });
+        assert_eq!(
+            canonical_form(&schema),
+            r#"{"name":"R","type":"record","fields":[{"name":"n","type":"int"}]}"#
+        );
+        assert_eq!(
+            canonical_form(&json!({"values": {"items": "int", "type": "array"}, "type": "map"})),
+            r#"{"type":"map","values":{"type":"array","items":"int"}}"#
+        );
+    }
+
+    #[test]
+    fn normalizes_without_stripping() {
+        let schema = json!({
+            "doc": "a flag",
+            "size": 1,
+            "type": "fixed",
+            "namespace": "n",
+            "name": "F",
+            "aliases": ["G"]
+        });
+        assert_eq!(
+            normalize(&schema).to_string(),
+            r#"{"name":"n.F","type":"fixed","size":1,"aliases":["G"],"doc":"a flag"}"#
+        );
+    }
+
+    #[test]
+    fn fingerprints_as_the_specification() {
+        let vectors: [(&str, i64); 11] = [
+            (r#""null""#, 7195948357588979594),
+            (r#""boolean""#, -6970731678124411036),
+            (r#""int""#, 8247732601305521295),
+            (r#""long""#, -3434872931120570953),
+            (r#""float""#, 5583340709985441680),
+            (r#""double""#, -8181574048448539266),
+            (r#""bytes""#, 5746618253357095269),
+            (r#""string""#, -8142146995180207161),
+            (r#"[]"#, -1241056759729112623),
+            (
+                r#"{"type":"fixed","name":"foo","size":15}"#,
+                1756455273707447556,
+            ),
+            (
+                r#"{"type":"enum","name":"foo","symbols":["A1"]}"#,
+                -6342190197741309591,
+            ),
+        ];
+
+        for (raw_schema, fingerprint) in vectors.iter() {
+            let schema: JsonValue = serde_json::from_str(raw_schema).unwrap();
+            let form = canonical_form(&schema);
+            assert_eq!(
+                fingerprint64(form.as_bytes()) as i64,
+                *fingerprint,
+                "{}",
+                raw_schema
+            );
+        }
+    }
+}
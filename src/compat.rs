@@ -0,0 +1,580 @@
+use crate::named;
+
+use serde_json::{Map, Value as JsonValue};
+use std::collections::{HashMap, HashSet};
+
+const PROMOTIONS: [(&str, &str); 8] = [
+    ("int", "long"),
+    ("int", "float"),
+    ("int", "double"),
+    ("long", "float"),
+    ("long", "double"),
+    ("float", "double"),
+    ("string", "bytes"),
+    ("bytes", "string"),
+];
+
+/// Compatibility level, as in schema-registry
+#[derive(Clone, Copy)]
+pub enum Level {
+    Backward,
+    Forward,
+    Full,
+    BackwardTransitive,
+    ForwardTransitive,
+    FullTransitive,
+}
+
+impl Level {
+    pub fn parse(raw: &str) -> Result<Level, String> {
+        match raw.to_lowercase().as_str() {
+            "backward" => Ok(Level::Backward),
+            "forward" => Ok(Level::Forward),
+            "full" => Ok(Level::Full),
+            "backward_transitive" => Ok(Level::BackwardTransitive),
+            "forward_transitive" => Ok(Level::ForwardTransitive),
+            "full_transitive" => Ok(Level::FullTransitive),
+            _ => Err(format!("unknown compatibility level `{}`", raw)),
+        }
+    }
+
+    fn is_transitive(self) -> bool {
+        matches!(
+            self,
+            Level::BackwardTransitive | Level::ForwardTransitive | Level::FullTransitive
+        )
+    }
+
+    /// New schema has to read data written with previous ones
+    fn is_backward(self) -> bool {
+        !matches!(self, Level::Forward | Level::ForwardTransitive)
+    }
+
+    /// Previous schemas have to read data written with the new one
+    fn is_forward(self) -> bool {
+        !matches!(self, Level::Backward | Level::BackwardTransitive)
+    }
+}
+
+pub struct Incompatibility {
+    /// Index of the previous schema the new one was checked against
+    pub previous: usize,
+    /// `backward` when the new schema cannot read, `forward` when previous one cannot
+    pub direction: &'static str,
+    /// Path in the reader schema, `$` is the root
+    pub path: String,
+    pub message: String,
+}
+
+/// Checks the new schema against previous ones, given from the oldest to the latest.
+/// Non-transitive levels check against the latest one only.
+pub fn check(previous: &[JsonValue], new: &JsonValue, level: Level) -> Vec<Incompatibility> {
+    let first = if level.is_transitive() {
+        0
+    } else {
+        previous.len().saturating_sub(1)
+    };
+
+    let mut incompatibilities = Vec::new();
+    for (idx, old) in previous.iter().enumerate().skip(first) {
+        if level.is_backward() {
+            incompatibilities.extend(can_read(new, old).into_iter().map(|(path, message)| {
+                Incompatibility {
+                    previous: idx,
+                    direction: "backward",
+                    path,
+                    message,
+                }
+            }));
+        }
+        if level.is_forward() {
+            incompatibilities.extend(can_read(old, new).into_iter().map(|(path, message)| {
+                Incompatibility {
+                    previous: idx,
+                    direction: "forward",
+                    path,
+                    message,
+                }
+            }));
+        }
+    }
+    incompatibilities
+}
+
+/// Paths and descriptions of what prevents the reader schema from reading data of the writer one
+fn can_read(reader: &JsonValue, writer: &JsonValue) -> Vec<(String, String)> {
+    let mut checker = Checker {
+        reader_types: HashMap::new(),
+        writer_types: HashMap::new(),
+        visited: HashSet::new(),
+        found: Vec::new(),
+    };
+    named::collect_definitions(reader, None, &mut checker.reader_types);
+    named::collect_definitions(writer, None, &mut checker.writer_types);
+
+    checker.check((reader, None), (writer, None), "$".to_string());
+    checker.found
+}
+
+/// Schema together with the namespace its names are resolved against
+type Scoped<'s> = (&'s JsonValue, Option<String>);
+
+struct Checker {
+    reader_types: HashMap<String, named::Definition>,
+    writer_types: HashMap<String, named::Definition>,
+    /// Pairs of named types being checked, so recursive types are checked once
+    visited: HashSet<(String, String)>,
+    found: Vec<(String, String)>,
+}
+
+impl Checker {
+    fn check(&mut self, reader: Scoped, writer: Scoped, path: String) {
+        let (reader, reader_ns) = resolve(reader, &self.reader_types);
+        let (writer, writer_ns) = resolve(writer, &self.writer_types);
+        let reader_kind = kind(&reader);
+        let writer_kind = kind(&writer);
+
+        match (reader_kind, writer_kind) {
+            // each writer variant has to be readable
+            ("union", "union") => {
+                for variant in writer.as_array().into_iter().flatten() {
+                    self.check_variant(
+                        (&reader, reader_ns.clone()),
+                        (variant, writer_ns.clone()),
+                        &path,
+                    );
+                }
+            }
+            (_, "union") => {
+                for variant in writer.as_array().into_iter().flatten() {
+                    self.check(
+                        (&reader, reader_ns.clone()),
+                        (variant, writer_ns.clone()),
+                        path.clone(),
+                    );
+                }
+            }
+            ("union", _) => self.check_variant((&reader, reader_ns), (&writer, writer_ns), &path),
+            ("record", "record") | ("error", "error") => {
+                self.check_record((&reader, reader_ns), (&writer, writer_ns), path)
+            }
+            ("enum", "enum") => self.check_enum(&reader, &writer, path),
+            ("fixed", "fixed") => {
+                self.check_name(&reader, &writer, &path);
+                if reader.get("size") != writer.get("size") {
+                    self.found
+                        .push((path, "fixed sizes are different".to_string()));
+                }
+            }
+            ("array", "array") => self.check(
+                (&reader["items"], reader_ns),
+                (&writer["items"], writer_ns),
+                format!("{}[]", path),
+            ),
+            ("map", "map") => self.check(
+                (&reader["values"], reader_ns),
+                (&writer["values"], writer_ns),
+                format!("{}{{}}", path),
+            ),
+            (reader_kind, writer_kind)
+                if reader_kind == writer_kind
+                    || PROMOTIONS.contains(&(writer_kind, reader_kind)) => {}
+            (reader_kind, writer_kind) => self.found.push((
+                path,
+                format!("{} cannot be read as {}", writer_kind, reader_kind),
+            )),
+        }
+    }
+
+    /// A writer type has to be readable by one of reader union variants
+    fn check_variant(&mut self, reader: Scoped, writer: Scoped, path: &str) {
+        let readable = reader.0.as_array().into_iter().flatten().any(|variant| {
+            let found = self.found.len();
+            self.check(
+                (variant, reader.1.clone()),
+                writer.clone(),
+                path.to_string(),
+            );
+            let is_readable = self.found.len() == found;
+            self.found.truncate(found);
+            is_readable
+        });
+
+        if !readable {
+            let (writer, _) = resolve(writer, &self.writer_types);
+            self.found.push((
+                path.to_string(),
+                format!("{} is not in the reader union", type_name(&writer)),
+            ));
+        }
+    }
+
+    fn check_record(&mut self, reader: Scoped, writer: Scoped, path: String) {
+        if !self.check_name(reader.0, writer.0, &path) {
+            return;
+        }
+
+        let key = (
+            named_fullname(reader.0, reader.1.as_deref()),
+            named_fullname(writer.0, writer.1.as_deref()),
+        );
+        if !self.visited.insert(key) {
+            return;
+        }
+
+        let reader_ns = inner_namespace(reader.0, reader.1.as_deref());
+        let writer_ns = inner_namespace(writer.0, writer.1.as_deref());
+        let writer_fields = fields(writer.0);
+
+        for reader_field in fields(reader.0) {
+            let name = reader_field
+                .get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or_default();
+            let field_path = format!("{}.{}", path, name);
+
+            let writer_field = writer_fields.iter().find(|writer_field| {
+                let writer_name = writer_field.get("name").and_then(|n| n.as_str());
+                writer_name == Some(name)
+                    || matches!(writer_name, Some(writer_name) if aliases(reader_field).contains(&writer_name))
+            });
+
+            match writer_field {
+                Some(writer_field) => self.check(
+                    (&reader_field["type"], reader_ns.clone()),
+                    (&writer_field["type"], writer_ns.clone()),
+                    field_path,
+                ),
+                None if reader_field.contains_key("default") => {}
+                None => self.found.push((
+                    field_path,
+                    "field is missing in the writer schema and has no default".to_string(),
+                )),
+            }
+        }
+    }
+
+    fn check_enum(&mut self, reader: &JsonValue, writer: &JsonValue, path: String) {
+        if !self.check_name(reader, writer, &path) || reader.get("default").is_some() {
+            return;
+        }
+
+        let reader_symbols = symbols(reader);
+        let missing: Vec<&str> = symbols(writer)
+            .into_iter()
+            .filter(|symbol| !reader_symbols.contains(symbol))
+            .collect();
+
+        if !missing.is_empty() {
+            self.found.push((
+                path,
+                format!(
+                    "symbols [{}] are missing in the reader enum, which has no default",
+                    missing.join(", ")
+                ),
+            ));
+        }
+    }
+
+    /// Named types match by unqualified name or by an alias of the reader
+    fn check_name(&mut self, reader: &JsonValue, writer: &JsonValue, path: &str) -> bool {
+        let reader_name = short_name(reader);
+        let writer_name = short_name(writer);
+        let writer_fullname = writer
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or_default();
+
+        let reader_aliases = reader.as_object().map(aliases).unwrap_or_default();
+        let matches = reader_name == writer_name
+            || reader_aliases.contains(&writer_name)
+            || reader_aliases.contains(&writer_fullname);
+
+        if !matches {
+            self.found.push((
+                path.to_string(),
+                format!("{} cannot be read as {}", writer_name, reader_name),
+            ));
+        }
+        matches
+    }
+}
+
+/// Follows named type references and `{"type": ...}` wrappers.
+/// Returns the schema and the namespace of the names inside it.
+fn resolve(
+    (schema, namespace): Scoped,
+    types: &HashMap<String, named::Definition>,
+) -> (JsonValue, Option<String>) {
+    match schema {
+        JsonValue::String(name) if !named::is_keyword(name) => {
+            match named::lookup(name, namespace.as_deref(), types) {
+                Some((_, (definition, namespace))) => (definition.clone(), namespace.clone()),
+                None => (schema.clone(), namespace),
+            }
+        }
+        JsonValue::Object(object) => match object.get("type") {
+            Some(inner @ JsonValue::String(name))
+                if !matches!(
+                    name.as_str(),
+                    "record" | "error" | "enum" | "fixed" | "array" | "map"
+                ) =>
+            {
+                resolve((inner, namespace), types)
+            }
+            _ => (schema.clone(), namespace),
+        },
+        _ => (schema.clone(), namespace),
+    }
+}
+
+fn kind(schema: &JsonValue) -> &str {
+    match schema {
+        JsonValue::String(name) => name,
+        JsonValue::Array(_) => "union",
+        JsonValue::Object(object) => object
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default(),
+        _ => "",
+    }
+}
+
+fn type_name(schema: &JsonValue) -> String {
+    match schema.get("name").and_then(|n| n.as_str()) {
+        Some(name) => name.to_string(),
+        None => kind(schema).to_string(),
+    }
+}
+
+fn short_name(schema: &JsonValue) -> &str {
+    let name = schema
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or_default();
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+fn named_fullname(schema: &JsonValue, namespace: Option<&str>) -> String {
+    schema
+        .as_object()
+        .and_then(|object| named::definition_name(object, namespace))
+        .map(|(fullname, _)| fullname)
+        .unwrap_or_default()
+}
+
+fn inner_namespace(schema: &JsonValue, namespace: Option<&str>) -> Option<String> {
+    schema
+        .as_object()
+        .and_then(|object| named::definition_name(object, namespace))
+        .and_then(|(_, namespace)| namespace)
+}
+
+fn fields(schema: &JsonValue) -> Vec<&Map<String, JsonValue>> {
+    schema
+        .get("fields")
+        .and_then(|f| f.as_array())
+        .map(|fields| fields.iter().filter_map(|f| f.as_object()).collect())
+        .unwrap_or_default()
+}
+
+fn aliases(object: &Map<String, JsonValue>) -> Vec<&str> {
+    object
+        .get("aliases")
+        .and_then(|a| a.as_array())
+        .map(|aliases| aliases.iter().filter_map(|a| a.as_str()).collect())
+        .unwrap_or_default()
+}
+
+fn symbols(schema: &JsonValue) -> Vec<&str> {
+    schema
+        .get("symbols")
+        .and_then(|s| s.as_array())
+        .map(|symbols| symbols.iter().filter_map(|s| s.as_str()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Index, direction and path of each incompatibility
+    fn incompatibilities(
+        previous: &[&JsonValue],
+        new: JsonValue,
+        level: &str,
+    ) -> Vec<(usize, &'static str, String)> {
+        let previous: Vec<JsonValue> = previous.iter().map(|&schema| schema.clone()).collect();
+        check(&previous, &new, Level::parse(level).unwrap())
+            .into_iter()
+            .map(|i| (i.previous, i.direction, i.path))
+            .collect()
+    }
+
+    fn record(fields: JsonValue) -> JsonValue {
+        json!({"type": "record", "name": "User", "fields": fields})
+    }
+
+    #[test]
+    fn needs_defaults_of_added_fields() {
+        let old = record(json!([{"name": "name", "type": "string"}]));
+        let without_default = record(json!([
+            {"name": "name", "type": "string"},
+            {"name": "age", "type": "int"}
+        ]));
+        let with_default = record(json!([
+            {"name": "name", "type": "string"},
+            {"name": "age", "type": "int", "default": 0}
+        ]));
+
+        assert_eq!(
+            incompatibilities(&[&old], without_default.clone(), "backward"),
+            vec![(0, "backward", "$.age".to_string())]
+        );
+        assert!(incompatibilities(&[&old], without_default, "forward").is_empty());
+        assert!(incompatibilities(&[&old], with_default, "full").is_empty());
+    }
+
+    #[test]
+    fn needs_defaults_of_removed_fields() {
+        let old = record(json!([
+            {"name": "name", "type": "string"},
+            {"name": "age", "type": "int"}
+        ]));
+        let new = record(json!([{"name": "name", "type": "string"}]));
+
+        assert!(incompatibilities(&[&old], new.clone(), "backward").is_empty());
+        assert_eq!(
+            incompatibilities(&[&old], new, "forward"),
+            vec![(0, "forward", "$.age".to_string())]
+        );
+    }
+
+    #[test]
+    fn promotes_primitives() {
+        assert!(incompatibilities(&[&json!("int")], json!("long"), "backward").is_empty());
+        assert!(incompatibilities(&[&json!("float")], json!("double"), "backward").is_empty());
+        assert!(incompatibilities(&[&json!("string")], json!("bytes"), "full").is_empty());
+        assert_eq!(
+            incompatibilities(&[&json!("long")], json!("int"), "backward"),
+            vec![(0, "backward", "$".to_string())]
+        );
+        assert_eq!(
+            incompatibilities(&[&json!("int")], json!("long"), "forward"),
+            vec![(0, "forward", "$".to_string())]
+        );
+    }
+
+    #[test]
+    fn needs_removed_symbols_or_a_default() {
+        let old = json!({"type": "enum", "name": "Status", "symbols": ["NEW", "PAID"]});
+        let new = json!({"type": "enum", "name": "Status", "symbols": ["NEW"]});
+        let new_with_default =
+            json!({"type": "enum", "name": "Status", "symbols": ["NEW"], "default": "NEW"});
+
+        assert_eq!(
+            incompatibilities(&[&old], new.clone(), "backward"),
+            vec![(0, "backward", "$".to_string())]
+        );
+        assert!(incompatibilities(&[&old], new, "forward").is_empty());
+        assert!(incompatibilities(&[&old], new_with_default, "backward").is_empty());
+    }
+
+    #[test]
+    fn reads_unions() {
+        // a union reader reads the writer type with one of its variants
+        assert!(
+            incompatibilities(&[&json!("int")], json!(["null", "long"]), "backward").is_empty()
+        );
+        assert_eq!(
+            incompatibilities(&[&json!("int")], json!(["null", "string"]), "backward"),
+            vec![(0, "backward", "$".to_string())]
+        );
+
+        // each variant of a union writer has to be readable
+        assert!(incompatibilities(&[&json!(["int"])], json!("long"), "backward").is_empty());
+        assert_eq!(
+            incompatibilities(&[&json!(["null", "int"])], json!("int"), "backward"),
+            vec![(0, "backward", "$".to_string())]
+        );
+
+        assert!(incompatibilities(
+            &[&json!(["null", "int"])],
+            json!(["null", "int", "string"]),
+            "backward"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn matches_aliases() {
+        let old = json!({"type": "record", "name": "Person", "fields": [
+            {"name": "name", "type": "string"}
+        ]});
+        let new = record(json!([
+            {"name": "full_name", "type": "string", "aliases": ["name"]}
+        ]));
+        let aliased = json!({"type": "record", "name": "User", "aliases": ["Person"], "fields": [
+            {"name": "full_name", "type": "string", "aliases": ["name"]}
+        ]});
+
+        assert_eq!(
+            incompatibilities(&[&old], new, "backward"),
+            vec![(0, "backward", "$".to_string())]
+        );
+        assert!(incompatibilities(&[&old], aliased, "backward").is_empty());
+    }
+
+    #[test]
+    fn checks_recursive_records() {
+        let list = |value: &str, extra: Option<JsonValue>| {
+            let mut fields = vec![
+                json!({"name": "value", "type": value}),
+                json!({"name": "next", "type": ["null", "Node"]}),
+            ];
+            fields.extend(extra);
+            json!({"type": "record", "name": "Node", "fields": fields})
+        };
+        let old = list("int", None);
+
+        assert!(incompatibilities(
+            &[&old],
+            list(
+                "long",
+                Some(json!({"name": "tag", "type": "string", "default": ""}))
+            ),
+            "backward"
+        )
+        .is_empty());
+        assert_eq!(
+            incompatibilities(&[&old], list("string", None), "backward"),
+            vec![(0, "backward", "$.value".to_string())]
+        );
+    }
+
+    #[test]
+    fn checks_previous_versions_if_transitive() {
+        let v1 = record(json!([{"name": "name", "type": "string"}]));
+        let v2 = record(json!([
+            {"name": "name", "type": "string"},
+            {"name": "age", "type": "int", "default": 0}
+        ]));
+        let v3 = record(json!([
+            {"name": "name", "type": "string"},
+            {"name": "age", "type": "int"}
+        ]));
+        let previous = [&v1, &v2];
+
+        assert!(incompatibilities(&previous, v3.clone(), "backward").is_empty());
+        assert_eq!(
+            incompatibilities(&previous, v3.clone(), "backward_transitive"),
+            vec![(0, "backward", "$.age".to_string())]
+        );
+        assert!(incompatibilities(&previous, v3.clone(), "full").is_empty());
+        assert_eq!(
+            incompatibilities(&previous, v3, "FULL_TRANSITIVE"),
+            vec![(0, "backward", "$.age".to_string())]
+        );
+    }
+}
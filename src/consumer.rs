@@ -0,0 +1,154 @@
+use crate::producer::build_kafka_config;
+use crate::ssl::SslConfig;
+
+use rdkafka::consumer::{BaseConsumer, Consumer as _};
+use rdkafka::error::KafkaError;
+use rdkafka::{Message, Offset, TopicPartitionList};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const CONSUMER_GROUP_ID: &str = "kafka-avro-cli";
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Brokers, the topic, the range of offsets and client settings of a consumer
+#[derive(Clone)]
+pub struct ConsumerConfig {
+    hosts: String,
+    topic: String,
+    ssl: Option<SslConfig>,
+    partition: Option<i32>,
+    start_offset: Option<i64>,
+    end_offset: Option<i64>,
+    /// librdkafka properties, set over the ones derived from other settings
+    properties: Vec<(String, String)>,
+}
+
+impl ConsumerConfig {
+    /// Hosts are `host:port[,host:port[...]]`
+    pub fn new(hosts: &str, topic: &str) -> ConsumerConfig {
+        ConsumerConfig {
+            hosts: hosts.to_string(),
+            topic: topic.to_string(),
+            ssl: None,
+            partition: None,
+            start_offset: None,
+            end_offset: None,
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn ssl(mut self, ssl: SslConfig) -> ConsumerConfig {
+        self.ssl = Some(ssl);
+        self
+    }
+
+    /// Reads the partition only, all partitions of the topic by default
+    pub fn partition(mut self, partition: i32) -> ConsumerConfig {
+        self.partition = Some(partition);
+        self
+    }
+
+    /// Inclusive start offset, the beginning of partitions by default
+    pub fn start_offset(mut self, offset: i64) -> ConsumerConfig {
+        self.start_offset = Some(offset);
+        self
+    }
+
+    /// Exclusive end offset, the end of partitions by default
+    pub fn end_offset(mut self, offset: i64) -> ConsumerConfig {
+        self.end_offset = Some(offset);
+        self
+    }
+
+    /// Sets a librdkafka property, e.g. `fetch.max.bytes`
+    pub fn set(mut self, key: &str, value: &str) -> ConsumerConfig {
+        self.properties.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Reads a range of offsets of the topic partitions.
+/// The range ends at the end of partitions at the moment the consumer is created.
+pub struct Consumer {
+    consumer: BaseConsumer,
+    /// Exclusive end offset of each partition not read till the end yet
+    remaining: HashMap<i32, i64>,
+}
+
+impl Consumer {
+    pub fn new(config: ConsumerConfig) -> Result<Consumer, KafkaError> {
+        let topic = config.topic.as_str();
+
+        //config
+        let mut client_cfg = build_kafka_config(&config.hosts, config.ssl.as_ref());
+        client_cfg.set("group.id", CONSUMER_GROUP_ID);
+        client_cfg.set("enable.auto.commit", "false");
+        client_cfg.set("enable.partition.eof", "true");
+        for (key, value) in &config.properties {
+            client_cfg.set(key, value);
+        }
+
+        //consumer
+        let consumer: BaseConsumer = client_cfg.create()?;
+
+        let partitions: Vec<i32> = match config.partition {
+            Some(partition) => vec![partition],
+            None => consumer
+                .fetch_metadata(Some(topic), METADATA_TIMEOUT)?
+                .topics()
+                .iter()
+                .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+                .collect(),
+        };
+
+        // assign partitions which have something in the range
+        let mut assignment = TopicPartitionList::new();
+        let mut remaining = HashMap::new();
+        for partition in partitions {
+            let (low, high) = consumer.fetch_watermarks(topic, partition, METADATA_TIMEOUT)?;
+            let start = config.start_offset.map_or(low, |from| from.max(low));
+            let end = config.end_offset.map_or(high, |to| to.min(high));
+
+            if start < end {
+                assignment.add_partition_offset(topic, partition, Offset::Offset(start))?;
+                remaining.insert(partition, end);
+            }
+        }
+        consumer.assign(&assignment)?;
+
+        Ok(Consumer {
+            consumer,
+            remaining,
+        })
+    }
+
+    /// Returns the payload of the next message in the range, or `None` when the range is over.
+    /// Messages without payload are skipped.
+    pub fn next_payload(&mut self) -> Result<Option<Vec<u8>>, KafkaError> {
+        while !self.remaining.is_empty() {
+            let message = match self.consumer.poll(POLL_TIMEOUT) {
+                None => continue,
+                Some(Err(KafkaError::PartitionEOF(partition))) => {
+                    self.remaining.remove(&partition);
+                    continue;
+                }
+                Some(message) => message?,
+            };
+
+            let end = match self.remaining.get(&message.partition()) {
+                Some(end) => *end,
+                None => continue,
+            };
+            if message.offset() + 1 >= end {
+                self.remaining.remove(&message.partition());
+            }
+
+            if let (true, Some(payload)) = (message.offset() < end, message.payload()) {
+                return Ok(Some(payload.to_vec()));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -1,115 +1,860 @@
+use crate::admin::TopicConfig;
+use crate::avro;
+use crate::compat::Level;
+use crate::consumer::ConsumerConfig;
+use crate::encoder::Format;
 use crate::error::CliError;
+use crate::generate::{parse_field_generator, FieldGenerator};
+use crate::idl;
+use crate::ocf::parse_codec;
+use crate::producer::ProducerConfig;
+use crate::registry::{parse_reference, RegistryConfig, RegistryType, SchemaId, SchemaReference};
+use crate::ssl::SslConfig;
+use crate::wire::WireFormat;
+use avro_rs::Codec;
 use clap::ArgMatches;
-use std::fs::read_to_string;
-
-pub struct SslCtx {
-    pub enabled: bool,
-    pub cert_validate: bool,
-    pub host_validate: bool,
-    pub ca_location: Option<String>,
-    pub keystore_location: Option<String>,
-    pub keystore_password: Option<String>,
-}
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub struct KafkaCtx {
-    pub hosts: String,
-    pub topic: String,
+    pub hosts: Option<String>,
+    pub topic: Option<String>,
 }
 
 pub struct AvroCtx {
     pub registry_url: Option<String>,
+    pub registry_type: RegistryType,
+    /// Apicurio artifact group or Glue registry name
+    pub registry_group: Option<String>,
+    /// User and password
+    pub registry_basic_auth: Option<(String, String)>,
+    pub registry_connect_timeout: Option<Duration>,
+    pub registry_read_timeout: Option<Duration>,
+    pub registry_retries: Option<u32>,
     pub schema: Option<String>,
+    pub references: Vec<SchemaReference>,
+    /// Register the normalized schema
+    pub normalize: bool,
+    pub wire_format: WireFormat,
+    /// Message of a Protobuf schema, the first one if not present
+    pub record: Option<String>,
+}
+
+/// How many times the payload is sent
+pub enum Repeat {
+    Times(u64),
+    Forever,
+    For(Duration),
+}
+
+impl Repeat {
+    /// Whether one more pass over the payload should be made
+    pub fn is_due(&self, passes: u64, elapsed: Duration) -> bool {
+        match self {
+            Repeat::Times(times) => passes < *times,
+            Repeat::Forever => true,
+            Repeat::For(duration) => elapsed < *duration,
+        }
+    }
+}
+
+pub struct GenerateCtx {
+    pub count: u64,
+    pub seed: Option<u64>,
+    pub generators: HashMap<String, FieldGenerator>,
+}
+
+pub struct ServeCtx {
+    pub listen: String,
+    pub dir: Option<String>,
+    /// Users of the basic authentication, anyone is served if there are none
+    pub basic_auth: Vec<(String, String)>,
+    /// Users who may only read schemas and settings
+    pub read_only_basic_auth: Vec<(String, String)>,
+}
+
+/// Range of the topic to read and where to write it
+pub struct ConsumeCtx {
+    pub partition: Option<i32>,
+    /// Inclusive start offset, the beginning of partitions by default
+    pub from: Option<i64>,
+    /// Exclusive end offset, the end of partitions by default
+    pub to: Option<i64>,
+    /// Avro Object Container File path. Records are printed as JSON if not present
+    pub output: Option<String>,
+    pub codec: Codec,
+    /// Schema records are resolved to
+    pub reader_schema: Option<String>,
+    /// Local writer schemas of single-object encoded and raw messages
+    pub writer_schemas: Vec<String>,
+    /// Wire format of messages, detected by their headers if not present
+    pub wire_format: Option<WireFormat>,
+}
+
+pub struct InferCtx {
+    pub name: String,
+    pub namespace: Option<String>,
+}
+
+/// Schemas to check, from the oldest to the new one
+pub struct CompatCtx {
+    /// Paths and raw schemas
+    pub schemas: Vec<(String, String)>,
+    pub level: Level,
+}
+
+pub enum SchemaCmd {
+    Infer(InferCtx),
+    /// Prints canonical form, fingerprints and normalized form
+    Describe,
+    Compat(CompatCtx),
+}
+
+pub enum RegistryCmd {
+    Subjects,
+    Versions {
+        subject: String,
+    },
+    Get {
+        subject: String,
+        version: String,
+    },
+    GetById(SchemaId),
+    Delete {
+        subject: String,
+        version: Option<String>,
+        permanent: bool,
+    },
+    /// Prints or sets a compatibility level
+    Compatibility {
+        subject: Option<String>,
+        level: Option<String>,
+    },
+    /// Prints or sets a mode
+    Mode {
+        subject: Option<String>,
+        mode: Option<String>,
+        force: bool,
+    },
+}
+
+/// Topics management. Commands other than listing act on the topic of `KafkaCtx`
+pub enum TopicCmd {
+    List,
+    Describe,
+    Create(TopicConfig),
+    Delete,
+    /// Sets and deletes config overrides
+    AlterConfig {
+        set: Vec<(String, String)>,
+        delete: Vec<String>,
+    },
 }
 
 pub enum AppCmd {
     Produce,
-    Consume,
+    Generate(GenerateCtx),
+    Schema(SchemaCmd),
+    Registry(RegistryCmd),
+    /// Runs a registry serving the Confluent API, it needs no registry url
+    ServeRegistry(ServeCtx),
+    Topic(TopicCmd),
+    Consume(ConsumeCtx),
 }
 
 pub struct AppCtx {
     pub command: AppCmd,
-    pub is_avro: bool,
+    /// Format of the payload, plain text if not present
+    pub format: Option<Format>,
+    /// Payload is an Avro Object Container File
+    pub is_ocf: bool,
     pub payload: Option<String>,
     pub payload_file: Option<String>,
+    pub repeat: Repeat,
     pub kafka_ctx: KafkaCtx,
     pub avro_ctx: AvroCtx,
-    pub ssl: SslCtx,
+    /// TLS of brokers, plain connections if not present
+    pub ssl: Option<SslConfig>,
+    /// TLS settings of the schema registry
+    pub registry_ssl: Option<SslConfig>,
+    /// Topic created before producing if missing
+    pub create_topic: Option<TopicConfig>,
+}
+
+impl AppCtx {
+    /// Client settings of the schema registry, if there is one
+    pub fn registry_config(&self) -> Option<RegistryConfig> {
+        let avro_ctx = &self.avro_ctx;
+        let mut config = RegistryConfig::new(avro_ctx.registry_url.as_ref()?)
+            .registry_type(avro_ctx.registry_type);
+        if let Some(ref group) = avro_ctx.registry_group {
+            config = config.group(group);
+        }
+        if let Some((ref user, ref password)) = avro_ctx.registry_basic_auth {
+            config = config.basic_auth(user, password);
+        }
+        if let Some(timeout) = avro_ctx.registry_connect_timeout {
+            config = config.connect_timeout(timeout);
+        }
+        if let Some(timeout) = avro_ctx.registry_read_timeout {
+            config = config.read_timeout(timeout);
+        }
+        if let Some(retries) = avro_ctx.registry_retries {
+            config = config.retries(retries);
+        }
+        if let Some(ref ssl) = self.registry_ssl {
+            config = config.ssl(ssl.clone());
+        }
+        Some(config)
+    }
+
+    pub fn consumer_config(&self, consume_ctx: &ConsumeCtx) -> Result<ConsumerConfig, CliError> {
+        let hosts = self.kafka_ctx.hosts.as_ref();
+        let topic = self.kafka_ctx.topic.as_ref();
+        let mut config = match (hosts, topic) {
+            (Some(hosts), Some(topic)) => ConsumerConfig::new(hosts, topic),
+            _ => return Err(CliError::Usage("--hosts and --topic expected".to_string())),
+        };
+        if let Some(ref ssl) = self.ssl {
+            config = config.ssl(ssl.clone());
+        }
+        if let Some(partition) = consume_ctx.partition {
+            config = config.partition(partition);
+        }
+        if let Some(from) = consume_ctx.from {
+            config = config.start_offset(from);
+        }
+        if let Some(to) = consume_ctx.to {
+            config = config.end_offset(to);
+        }
+        Ok(config)
+    }
+
+    pub fn producer_config(&self) -> Result<ProducerConfig, CliError> {
+        let hosts = self.kafka_ctx.hosts.as_ref();
+        let topic = self.kafka_ctx.topic.as_ref();
+        let config = match (hosts, topic) {
+            (Some(hosts), Some(topic)) => ProducerConfig::new(hosts, topic),
+            _ => return Err(CliError::Usage("--hosts and --topic expected".to_string())),
+        };
+        Ok(match self.ssl {
+            Some(ref ssl) => config.ssl(ssl.clone()),
+            None => config,
+        })
+    }
 }
 
 pub fn parse_app_ctx(arg_matches: &ArgMatches) -> Result<AppCtx, CliError> {
     // parse command
-    let (subcommand_args, command) = arg_matches
-        .subcommand_matches("produce")
-        .map(|a| (a, AppCmd::Produce))
-        .or_else(|| {
-            arg_matches
-                .subcommand_matches("consume")
-                .map(|a| (a, AppCmd::Consume))
-        })
-        .expect("subcommand expected");
-
-    let hosts: String = subcommand_args
-        .value_of("hosts")
-        .expect("hosts expected")
-        .to_owned();
-    let topic: String = subcommand_args
-        .value_of("topic")
-        .expect("topic expected")
-        .to_owned();
-    let is_json = !subcommand_args.is_present("text");
+    let (subcommand_args, command) = match arg_matches.subcommand() {
+        Some(("produce", args)) => (args, AppCmd::Produce),
+        Some(("generate", args)) => (args, AppCmd::Generate(parse_generate_ctx(args))),
+        Some(("schema", args)) => match args.subcommand() {
+            Some(("infer", args)) => (
+                args,
+                AppCmd::Schema(SchemaCmd::Infer(parse_infer_ctx(args))),
+            ),
+            Some(("describe", args)) => (args, AppCmd::Schema(SchemaCmd::Describe)),
+            Some(("compat", args)) => (
+                args,
+                AppCmd::Schema(SchemaCmd::Compat(parse_compat_ctx(args)?)),
+            ),
+            _ => panic!("schema subcommand expected"),
+        },
+        Some(("registry", args)) => match args.subcommand() {
+            Some(("serve", args)) => (args, AppCmd::ServeRegistry(parse_serve_ctx(args))),
+            _ => {
+                let (args, registry_cmd) = parse_registry_cmd(args);
+                (args, AppCmd::Registry(registry_cmd))
+            }
+        },
+        Some(("topic", args)) => {
+            let (args, topic_cmd) = parse_topic_cmd(args);
+            (args, AppCmd::Topic(topic_cmd))
+        }
+        Some(("consume", args)) => (args, AppCmd::Consume(parse_consume_ctx(args)?)),
+        _ => panic!("subcommand expected"),
+    };
+
+    let hosts = subcommand_args.value_of("hosts").map(|s| s.to_owned());
+    let topic = subcommand_args.value_of("topic").map(|s| s.to_owned());
+    let format = if subcommand_args.is_present("text") {
+        None
+    } else {
+        Some(
+            subcommand_args
+                .value_of("format")
+                .map(|format| Format::parse(format).expect("valid format expected"))
+                .unwrap_or(Format::Avro),
+        )
+    };
 
     let payload = subcommand_args.value_of("payload").map(|s| s.to_owned());
     let payload_file = subcommand_args
         .value_of("payload-file")
         .map(|s| s.to_owned());
-    if payload.is_none() && payload_file.is_none() {
-        panic!("payload expected")
+    if matches!(command, AppCmd::Produce) && payload.is_none() && payload_file.is_none() {
+        return Err(CliError::Usage(
+            "--payload or --payload-file expected".to_string(),
+        ));
     }
 
-    let ssl = parse_ssl_ctx(subcommand_args)?;
+    let is_ocf = match subcommand_args.value_of("input-format") {
+        Some(format) => format == "ocf",
+        // `--text` payload is sent as is and JSON payload is mapped, whatever the file is
+        None => {
+            format == Some(Format::Avro)
+                && matches!(&payload_file, Some(path) if path.ends_with(".avro"))
+        }
+    };
 
-    parse_avro_ctx(subcommand_args).map(|avro_ctx| AppCtx {
+    if is_ocf && matches!(format, Some(Format::JsonSchema) | Some(Format::Protobuf)) {
+        return Err(CliError::Usage(
+            "Object Container File payload is Avro".to_string(),
+        ));
+    }
+
+    let repeat = parse_repeat(subcommand_args);
+    let ssl = parse_ssl_config(subcommand_args);
+    let registry_ssl = parse_registry_ssl_config(subcommand_args).or_else(|| ssl.clone());
+    let avro_ctx = parse_avro_ctx(subcommand_args, format)?;
+    let create_topic = topic
+        .as_ref()
+        .filter(|_| subcommand_args.is_present("create-topic"))
+        .map(|topic| parse_topic_config(subcommand_args, topic));
+
+    // JSON payload is mapped with a schema, OCF payload has its own
+    if matches!(command, AppCmd::Produce)
+        && format.is_some()
+        && !is_ocf
+        && avro_ctx.schema.is_none()
+        && avro_ctx.registry_url.is_none()
+    {
+        return Err(CliError::Usage(
+            "one of --schema, --schema-file, --schema-dir or --registry-url expected".to_string(),
+        ));
+    }
+
+    Ok(AppCtx {
         command,
-        is_avro: is_json,
+        format,
+        is_ocf,
         payload,
         payload_file,
+        repeat,
         kafka_ctx: KafkaCtx { hosts, topic },
         avro_ctx,
         ssl,
+        registry_ssl,
+        create_topic,
+    })
+}
+
+fn parse_generate_ctx(arg_matches: &ArgMatches) -> GenerateCtx {
+    let generators = arg_matches
+        .values_of("generator")
+        .map(|values| {
+            values
+                .map(|raw| parse_field_generator(raw).expect("valid generator expected"))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    GenerateCtx {
+        count: arg_matches
+            .value_of("count")
+            .map(|count| count.parse().expect("valid number expected"))
+            .unwrap_or(1),
+        seed: arg_matches
+            .value_of("seed")
+            .map(|seed| seed.parse().expect("valid number expected")),
+        generators,
+    }
+}
+
+fn parse_consume_ctx(arg_matches: &ArgMatches) -> Result<ConsumeCtx, CliError> {
+    let offset = |name: &str| {
+        arg_matches
+            .value_of(name)
+            .map(|offset| offset.parse().expect("valid offset expected"))
+    };
+
+    let reader_schema = match (
+        arg_matches.value_of("reader-schema"),
+        arg_matches.value_of("reader-schema-file"),
+    ) {
+        (Some(raw_schema), _) => Some(raw_schema.to_owned()),
+        (None, Some(path)) => avro::combine_schemas(
+            &read_schema_file(Path::new(path))?,
+            arg_matches.value_of("record"),
+        )?,
+        (None, None) => None,
+    };
+
+    let writer_schemas = arg_matches
+        .values_of("writer-schema-file")
+        .map(|paths| {
+            paths
+                .map(|path| {
+                    avro::combine_schemas(&read_schema_file(Path::new(path))?, None)?
+                        .ok_or_else(|| no_schema(path))
+                })
+                .collect::<Result<Vec<String>, CliError>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(ConsumeCtx {
+        partition: arg_matches
+            .value_of("partition")
+            .map(|partition| partition.parse().expect("valid partition expected")),
+        from: offset("from"),
+        to: offset("to"),
+        output: arg_matches.value_of("output").map(|s| s.to_owned()),
+        codec: arg_matches
+            .value_of("codec")
+            .map(|codec| parse_codec(codec).expect("valid codec expected"))
+            .unwrap_or(Codec::Null),
+        reader_schema,
+        writer_schemas,
+        wire_format: arg_matches
+            .value_of("wire-format")
+            .map(|format| WireFormat::parse(format).expect("valid wire format expected")),
     })
 }
 
-fn parse_avro_ctx(arg_matches: &ArgMatches) -> Result<AvroCtx, CliError> {
+fn parse_infer_ctx(arg_matches: &ArgMatches) -> InferCtx {
+    InferCtx {
+        name: arg_matches
+            .value_of("name")
+            .expect("name expected")
+            .to_owned(),
+        namespace: arg_matches.value_of("namespace").map(|s| s.to_owned()),
+    }
+}
+
+fn parse_compat_ctx(arg_matches: &ArgMatches) -> Result<CompatCtx, CliError> {
+    let schemas = arg_matches
+        .values_of("schemas")
+        .expect("schemas expected")
+        .map(|path| {
+            let raw_schema = avro::combine_schemas(&read_schema_file(Path::new(path))?, None)?
+                .ok_or_else(|| no_schema(path))?;
+            Ok((path.to_owned(), raw_schema))
+        })
+        .collect::<Result<Vec<(String, String)>, CliError>>()?;
+
+    Ok(CompatCtx {
+        schemas,
+        level: arg_matches
+            .value_of("level")
+            .map(|level| Level::parse(level).expect("valid level expected"))
+            .unwrap_or(Level::Backward),
+    })
+}
+
+fn parse_serve_ctx(arg_matches: &ArgMatches) -> ServeCtx {
+    let users = |name| {
+        arg_matches
+            .values_of(name)
+            .map(|values| {
+                values
+                    .map(|raw| parse_basic_auth(raw).expect("valid user and password expected"))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    ServeCtx {
+        listen: arg_matches
+            .value_of("listen")
+            .expect("listen address expected")
+            .to_owned(),
+        dir: arg_matches.value_of("dir").map(|s| s.to_owned()),
+        basic_auth: users("basic-auth"),
+        read_only_basic_auth: users("read-only-basic-auth"),
+    }
+}
+
+fn parse_registry_cmd(arg_matches: &ArgMatches) -> (&ArgMatches, RegistryCmd) {
+    let subject = |args: &ArgMatches| {
+        args.value_of("subject")
+            .expect("subject expected")
+            .to_owned()
+    };
+
+    match arg_matches.subcommand() {
+        Some(("subjects", args)) => (args, RegistryCmd::Subjects),
+        Some(("versions", args)) => (
+            args,
+            RegistryCmd::Versions {
+                subject: subject(args),
+            },
+        ),
+        Some(("get", args)) => (
+            args,
+            RegistryCmd::Get {
+                subject: subject(args),
+                version: args
+                    .value_of("version")
+                    .expect("version expected")
+                    .to_owned(),
+            },
+        ),
+        Some(("get-id", args)) => (
+            args,
+            RegistryCmd::GetById(
+                SchemaId::parse(args.value_of("id").expect("id expected"))
+                    .expect("valid id expected"),
+            ),
+        ),
+        Some(("delete", args)) => (
+            args,
+            RegistryCmd::Delete {
+                subject: subject(args),
+                version: args.value_of("version").map(|s| s.to_owned()),
+                permanent: args.is_present("permanent"),
+            },
+        ),
+        Some(("config", args)) => (
+            args,
+            RegistryCmd::Compatibility {
+                subject: args.value_of("subject").map(|s| s.to_owned()),
+                level: args.value_of("set").map(|s| s.to_owned()),
+            },
+        ),
+        Some(("mode", args)) => (
+            args,
+            RegistryCmd::Mode {
+                subject: args.value_of("subject").map(|s| s.to_owned()),
+                mode: args.value_of("set").map(|s| s.to_owned()),
+                force: args.is_present("force"),
+            },
+        ),
+        _ => panic!("registry subcommand expected"),
+    }
+}
+
+fn parse_topic_cmd(arg_matches: &ArgMatches) -> (&ArgMatches, TopicCmd) {
+    match arg_matches.subcommand() {
+        Some(("list", args)) => (args, TopicCmd::List),
+        Some(("describe", args)) => (args, TopicCmd::Describe),
+        Some(("create", args)) => (
+            args,
+            TopicCmd::Create(parse_topic_config(
+                args,
+                args.value_of("topic").expect("topic expected"),
+            )),
+        ),
+        Some(("delete", args)) => (args, TopicCmd::Delete),
+        Some(("alter-config", args)) => (
+            args,
+            TopicCmd::AlterConfig {
+                set: parse_topic_configs(args),
+                delete: args
+                    .values_of("delete-config")
+                    .map(|keys| keys.map(|key| key.to_owned()).collect())
+                    .unwrap_or_default(),
+            },
+        ),
+        _ => panic!("topic subcommand expected"),
+    }
+}
+
+fn parse_topic_config(arg_matches: &ArgMatches, topic: &str) -> TopicConfig {
+    let mut config = TopicConfig::new(topic);
+    if let Some(partitions) = arg_matches.value_of("partitions") {
+        config = config.partitions(partitions.parse().expect("valid number expected"));
+    }
+    if let Some(replication_factor) = arg_matches.value_of("replication-factor") {
+        config =
+            config.replication_factor(replication_factor.parse().expect("valid number expected"));
+    }
+    for (key, value) in parse_topic_configs(arg_matches) {
+        config = config.set(&key, &value);
+    }
+    config
+}
+
+/// Topic configs of `--config` and of the shorthands of common ones
+fn parse_topic_configs(arg_matches: &ArgMatches) -> Vec<(String, String)> {
+    let mut configs: Vec<(String, String)> = arg_matches
+        .values_of("config")
+        .map(|values| {
+            values
+                .map(|raw| parse_config_entry(raw).expect("valid config expected"))
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(policy) = arg_matches.value_of("cleanup-policy") {
+        configs.push(("cleanup.policy".to_string(), policy.to_string()));
+    }
+    if let Some(retention) = arg_matches.value_of("retention") {
+        let retention = parse_duration(retention).expect("valid duration expected");
+        configs.push((
+            "retention.ms".to_string(),
+            retention.as_millis().to_string(),
+        ));
+    }
+    configs
+}
+
+fn parse_avro_ctx(arg_matches: &ArgMatches, format: Option<Format>) -> Result<AvroCtx, CliError> {
     let schema = arg_matches.value_of("schema").map(|s| s.to_owned());
-    // try to read schema from file if path was passed as an arg
-    let schema_file = arg_matches
-        .value_of("schema-file")
-        .map(read_to_string)
-        .transpose()?;
+    // try to read schema from files if paths were passed as args
+    let schema_file = match format {
+        Some(Format::JsonSchema) | Some(Format::Protobuf) => read_single_schema_file(arg_matches)?,
+        _ => avro::combine_schemas(
+            &read_schema_files(arg_matches)?,
+            arg_matches.value_of("record"),
+        )?,
+    };
+
+    let registry_url = arg_matches.value_of("registry-url").map(|s| s.to_owned());
+    let registry_type = arg_matches
+        .value_of("registry-type")
+        .map(|registry_type| {
+            RegistryType::parse(registry_type).expect("valid registry type expected")
+        })
+        .unwrap_or(RegistryType::Confluent);
+    let wire_format = match arg_matches.value_of("wire-format") {
+        Some(format) => WireFormat::parse(format).expect("valid wire format expected"),
+        None if registry_url.is_some() => WireFormat::of_registry(registry_type),
+        None => WireFormat::Raw,
+    };
+    let is_avro = matches!(format, None | Some(Format::Avro));
+    if !is_avro && matches!(wire_format, WireFormat::SingleObject) {
+        return Err(CliError::Usage(
+            "single-object encoding is defined for Avro only".to_string(),
+        ));
+    }
+    if !is_avro && arg_matches.is_present("normalize") {
+        return Err(CliError::Usage(
+            "--normalize is supported for Avro only".to_string(),
+        ));
+    }
 
     Ok(AvroCtx {
-        registry_url: arg_matches.value_of("registry-url").map(|s| s.to_owned()),
+        registry_url,
+        registry_type,
+        registry_group: arg_matches.value_of("registry-group").map(|s| s.to_owned()),
+        registry_basic_auth: arg_matches
+            .value_of("registry-basic-auth")
+            .map(|raw| parse_basic_auth(raw).expect("valid user and password expected")),
+        registry_connect_timeout: arg_matches
+            .value_of("registry-connect-timeout")
+            .map(|raw| parse_duration(raw).expect("valid duration expected")),
+        registry_read_timeout: arg_matches
+            .value_of("registry-read-timeout")
+            .map(|raw| parse_duration(raw).expect("valid duration expected")),
+        registry_retries: arg_matches
+            .value_of("registry-retries")
+            .map(|raw| raw.parse().expect("valid number expected")),
         schema: schema.or(schema_file),
+        references: arg_matches
+            .values_of("reference")
+            .map(|values| {
+                values
+                    .map(|raw| parse_reference(raw).expect("valid reference expected"))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        normalize: arg_matches.is_present("normalize"),
+        wire_format,
+        record: arg_matches.value_of("record").map(|s| s.to_owned()),
     })
 }
 
-fn parse_ssl_ctx(arg_matches: &ArgMatches) -> Result<SslCtx, CliError> {
-    let ca_location = arg_matches
-        .value_of("ssl-ca-location")
-        .map(|s| s.to_owned());
-    let keystore_location = arg_matches
-        .value_of("ssl-keystore-location")
-        .map(|s| s.to_owned());
-    let keystore_password = arg_matches
-        .value_of("ssl-keystore-password")
-        .map(|s| s.to_owned());
+fn parse_repeat(arg_matches: &ArgMatches) -> Repeat {
+    if arg_matches.is_present("loop") {
+        return Repeat::Forever;
+    }
 
-    Ok(SslCtx {
-        enabled: arg_matches.is_present("ssl-enabled"),
-        cert_validate: !arg_matches.is_present("ssl-disable-validate"),
-        host_validate: arg_matches.is_present("ssl-host-validate"),
-        ca_location,
-        keystore_location,
-        keystore_password,
-    })
+    match (
+        arg_matches.value_of("repeat"),
+        arg_matches.value_of("duration"),
+    ) {
+        (_, Some(duration)) => {
+            Repeat::For(parse_duration(duration).expect("valid duration expected"))
+        }
+        (Some(times), None) => Repeat::Times(times.parse().expect("valid number expected")),
+        (None, None) => Repeat::Times(1),
+    }
+}
+
+/// Parses credentials in `USER:PASSWORD` form
+pub fn parse_basic_auth(raw: &str) -> Result<(String, String), String> {
+    match raw.find(':') {
+        Some(idx) => Ok((raw[..idx].to_string(), raw[idx + 1..].to_string())),
+        None => Err("credentials in USER:PASSWORD form expected".to_string()),
+    }
+}
+
+/// Parses a topic config in `KEY=VALUE` form
+pub fn parse_config_entry(raw: &str) -> Result<(String, String), String> {
+    match raw.find('=') {
+        Some(idx) if idx > 0 => Ok((raw[..idx].to_string(), raw[idx + 1..].to_string())),
+        _ => Err("config in KEY=VALUE form expected".to_string()),
+    }
+}
+
+/// Parses a duration like `500ms`, `30s`, `10m`, `2h` or `1d`.
+/// A number without a unit is treated as seconds.
+pub fn parse_duration(raw: &str) -> Result<Duration, String> {
+    let raw = raw.trim();
+    let split_at = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (amount, unit) = raw.split_at(split_at);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", raw))?;
+
+    let seconds_per_unit = match unit {
+        "ms" => return Ok(Duration::from_millis(amount)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(format!("unknown duration unit `{}`", unit)),
+    };
+    amount
+        .checked_mul(seconds_per_unit)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration `{}` is too long", raw))
+}
+
+/// Reads `--schema-file`s and schema files of `--schema-dir`.
+/// Protocols, `.avpr` and `.avdl` files, contribute each of their named types.
+fn read_schema_files(arg_matches: &ArgMatches) -> Result<Vec<String>, CliError> {
+    let mut paths: Vec<PathBuf> = arg_matches
+        .values_of("schema-file")
+        .map(|values| values.map(PathBuf::from).collect())
+        .unwrap_or_default();
+
+    if let Some(dir) = arg_matches.value_of("schema-dir") {
+        let mut dir_paths = read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()?;
+        dir_paths.retain(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("avsc") | Some("avpr") | Some("avdl")
+            )
+        });
+        dir_paths.sort();
+        paths.extend(dir_paths);
+    }
+
+    let mut raw_schemas = Vec::new();
+    for path in paths {
+        raw_schemas.extend(read_schema_file(&path)?);
+    }
+    Ok(raw_schemas)
+}
+
+/// Reads the only `--schema-file` of a JSON Schema or a Protobuf schema, which are not combined
+fn read_single_schema_file(arg_matches: &ArgMatches) -> Result<Option<String>, CliError> {
+    let paths: Vec<&str> = arg_matches
+        .values_of("schema-file")
+        .map(|values| values.collect())
+        .unwrap_or_default();
+
+    match paths.as_slice() {
+        _ if arg_matches.is_present("schema-dir") => Err(CliError::Usage(
+            "--schema-dir is supported for Avro only".to_string(),
+        )),
+        [] => Ok(None),
+        [path] => Ok(Some(read_to_string(path)?)),
+        _ => Err(CliError::Usage(
+            "a single --schema-file expected for JSON Schema and Protobuf".to_string(),
+        )),
+    }
+}
+
+fn read_schema_file(path: &Path) -> Result<Vec<String>, CliError> {
+    let protocol = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("avdl") => idl::parse_idl_file(path)?,
+        Some("avpr") => idl::parse_protocol_file(path)?,
+        _ => return Ok(vec![read_to_string(path)?]),
+    };
+
+    Ok(idl::protocol_types(&protocol)
+        .iter()
+        .map(|schema| schema.to_string())
+        .collect())
+}
+
+fn parse_ssl_config(arg_matches: &ArgMatches) -> Option<SslConfig> {
+    if !arg_matches.is_present("ssl-enabled") {
+        return None;
+    }
+
+    Some(read_ssl_config(arg_matches, "ssl"))
+}
+
+/// Settings of `--registry-ssl.*` arguments, if there are any
+fn parse_registry_ssl_config(arg_matches: &ArgMatches) -> Option<SslConfig> {
+    let is_set = [
+        "registry-ssl-disable-validate",
+        "registry-ssl-host-validate",
+        "registry-ssl-ca-location",
+        "registry-ssl-certificate-location",
+        "registry-ssl-key-location",
+        "registry-ssl-keystore-location",
+        "registry-ssl-keystore-password",
+    ]
+    .iter()
+    .any(|arg| arg_matches.is_present(arg));
+    if !is_set {
+        return None;
+    }
+
+    Some(read_ssl_config(arg_matches, "registry-ssl"))
+}
+
+/// Reads TLS arguments, ids of which start with the prefix
+fn read_ssl_config(arg_matches: &ArgMatches, prefix: &str) -> SslConfig {
+    let value = |name: &str| {
+        arg_matches
+            .value_of(format!("{}-{}", prefix, name).as_str())
+            .map(|s| s.to_owned())
+    };
+
+    SslConfig {
+        cert_validate: !arg_matches.is_present(format!("{}-disable-validate", prefix).as_str()),
+        host_validate: arg_matches.is_present(format!("{}-host-validate", prefix).as_str()),
+        ca_location: value("ca-location"),
+        certificate_location: value("certificate-location"),
+        key_location: value("key-location"),
+        keystore_location: value("keystore-location"),
+        keystore_password: value("keystore-password"),
+    }
+}
+
+fn no_schema(path: &str) -> CliError {
+    CliError::Schema(format!("no schema declared in {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration(&format!("{}d", u64::MAX / 1000)).is_err());
+        assert!(parse_duration(&format!("{}s", u64::MAX)).is_ok());
+    }
 }
@@ -0,0 +1,217 @@
+use crate::avro;
+use crate::error::CliError;
+use crate::json_schema;
+use crate::protobuf::{self, Proto};
+use crate::registry::{RegistryClient, SchemaId, SchemaReference};
+use crate::wire::{self, Header, WireFormat};
+
+use avro_rs::types::Value as AvroValue;
+use avro_rs::Schema;
+use serde_json::Value as JsonValue;
+
+/// Format of JSON payloads and their schemas
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Avro,
+    JsonSchema,
+    Protobuf,
+}
+
+impl Format {
+    pub fn parse(raw: &str) -> Result<Format, String> {
+        match raw {
+            "avro" => Ok(Format::Avro),
+            "json-schema" => Ok(Format::JsonSchema),
+            "protobuf" => Ok(Format::Protobuf),
+            _ => Err(format!("unknown format `{}`", raw)),
+        }
+    }
+
+    /// Schema type the registries know the format by
+    pub fn schema_type(self) -> &'static str {
+        match self {
+            Format::Avro => "AVRO",
+            Format::JsonSchema => "JSON",
+            Format::Protobuf => "PROTOBUF",
+        }
+    }
+
+    pub fn of_schema_type(schema_type: &str) -> Result<Format, String> {
+        match schema_type {
+            "AVRO" => Ok(Format::Avro),
+            "JSON" => Ok(Format::JsonSchema),
+            "PROTOBUF" => Ok(Format::Protobuf),
+            _ => Err(format!("unknown schema type `{}`", schema_type)),
+        }
+    }
+}
+
+/// Serializes JSON values in one of the formats and frames them with the header of the schema
+pub enum Encoder {
+    Avro(Header, Schema),
+    /// Values are validated against the schema and sent as JSON
+    JsonSchema(Header, json_schema::Schema),
+    /// Parsed `.proto` file and the full name of the message
+    Protobuf(Header, Proto, String),
+}
+
+impl Encoder {
+    /// Encoder of a schema which is not registered. Messages are framed with the fingerprint
+    /// of the schema or left raw. `message` is the Protobuf message, the first one by default
+    pub fn from_schema(
+        raw_schema: &str,
+        format: Format,
+        wire_format: WireFormat,
+        message: Option<&str>,
+    ) -> Result<Encoder, CliError> {
+        let header = header(wire_format, None, raw_schema)?;
+        match format {
+            Format::Avro => Ok(Encoder::Avro(header, avro::parse_schema(raw_schema)?)),
+            format => Encoder::parse(header, raw_schema, format, message),
+        }
+    }
+
+    /// Registers the schema under the subject, or takes the latest schema of the subject
+    /// if there is none. Messages are framed with the schema id in the wire format.
+    /// `message` is the Protobuf message, the first one by default
+    pub fn from_registry(
+        registry_client: &RegistryClient,
+        subject: &str,
+        raw_schema: Option<&str>,
+        references: &[SchemaReference],
+        format: Format,
+        wire_format: WireFormat,
+        message: Option<&str>,
+    ) -> Result<Encoder, CliError> {
+        let (id, raw_schema, references) = match raw_schema {
+            Some(raw_schema) => {
+                let id =
+                    registry_client.register_schema(subject, raw_schema, format, references)?;
+                (id, raw_schema.to_string(), references.to_vec())
+            }
+            None => {
+                let registered = registry_client.get_schema_by_subject(subject)?;
+                if registered.schema_type != format.schema_type() {
+                    return Err(CliError::Usage(format!(
+                        "latest schema of subject `{}` is {}, not {}",
+                        subject,
+                        registered.schema_type,
+                        format.schema_type()
+                    )));
+                }
+                (registered.id, registered.schema, registered.references)
+            }
+        };
+
+        match format {
+            // referenced types are inlined, the fingerprint is of the standalone schema
+            Format::Avro => {
+                let dependencies = registry_client.resolve_references(&references)?;
+                let standalone = avro::standalone_schema(&raw_schema, &dependencies)?;
+                let header = header(wire_format, Some(id), &standalone)?;
+                Ok(Encoder::Avro(header, avro::parse_schema(&standalone)?))
+            }
+            format => {
+                let header = header(wire_format, Some(id), &raw_schema)?;
+                Encoder::parse(header, &raw_schema, format, message)
+            }
+        }
+    }
+
+    /// JSON Schema or Protobuf encoder
+    fn parse(
+        header: Header,
+        raw_schema: &str,
+        format: Format,
+        message: Option<&str>,
+    ) -> Result<Encoder, CliError> {
+        match format {
+            // Avro schemas are parsed with references
+            Format::Avro => Err(CliError::Schema(
+                "Avro schemas are not parsed as JSON Schema or Protobuf".to_string(),
+            )),
+            Format::JsonSchema => Ok(Encoder::JsonSchema(
+                header,
+                json_schema::Schema::compile(serde_json::from_str(raw_schema)?)?,
+            )),
+            Format::Protobuf => {
+                let proto = protobuf::parse_proto(raw_schema)?;
+                let message = proto.message_name(message)?;
+                Ok(Encoder::Protobuf(header, proto, message))
+            }
+        }
+    }
+
+    /// Schema of Avro encoders
+    pub fn avro_schema(&self) -> Option<&Schema> {
+        match self {
+            Encoder::Avro(_, schema) => Some(schema),
+            _ => None,
+        }
+    }
+
+    /// Encodes an Avro value of the schema into a message, Avro encoders only
+    pub fn encode_value(&self, value: AvroValue) -> Result<Vec<u8>, CliError> {
+        match self {
+            Encoder::Avro(header, schema) => encode_avro(*header, value, schema),
+            _ => Err(CliError::Usage(
+                "Avro values are encoded with Avro schemas only".to_string(),
+            )),
+        }
+    }
+
+    /// Encodes a JSON value into a message
+    pub fn encode(&self, json: &JsonValue) -> Result<Vec<u8>, CliError> {
+        match self {
+            Encoder::Avro(header, schema) => {
+                let value = avro::map_with_schema(json, &schema.root())?;
+                encode_avro(*header, value, schema)
+            }
+            Encoder::JsonSchema(header, schema) => {
+                let violations = schema.validate(json)?;
+                if !violations.is_empty() {
+                    return Err(CliError::Schema(format!(
+                        "payload does not match the JSON Schema: {}",
+                        violations.join("; ")
+                    )));
+                }
+                Ok(wire::frame(*header, serde_json::to_vec(json)?))
+            }
+            Encoder::Protobuf(header, proto, message) => {
+                // Confluent serializers put the indexes of the message in the file after the id
+                let mut datum = match header {
+                    Header::Confluent(_) => {
+                        protobuf::encode_message_indexes(proto.message_indexes(message))
+                    }
+                    _ => Vec::new(),
+                };
+                datum.extend(proto.encode(message, json)?);
+                Ok(wire::frame(*header, datum))
+            }
+        }
+    }
+}
+
+/// Header of messages of the schema in the wire format. Schema-registry formats need the id
+pub fn header(
+    wire_format: WireFormat,
+    schema_id: Option<SchemaId>,
+    raw_schema: &str,
+) -> Result<Header, CliError> {
+    match wire_format {
+        WireFormat::SingleObject => Ok(Header::Fingerprint(wire::fingerprint(raw_schema)?)),
+        WireFormat::Raw => Ok(Header::Raw),
+        format => match schema_id {
+            Some(schema_id) => Header::with_schema_id(format, schema_id),
+            None => Err(CliError::Usage(
+                "schema-registry wire format requires --registry-url".to_string(),
+            )),
+        },
+    }
+}
+
+/// Encodes an Avro value of the schema and frames it with the header
+pub fn encode_avro(header: Header, value: AvroValue, schema: &Schema) -> Result<Vec<u8>, CliError> {
+    let datum = avro::encode(value, schema)?;
+    Ok(wire::frame(header, datum))
+}
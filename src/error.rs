@@ -1,9 +1,10 @@
-use crate::error::CliError::{Avro, Json, Kafka, Mapping, SchemaRegistry, IO};
+use crate::error::CliError::{Avro, Json, Kafka, Mapping, Schema, SchemaRegistry, Usage, IO};
 use crate::registry::RegistryError;
 
 use core::fmt;
 use core::fmt::Debug;
 use serde::export::Formatter;
+use std::error::Error;
 use std::io;
 
 pub enum CliError {
@@ -13,6 +14,25 @@ pub enum CliError {
     Json(serde_json::Error),
     Kafka(rdkafka::error::KafkaError),
     Mapping(String, String),
+    Schema(String),
+    /// Arguments missing or not valid together
+    Usage(String),
+}
+
+impl CliError {
+    /// Exit code of the command line tool:
+    /// 1 - IO or JSON parsing failure, 2 - usage or registry config, 3 - schema, 4 - mapping,
+    /// 5 - schema-registry, 6 - broker
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            IO(_) | Json(_) => 1,
+            Usage(_) | SchemaRegistry(RegistryError::Config(_)) => 2,
+            Schema(_) | Avro(_) => 3,
+            Mapping(_, _) => 4,
+            SchemaRegistry(_) => 5,
+            Kafka(_) => 6,
+        }
+    }
 }
 
 impl Debug for CliError {
@@ -26,10 +46,25 @@ impl fmt::Display for CliError {
         match self {
             SchemaRegistry(e) => write!(f, "schema registry error: {}", e),
             Avro(e) => write!(f, "avro error: {:?}", e),
-            IO(e) => write!(f, "schema IO error: {}", e),
+            IO(e) => write!(f, "IO error: {}", e),
             Json(e) => write!(f, "json parsing error: {}", e),
             Kafka(e) => write!(f, "kafka error: {}", e),
             Mapping(schema, value) => write!(f, "cannot convert {} into {}", value, schema),
+            Schema(e) => write!(f, "schema error: {}", e),
+            Usage(e) => write!(f, "usage error: {}", e),
+        }
+    }
+}
+
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SchemaRegistry(e) => Some(e),
+            Avro(e) => Some(e),
+            IO(e) => Some(e),
+            Json(e) => Some(e),
+            Kafka(e) => Some(e),
+            Mapping(_, _) | Schema(_) | Usage(_) => None,
         }
     }
 }
@@ -0,0 +1,494 @@
+use crate::avro;
+use crate::error::CliError;
+
+use avro_rs::types::Value as AvroValue;
+use avro_rs::{Days, Decimal, Duration, Millis, Months, SchemaType};
+
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use uuid::{Builder, Variant, Version};
+
+const MAX_COLLECTION_LEN: usize = 3;
+/// Arrays and maps nested deeper are empty and unions are null if they can be,
+/// so values of recursive schemas end
+const MAX_DEPTH: usize = 8;
+/// Values of schemas which recurse without an empty collection or null to end at
+const DEPTH_LIMIT: usize = 64;
+const MIN_STRING_LEN: usize = 4;
+const MAX_STRING_LEN: usize = 16;
+const MAX_REGEX_REPEAT: u32 = 16;
+const MILLIS_IN_DAY: i64 = 24 * 60 * 60 * 1000;
+/// 2020-01-01T00:00:00Z, generated moments are within a year after it,
+/// so the output does not depend on when it is generated
+const EPOCH_MILLIS: i64 = 1_577_836_800_000;
+/// Digits of generated decimals, unscaled values of more digits do not fit an i64
+const MAX_DECIMAL_DIGITS: usize = 18;
+
+/// Field-specific generator, set by the user with `PATH=SPEC`
+pub enum FieldGenerator {
+    /// `range:MIN..MAX`, both bounds are inclusive
+    IntRange(i64, i64),
+    FloatRange(f64, f64),
+    /// `regex:PATTERN`
+    Regex(rand_regex::Regex),
+    /// `choice:A|B|C`, each choice is a JSON value or a plain string
+    Choice(Vec<JsonValue>),
+}
+
+/// Parses a `PATH=SPEC` pair, where PATH is dot-separated field names
+pub fn parse_field_generator(raw: &str) -> Result<(String, FieldGenerator), String> {
+    let (path, spec) = match raw.find('=') {
+        Some(idx) => (&raw[..idx], &raw[idx + 1..]),
+        None => return Err(format!("`{}` is not in a PATH=SPEC form", raw)),
+    };
+    let (kind, arg) = match spec.find(':') {
+        Some(idx) => (&spec[..idx], &spec[idx + 1..]),
+        None => return Err(format!("`{}` is not in a KIND:ARG form", spec)),
+    };
+
+    let generator = match kind {
+        "range" => parse_range(arg)?,
+        "regex" => rand_regex::Regex::compile(arg, MAX_REGEX_REPEAT)
+            .map(FieldGenerator::Regex)
+            .map_err(|e| format!("invalid regex `{}`: {}", arg, e))?,
+        "choice" => FieldGenerator::Choice(
+            arg.split('|')
+                .map(|choice| {
+                    serde_json::from_str(choice)
+                        .unwrap_or_else(|_| JsonValue::String(choice.to_string()))
+                })
+                .collect(),
+        ),
+        _ => return Err(format!("unknown generator `{}`", kind)),
+    };
+
+    Ok((path.to_string(), generator))
+}
+
+fn parse_range(raw: &str) -> Result<FieldGenerator, String> {
+    let idx = raw
+        .find("..")
+        .ok_or_else(|| format!("`{}` is not in a MIN..MAX form", raw))?;
+    let (min, max) = (&raw[..idx], &raw[idx + 2..]);
+
+    if let (Ok(min), Ok(max)) = (min.parse::<i64>(), max.parse::<i64>()) {
+        if min <= max {
+            return Ok(FieldGenerator::IntRange(min, max));
+        }
+    } else if let (Ok(min), Ok(max)) = (min.parse::<f64>(), max.parse::<f64>()) {
+        if min.is_finite() && max.is_finite() && min <= max {
+            return Ok(FieldGenerator::FloatRange(min, max));
+        }
+    }
+
+    Err(format!("invalid range `{}`", raw))
+}
+
+/// Generates random Avro values which conform to a schema
+pub struct Generator<'g> {
+    rng: StdRng,
+    generators: &'g HashMap<String, FieldGenerator>,
+}
+
+impl<'g> Generator<'g> {
+    pub fn new(seed: Option<u64>, generators: &'g HashMap<String, FieldGenerator>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Generator { rng, generators }
+    }
+
+    pub fn generate(&mut self, schema: &SchemaType) -> Result<AvroValue, CliError> {
+        self.generate_at(schema, "", 0)
+    }
+
+    fn generate_at(
+        &mut self,
+        schema: &SchemaType,
+        path: &str,
+        depth: usize,
+    ) -> Result<AvroValue, CliError> {
+        let generators = self.generators;
+        if let Some(generator) = generators.get(path) {
+            return self.generate_with(generator, schema);
+        }
+        if depth > DEPTH_LIMIT {
+            return Err(CliError::Mapping(
+                schema.to_string(),
+                format!("a generated value nested less than {} levels", DEPTH_LIMIT),
+            ));
+        }
+
+        match schema {
+            SchemaType::Null => Ok(AvroValue::Null),
+            SchemaType::Boolean => Ok(AvroValue::Boolean(self.rng.gen())),
+            SchemaType::Int => Ok(AvroValue::Int(self.rng.gen())),
+            SchemaType::Long => Ok(AvroValue::Long(self.rng.gen())),
+            SchemaType::Float => Ok(AvroValue::Float(self.rng.gen())),
+            SchemaType::Double => Ok(AvroValue::Double(self.rng.gen())),
+            SchemaType::Bytes => Ok(AvroValue::Bytes(self.random_bytes(None))),
+            SchemaType::String => Ok(AvroValue::String(self.random_string())),
+            SchemaType::Uuid => {
+                let uuid = Builder::from_bytes(self.rng.gen())
+                    .set_variant(Variant::RFC4122)
+                    .set_version(Version::Random)
+                    .build();
+                Ok(AvroValue::Uuid(uuid))
+            }
+            SchemaType::Date => {
+                let days = self.random_timestamp_millis() / MILLIS_IN_DAY;
+                Ok(AvroValue::Date(days as i32))
+            }
+            SchemaType::TimeMillis => Ok(AvroValue::TimeMillis(
+                self.rng.gen_range(0..MILLIS_IN_DAY) as i32,
+            )),
+            SchemaType::TimeMicros => Ok(AvroValue::TimeMicros(
+                self.rng.gen_range(0..MILLIS_IN_DAY * 1000),
+            )),
+            SchemaType::TimestampMillis => {
+                Ok(AvroValue::TimestampMillis(self.random_timestamp_millis()))
+            }
+            SchemaType::TimestampMicros => Ok(AvroValue::TimestampMicros(
+                self.random_timestamp_millis() * 1000 + self.rng.gen_range(0..1000),
+            )),
+            SchemaType::Decimal(decimal_schema) => {
+                let digits = decimal_schema.precision().min(MAX_DECIMAL_DIGITS) as u32;
+                let max = 10i64.pow(digits) - 1;
+                let unscaled = self.rng.gen_range(-max..=max);
+                Ok(AvroValue::Decimal(Decimal::from(twos_complement(unscaled))))
+            }
+            SchemaType::Duration => Ok(AvroValue::Duration(Duration::new(
+                Months::new(self.rng.gen_range(0..12)),
+                Days::new(self.rng.gen_range(0..31)),
+                Millis::new(self.rng.gen_range(0..MILLIS_IN_DAY) as u32),
+            ))),
+            SchemaType::Fixed(ref fixed_schema) => {
+                let size = fixed_schema.size();
+                Ok(AvroValue::Fixed(size, self.random_bytes(Some(size))))
+            }
+            SchemaType::Enum(ref enum_schema) => {
+                let symbols = enum_schema.symbols();
+                if symbols.is_empty() {
+                    return Err(CliError::Schema(format!(
+                        "enum {} has no symbols to generate",
+                        schema
+                    )));
+                }
+                let idx = self.rng.gen_range(0..symbols.len());
+                Ok(AvroValue::Enum(idx as i32, symbols[idx].clone()))
+            }
+            SchemaType::Array(ref agg) => {
+                let len = self.collection_len(depth);
+                (0..len)
+                    .map(|_| self.generate_at(&agg.items(), path, depth + 1))
+                    .collect::<Result<Vec<AvroValue>, CliError>>()
+                    .map(AvroValue::Array)
+            }
+            SchemaType::Map(ref agg) => {
+                let len = self.collection_len(depth);
+                (0..len)
+                    .map(|_| {
+                        let key = self.random_string();
+                        self.generate_at(&agg.items(), path, depth + 1)
+                            .map(|value| (key, value))
+                    })
+                    .collect::<Result<HashMap<String, AvroValue>, CliError>>()
+                    .map(AvroValue::Map)
+            }
+            SchemaType::Record(ref record_schema) => record_schema
+                .iter_fields()
+                .map(|field| {
+                    let field_path = field_path(path, field.name());
+                    self.generate_at(&field.schema(), &field_path, depth + 1)
+                        .map(|value| (field.name().to_string(), value))
+                })
+                .collect::<Result<Vec<(String, AvroValue)>, CliError>>()
+                .map(AvroValue::Record),
+            SchemaType::Union(union_schema) => {
+                let variants: Vec<SchemaType> = union_schema.iter_variants().collect();
+                let null = variants.iter().position(|v| matches!(v, SchemaType::Null));
+                let idx = match null {
+                    Some(idx) if depth >= MAX_DEPTH => idx,
+                    _ => self.rng.gen_range(0..variants.len()),
+                };
+                self.generate_at(&variants[idx], path, depth)
+                    .map(|v| AvroValue::Union(Box::new(v)))
+            }
+        }
+    }
+
+    fn generate_with(
+        &mut self,
+        generator: &FieldGenerator,
+        schema: &SchemaType,
+    ) -> Result<AvroValue, CliError> {
+        match (generator, schema) {
+            (FieldGenerator::Choice(choices), _) => {
+                let choice = choices.choose(&mut self.rng).unwrap_or(&JsonValue::Null);
+                avro::map_with_schema(choice, schema)
+            }
+            (FieldGenerator::IntRange(min, max), SchemaType::Int) => {
+                if *min < i32::MIN as i64 || *max > i32::MAX as i64 {
+                    return Err(CliError::Mapping(
+                        schema.to_string(),
+                        format!("range {}..{}", min, max),
+                    ));
+                }
+                Ok(AvroValue::Int(self.rng.gen_range(*min..=*max) as i32))
+            }
+            (FieldGenerator::IntRange(min, max), SchemaType::Long) => {
+                Ok(AvroValue::Long(self.rng.gen_range(*min..=*max)))
+            }
+            (FieldGenerator::IntRange(min, max), SchemaType::TimestampMillis) => {
+                Ok(AvroValue::TimestampMillis(self.rng.gen_range(*min..=*max)))
+            }
+            (FieldGenerator::IntRange(min, max), SchemaType::TimestampMicros) => {
+                Ok(AvroValue::TimestampMicros(self.rng.gen_range(*min..=*max)))
+            }
+            (FieldGenerator::IntRange(min, max), SchemaType::Float) => Ok(AvroValue::Float(
+                self.rng.gen_range(*min as f32..=*max as f32),
+            )),
+            (FieldGenerator::IntRange(min, max), SchemaType::Double) => Ok(AvroValue::Double(
+                self.rng.gen_range(*min as f64..=*max as f64),
+            )),
+            (FieldGenerator::FloatRange(min, max), SchemaType::Float) => Ok(AvroValue::Float(
+                self.rng.gen_range(*min as f32..=*max as f32),
+            )),
+            (FieldGenerator::FloatRange(min, max), SchemaType::Double) => {
+                Ok(AvroValue::Double(self.rng.gen_range(*min..=*max)))
+            }
+            (FieldGenerator::Regex(regex), SchemaType::String) => {
+                Ok(AvroValue::String(self.rng.sample(regex)))
+            }
+            (_, SchemaType::Union(union_schema)) => {
+                // the first variant the generator is able to produce, usually the non-null one
+                match union_schema
+                    .iter_variants()
+                    .find(|variant| is_applicable(generator, variant))
+                {
+                    Some(variant) => self
+                        .generate_with(generator, &variant)
+                        .map(|v| AvroValue::Union(Box::new(v))),
+                    None => Err(CliError::Mapping(
+                        schema.to_string(),
+                        "a generated value".to_string(),
+                    )),
+                }
+            }
+            (_, s) => Err(CliError::Mapping(
+                s.to_string(),
+                "a generated value".to_string(),
+            )),
+        }
+    }
+
+    fn collection_len(&mut self, depth: usize) -> usize {
+        if depth >= MAX_DEPTH {
+            0
+        } else {
+            self.rng.gen_range(0..=MAX_COLLECTION_LEN)
+        }
+    }
+
+    fn random_string(&mut self) -> String {
+        let len = self.rng.gen_range(MIN_STRING_LEN..=MAX_STRING_LEN);
+        (&mut self.rng)
+            .sample_iter(&Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_bytes(&mut self, size: Option<usize>) -> Vec<u8> {
+        let len = size.unwrap_or_else(|| self.rng.gen_range(0..=MAX_STRING_LEN));
+        (0..len).map(|_| self.rng.gen()).collect()
+    }
+
+    /// Random moment within a year after the epoch
+    fn random_timestamp_millis(&mut self) -> i64 {
+        EPOCH_MILLIS + self.rng.gen_range(0..MILLIS_IN_DAY * 365)
+    }
+}
+
+fn is_applicable(generator: &FieldGenerator, schema: &SchemaType) -> bool {
+    match (generator, schema) {
+        (FieldGenerator::Choice(_), SchemaType::Null) => false,
+        (FieldGenerator::Choice(_), _) => true,
+        (FieldGenerator::IntRange(_, _), SchemaType::Int)
+        | (FieldGenerator::IntRange(_, _), SchemaType::Long)
+        | (FieldGenerator::IntRange(_, _), SchemaType::TimestampMillis)
+        | (FieldGenerator::IntRange(_, _), SchemaType::TimestampMicros) => true,
+        (FieldGenerator::IntRange(_, _), SchemaType::Float)
+        | (FieldGenerator::IntRange(_, _), SchemaType::Double)
+        | (FieldGenerator::FloatRange(_, _), SchemaType::Float)
+        | (FieldGenerator::FloatRange(_, _), SchemaType::Double) => true,
+        (FieldGenerator::Regex(_), SchemaType::String) => true,
+        _ => false,
+    }
+}
+
+/// Big-endian two's complement bytes of a decimal's unscaled value, as short as possible
+fn twos_complement(unscaled: i64) -> Vec<u8> {
+    let bytes = unscaled.to_be_bytes();
+    let sign = if unscaled < 0 { 0xff } else { 0x00 };
+    // a leading sign byte is redundant when the next byte has the same sign bit
+    let start = (0..bytes.len() - 1)
+        .find(|&i| bytes[i] != sign || (bytes[i + 1] & 0x80) != (sign & 0x80))
+        .unwrap_or(bytes.len() - 1);
+    bytes[start..].to_vec()
+}
+
+fn field_path(parent: &str, field: &str) -> String {
+    if parent.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", parent, field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use avro_rs::Schema;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "Order",
+        "fields": [
+            {"name": "id", "type": {"type": "string", "logicalType": "uuid"}},
+            {"name": "count", "type": "int"},
+            {"name": "price", "type": {"type": "bytes", "logicalType": "decimal", "precision": 6, "scale": 2}},
+            {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["NEW", "PAID"]}},
+            {"name": "created", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+            {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+            {"name": "ttl", "type": {"type": "fixed", "name": "Ttl", "size": 12, "logicalType": "duration"}},
+            {"name": "tags", "type": {"type": "map", "values": "string"}},
+            {"name": "parent", "type": ["null", "Order"]},
+            {"name": "lines", "type": {"type": "array", "items": {
+                "type": "record",
+                "name": "Line",
+                "fields": [{"name": "sku", "type": ["null", "string"]}, {"name": "digest", "type": {"type": "fixed", "name": "Digest", "size": 4}}]
+            }}}
+        ]
+    }"#;
+
+    fn generate(seed: u64, generators: &HashMap<String, FieldGenerator>) -> AvroValue {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        Generator::new(Some(seed), generators)
+            .generate(&schema.root())
+            .unwrap()
+    }
+
+    /// Whether a value is of a schema, as far as the generator is concerned
+    fn conforms(value: &AvroValue, schema: &SchemaType) -> bool {
+        match (value, schema) {
+            (AvroValue::Null, SchemaType::Null)
+            | (AvroValue::Boolean(_), SchemaType::Boolean)
+            | (AvroValue::Int(_), SchemaType::Int)
+            | (AvroValue::Long(_), SchemaType::Long)
+            | (AvroValue::String(_), SchemaType::String)
+            | (AvroValue::Uuid(_), SchemaType::Uuid)
+            | (AvroValue::Decimal(_), SchemaType::Decimal(_))
+            | (AvroValue::Duration(_), SchemaType::Duration) => true,
+            (AvroValue::TimestampMillis(millis), SchemaType::TimestampMillis) => {
+                (EPOCH_MILLIS..EPOCH_MILLIS + MILLIS_IN_DAY * 365).contains(millis)
+            }
+            (AvroValue::Date(days), SchemaType::Date) => {
+                *days as i64 >= EPOCH_MILLIS / MILLIS_IN_DAY
+            }
+            (AvroValue::Fixed(size, bytes), SchemaType::Fixed(fixed)) => {
+                *size == fixed.size() && bytes.len() == fixed.size()
+            }
+            (AvroValue::Enum(idx, symbol), SchemaType::Enum(enum_schema)) => {
+                enum_schema.symbols()[*idx as usize] == *symbol
+            }
+            (AvroValue::Array(items), SchemaType::Array(array)) => {
+                items.iter().all(|item| conforms(item, &array.items()))
+            }
+            (AvroValue::Map(items), SchemaType::Map(map)) => {
+                items.values().all(|item| conforms(item, &map.items()))
+            }
+            (AvroValue::Union(inner), SchemaType::Union(union_schema)) => union_schema
+                .iter_variants()
+                .any(|variant| conforms(inner, &variant)),
+            (AvroValue::Record(fields), SchemaType::Record(record)) => {
+                let schemas: Vec<_> = record.iter_fields().collect();
+                fields.len() == schemas.len()
+                    && fields.iter().zip(schemas).all(|((name, value), field)| {
+                        name == field.name() && conforms(value, &field.schema())
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn generates_values_of_the_schema() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let generators = HashMap::new();
+        for seed in 0..32 {
+            assert!(conforms(&generate(seed, &generators), &schema.root()));
+        }
+    }
+
+    #[test]
+    fn generates_the_same_values_of_the_same_seed() {
+        let generators = HashMap::new();
+        assert_eq!(generate(7, &generators), generate(7, &generators));
+        assert_ne!(generate(7, &generators), generate(8, &generators));
+    }
+
+    #[test]
+    fn generates_fields_with_generators() {
+        let generators: HashMap<String, FieldGenerator> =
+            vec![parse_field_generator("count=range:1..3").unwrap()]
+                .into_iter()
+                .collect();
+
+        for seed in 0..32 {
+            let fields = match generate(seed, &generators) {
+                AvroValue::Record(fields) => fields,
+                other => panic!("{:?} is not a record", other),
+            };
+            assert!(matches!(fields[1].1, AvroValue::Int(1..=3)));
+        }
+    }
+
+    #[test]
+    fn rejects_enums_without_symbols() {
+        let schema = Schema::parse_str(r#"{"type":"enum","name":"E","symbols":[]}"#).unwrap();
+        let generators = HashMap::new();
+        assert!(matches!(
+            Generator::new(Some(0), &generators).generate(&schema.root()),
+            Err(CliError::Schema(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        assert!(parse_field_generator("a=range:0..inf").is_err());
+        assert!(parse_field_generator("a=range:NaN..1").is_err());
+        assert!(parse_field_generator("a=range:3..1").is_err());
+        assert!(matches!(
+            parse_field_generator("a=range:0.5..1"),
+            Ok((_, FieldGenerator::FloatRange(_, _)))
+        ));
+    }
+
+    #[test]
+    fn encodes_decimals_in_twos_complement() {
+        assert_eq!(twos_complement(0), vec![0x00]);
+        assert_eq!(twos_complement(127), vec![0x7f]);
+        assert_eq!(twos_complement(128), vec![0x00, 0x80]);
+        assert_eq!(twos_complement(-1), vec![0xff]);
+        assert_eq!(twos_complement(-128), vec![0x80]);
+        assert_eq!(twos_complement(-129), vec![0xff, 0x7f]);
+    }
+}
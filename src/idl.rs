@@ -0,0 +1,813 @@
+use crate::error::CliError;
+use crate::named;
+
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// `@name(json)` annotations, in the order of appearance
+type Annotations = Vec<(String, JsonValue)>;
+
+/// Parses an Avro IDL file into the JSON protocol it declares.
+/// Imports are resolved relative to the file, a file imported twice declares its types once.
+pub fn parse_idl_file(path: &Path) -> Result<JsonValue, CliError> {
+    parse_idl(path, &mut Imports::default())
+}
+
+fn parse_idl(path: &Path, imports: &mut Imports) -> Result<JsonValue, CliError> {
+    let src = read_to_string(path)?;
+    imports.parsing.push(path.canonicalize()?);
+    let mut parser = Parser {
+        src: &src,
+        pos: 0,
+        path,
+        namespace: None,
+        doc: None,
+        imports,
+    };
+    let protocol = parser.protocol();
+    parser.imports.parsing.pop();
+    protocol
+}
+
+/// Files met while parsing an IDL file and its imports, by canonical path
+#[derive(Default)]
+struct Imports {
+    /// Files imported already
+    imported: HashSet<PathBuf>,
+    /// IDL files being parsed, importing one of them is a cycle
+    parsing: Vec<PathBuf>,
+}
+
+/// Reads an Avro protocol (`.avpr`) file
+pub fn parse_protocol_file(path: &Path) -> Result<JsonValue, CliError> {
+    serde_json::from_str(&read_to_string(path)?).map_err(|e| e.into())
+}
+
+/// Named types declared by a protocol. Top-level types get the protocol namespace
+/// explicitly, so each of them can be used as a standalone schema.
+pub fn protocol_types(protocol: &JsonValue) -> Vec<JsonValue> {
+    let namespace = protocol.get("namespace").and_then(|ns| ns.as_str());
+
+    protocol
+        .get("types")
+        .and_then(|types| types.as_array())
+        .map(|types| {
+            types
+                .iter()
+                .map(|schema| with_namespace(schema, namespace))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn with_namespace(schema: &JsonValue, namespace: Option<&str>) -> JsonValue {
+    match (schema, namespace) {
+        (JsonValue::Object(object), Some(namespace)) if !object.contains_key("namespace") => {
+            let mut object = object.clone();
+            object.insert(
+                "namespace".to_string(),
+                JsonValue::String(namespace.to_string()),
+            );
+            JsonValue::Object(object)
+        }
+        _ => schema.clone(),
+    }
+}
+
+/// Adds annotations of a type as its properties. Primitives and references become objects.
+fn with_properties(schema: JsonValue, annotations: Annotations) -> JsonValue {
+    if annotations.is_empty() {
+        return schema;
+    }
+
+    let mut object = match schema {
+        JsonValue::Object(object) => object,
+        other => {
+            let mut object = Map::new();
+            object.insert("type".to_string(), other);
+            object
+        }
+    };
+    object.extend(annotations);
+    JsonValue::Object(object)
+}
+
+/// Text of a `/** ... */` comment without the leading asterisks
+fn doc_text(raw: &str) -> String {
+    raw.lines()
+        .map(|line| line.trim().trim_start_matches('*').trim())
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Recursive descent parser of the IDL protocol syntax
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    path: &'a Path,
+    /// Namespace the short names of referenced types are resolved against
+    namespace: Option<String>,
+    /// The last doc comment, taken by the declaration which follows it
+    doc: Option<String>,
+    imports: &'a mut Imports,
+}
+
+impl<'a> Parser<'a> {
+    fn protocol(&mut self) -> Result<JsonValue, CliError> {
+        let annotations = self.annotations()?;
+        let doc = self.doc.take();
+        self.keyword("protocol")?;
+        let name = self.ident()?;
+
+        self.namespace = annotations
+            .iter()
+            .find(|(key, _)| key == "namespace")
+            .and_then(|(_, namespace)| namespace.as_str())
+            .map(|namespace| namespace.to_string());
+
+        let mut protocol = Map::new();
+        protocol.insert("protocol".to_string(), JsonValue::String(name));
+        protocol.extend(annotations);
+        if let Some(doc) = doc {
+            protocol.insert("doc".to_string(), JsonValue::String(doc));
+        }
+
+        let mut types = Vec::new();
+        let mut messages = Map::new();
+        self.expect('{')?;
+        while !self.eat('}') {
+            self.item(&mut types, &mut messages)?;
+        }
+
+        self.skip_ws();
+        if self.pos < self.src.len() {
+            return Err(self.error("unexpected input after the protocol"));
+        }
+
+        protocol.insert("types".to_string(), JsonValue::Array(types));
+        protocol.insert("messages".to_string(), JsonValue::Object(messages));
+        Ok(JsonValue::Object(protocol))
+    }
+
+    fn item(
+        &mut self,
+        types: &mut Vec<JsonValue>,
+        messages: &mut Map<String, JsonValue>,
+    ) -> Result<(), CliError> {
+        let annotations = self.annotations()?;
+        let doc = self.doc.take();
+        let start = self.pos;
+
+        match self.ident()?.as_str() {
+            "import" => self.import(types)?,
+            kind @ "record" | kind @ "error" => types.push(self.record(kind, annotations, doc)?),
+            "enum" => types.push(self.enumeration(annotations, doc)?),
+            "fixed" => types.push(self.fixed(annotations, doc)?),
+            _ => {
+                self.pos = start;
+                let (name, message) = self.message(annotations, doc)?;
+                messages.insert(name, message);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn import(&mut self, types: &mut Vec<JsonValue>) -> Result<(), CliError> {
+        let kind = self.ident()?;
+        let file = self.string()?;
+        self.expect(';')?;
+
+        let path = self
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(file);
+        let canonical = path.canonicalize()?;
+        if self.imports.parsing.contains(&canonical) {
+            return Err(self.error(&format!("import cycle through {}", path.display())));
+        }
+        if !self.imports.imported.insert(canonical) {
+            return Ok(());
+        }
+
+        match kind.as_str() {
+            "idl" => types.extend(protocol_types(&parse_idl(&path, self.imports)?)),
+            "protocol" => types.extend(protocol_types(&parse_protocol_file(&path)?)),
+            "schema" => types.push(serde_json::from_str(&read_to_string(&path)?)?),
+            _ => return Err(self.error(&format!("unknown import kind `{}`", kind))),
+        }
+
+        Ok(())
+    }
+
+    /// Common part of named type declarations: the name, annotations and doc
+    fn named_type(
+        &mut self,
+        kind: &str,
+        annotations: Annotations,
+        doc: Option<String>,
+    ) -> Result<Map<String, JsonValue>, CliError> {
+        let name = self.ident()?;
+
+        let mut schema = Map::new();
+        schema.insert("type".to_string(), JsonValue::String(kind.to_string()));
+        schema.insert("name".to_string(), JsonValue::String(name));
+        schema.extend(annotations);
+        if let Some(doc) = doc {
+            schema.insert("doc".to_string(), JsonValue::String(doc));
+        }
+        Ok(schema)
+    }
+
+    fn record(
+        &mut self,
+        kind: &str,
+        annotations: Annotations,
+        doc: Option<String>,
+    ) -> Result<JsonValue, CliError> {
+        let mut schema = self.named_type(kind, annotations, doc)?;
+
+        let mut fields = Vec::new();
+        self.expect('{')?;
+        while !self.eat('}') {
+            fields.extend(self.fields()?);
+        }
+
+        schema.insert("fields".to_string(), JsonValue::Array(fields));
+        Ok(JsonValue::Object(schema))
+    }
+
+    fn enumeration(
+        &mut self,
+        annotations: Annotations,
+        doc: Option<String>,
+    ) -> Result<JsonValue, CliError> {
+        let mut schema = self.named_type("enum", annotations, doc)?;
+
+        let mut symbols = Vec::new();
+        self.expect('{')?;
+        loop {
+            symbols.push(JsonValue::String(self.ident()?));
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect('}')?;
+        schema.insert("symbols".to_string(), JsonValue::Array(symbols));
+
+        if self.eat('=') {
+            schema.insert("default".to_string(), JsonValue::String(self.ident()?));
+        }
+        self.eat(';');
+        Ok(JsonValue::Object(schema))
+    }
+
+    fn fixed(
+        &mut self,
+        annotations: Annotations,
+        doc: Option<String>,
+    ) -> Result<JsonValue, CliError> {
+        let mut schema = self.named_type("fixed", annotations, doc)?;
+
+        self.expect('(')?;
+        let size = self.integer()?;
+        self.expect(')')?;
+        self.expect(';')?;
+
+        schema.insert("size".to_string(), JsonValue::from(size));
+        Ok(JsonValue::Object(schema))
+    }
+
+    /// `RESPONSE name(REQUEST...) [oneway | throws ERRORS];`
+    fn message(
+        &mut self,
+        annotations: Annotations,
+        doc: Option<String>,
+    ) -> Result<(String, JsonValue), CliError> {
+        let (response, _) = self.schema_type()?;
+        let name = self.ident()?;
+
+        let mut request = Vec::new();
+        self.expect('(')?;
+        if !self.eat(')') {
+            loop {
+                let (param_type, optional) = self.schema_type()?;
+                request.push(self.variable(&param_type, optional, None)?);
+                if !self.eat(',') {
+                    break;
+                }
+            }
+            self.expect(')')?;
+        }
+
+        let mut message = Map::new();
+        message.extend(annotations);
+        if let Some(doc) = doc {
+            message.insert("doc".to_string(), JsonValue::String(doc));
+        }
+        message.insert("request".to_string(), JsonValue::Array(request));
+        message.insert("response".to_string(), response);
+
+        if self.eat_keyword("oneway") {
+            message.insert("one-way".to_string(), JsonValue::Bool(true));
+        } else if self.eat_keyword("throws") {
+            let mut errors = Vec::new();
+            loop {
+                errors.push(self.reference()?);
+                if !self.eat(',') {
+                    break;
+                }
+            }
+            message.insert("errors".to_string(), JsonValue::Array(errors));
+        }
+        self.expect(';')?;
+
+        Ok((name, JsonValue::Object(message)))
+    }
+
+    /// `TYPE name [= default], ...;` declaring one field per name
+    fn fields(&mut self) -> Result<Vec<JsonValue>, CliError> {
+        let doc = self.doc.take();
+        let (field_type, optional) = self.schema_type()?;
+
+        let mut fields = Vec::new();
+        loop {
+            fields.push(self.variable(&field_type, optional, doc.clone())?);
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect(';')?;
+        Ok(fields)
+    }
+
+    fn variable(
+        &mut self,
+        field_type: &JsonValue,
+        optional: bool,
+        doc: Option<String>,
+    ) -> Result<JsonValue, CliError> {
+        let annotations = self.annotations()?;
+        let doc = self.doc.take().or(doc);
+        let name = self.ident()?;
+        let default = if self.eat('=') {
+            Some(self.json()?)
+        } else {
+            None
+        };
+
+        // the default of a union is of its first variant, so `T?` with a non-null default is [T, null]
+        let field_type = match (field_type, &default) {
+            (JsonValue::Array(variants), Some(default)) if optional && !default.is_null() => {
+                JsonValue::Array(variants.iter().rev().cloned().collect())
+            }
+            _ => field_type.clone(),
+        };
+
+        let mut field = Map::new();
+        field.insert("name".to_string(), JsonValue::String(name));
+        field.insert("type".to_string(), field_type);
+        if let Some(doc) = doc {
+            field.insert("doc".to_string(), JsonValue::String(doc));
+        }
+        if let Some(default) = default {
+            field.insert("default".to_string(), default);
+        }
+        field.extend(annotations);
+        Ok(JsonValue::Object(field))
+    }
+
+    /// A type, possibly annotated. Returns whether it is optional, i.e. `T?`
+    fn schema_type(&mut self) -> Result<(JsonValue, bool), CliError> {
+        let annotations = self.annotations()?;
+        let schema = with_properties(self.base_type()?, annotations);
+
+        if self.eat('?') {
+            Ok((json!(["null", schema]), true))
+        } else {
+            Ok((schema, false))
+        }
+    }
+
+    fn base_type(&mut self) -> Result<JsonValue, CliError> {
+        let start = self.pos;
+
+        let schema = match self.ident()?.as_str() {
+            "array" => {
+                self.expect('<')?;
+                let (items, _) = self.schema_type()?;
+                self.expect('>')?;
+                json!({ "type": "array", "items": items })
+            }
+            "map" => {
+                self.expect('<')?;
+                let (values, _) = self.schema_type()?;
+                self.expect('>')?;
+                json!({ "type": "map", "values": values })
+            }
+            "union" => {
+                let mut variants = Vec::new();
+                self.expect('{')?;
+                loop {
+                    variants.push(self.schema_type()?.0);
+                    if !self.eat(',') {
+                        break;
+                    }
+                }
+                self.expect('}')?;
+                JsonValue::Array(variants)
+            }
+            "decimal" => {
+                self.expect('(')?;
+                let precision = self.integer()?;
+                self.expect(',')?;
+                let scale = self.integer()?;
+                self.expect(')')?;
+                json!({ "type": "bytes", "logicalType": "decimal", "precision": precision, "scale": scale })
+            }
+            "date" => json!({ "type": "int", "logicalType": "date" }),
+            "time_ms" => json!({ "type": "int", "logicalType": "time-millis" }),
+            "timestamp_ms" => json!({ "type": "long", "logicalType": "timestamp-millis" }),
+            "local_timestamp_ms" => {
+                json!({ "type": "long", "logicalType": "local-timestamp-millis" })
+            }
+            "uuid" => json!({ "type": "string", "logicalType": "uuid" }),
+            "void" => JsonValue::String("null".to_string()),
+            primitive @ "null"
+            | primitive @ "boolean"
+            | primitive @ "int"
+            | primitive @ "long"
+            | primitive @ "float"
+            | primitive @ "double"
+            | primitive @ "bytes"
+            | primitive @ "string" => JsonValue::String(primitive.to_string()),
+            _ => {
+                self.pos = start;
+                self.reference()?
+            }
+        };
+
+        Ok(schema)
+    }
+
+    /// Named type reference, resolved to a fullname
+    fn reference(&mut self) -> Result<JsonValue, CliError> {
+        let name = self.ident()?;
+        Ok(JsonValue::String(named::fullname(
+            &name,
+            self.namespace.as_deref(),
+        )))
+    }
+
+    fn annotations(&mut self) -> Result<Annotations, CliError> {
+        let mut annotations = Vec::new();
+        while self.eat('@') {
+            let name = self.word(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')?;
+            self.expect('(')?;
+            let value = self.json()?;
+            self.expect(')')?;
+            annotations.push((name, value));
+        }
+        Ok(annotations)
+    }
+
+    /// Identifier, optionally dotted. Backticks allow keywords as names.
+    fn ident(&mut self) -> Result<String, CliError> {
+        self.skip_ws();
+        if self.src[self.pos..].starts_with('`') {
+            let rest = &self.src[self.pos + 1..];
+            return match rest.find('`') {
+                Some(end) => {
+                    self.pos += end + 2;
+                    Ok(rest[..end].to_string())
+                }
+                None => Err(self.error("unterminated `")),
+            };
+        }
+        self.word(|c| c.is_alphanumeric() || c == '_' || c == '.')
+    }
+
+    fn word(&mut self, is_part: fn(char) -> bool) -> Result<String, CliError> {
+        self.skip_ws();
+        let rest = &self.src[self.pos..];
+        let len = rest.find(|c: char| !is_part(c)).unwrap_or(rest.len());
+
+        match rest.chars().next() {
+            Some(first) if len > 0 && !first.is_ascii_digit() => {
+                self.pos += len;
+                Ok(rest[..len].to_string())
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), CliError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", keyword)))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let start = self.pos;
+        match self.ident() {
+            Ok(ref ident) if ident == keyword => true,
+            _ => {
+                self.pos = start;
+                false
+            }
+        }
+    }
+
+    fn json(&mut self) -> Result<JsonValue, CliError> {
+        self.skip_ws();
+        let rest = &self.src[self.pos..];
+
+        // numbers and literals must be followed by JSON delimiters in a stream, so they are cut out
+        let (value, len) = if rest.starts_with(&['{', '[', '"'][..]) {
+            let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<JsonValue>();
+            match stream.next() {
+                Some(Ok(value)) => (Some(value), stream.byte_offset()),
+                _ => (None, 0),
+            }
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '-' || c == '+'))
+                .unwrap_or(rest.len());
+            (serde_json::from_str(&rest[..len]).ok(), len)
+        };
+
+        match value {
+            Some(value) => {
+                self.pos += len;
+                Ok(value)
+            }
+            None => Err(self.error("expected a JSON value")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, CliError> {
+        match self.json()? {
+            JsonValue::String(s) => Ok(s),
+            _ => Err(self.error("expected a string")),
+        }
+    }
+
+    fn integer(&mut self) -> Result<u64, CliError> {
+        self.json()?
+            .as_u64()
+            .ok_or_else(|| self.error("expected a non-negative integer"))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), CliError> {
+        if self.eat(c) {
+            return Ok(());
+        }
+
+        let found = match self.src[self.pos..].chars().next() {
+            Some(found) => format!("`{}`", found),
+            None => "the end of file".to_string(),
+        };
+        Err(self.error(&format!("expected `{}`, found {}", c, found)))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.src[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips whitespaces and comments, remembering the doc comment
+    fn skip_ws(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(idx) => {
+                        // `/**/` is an empty plain comment
+                        if trimmed.starts_with("/**") && idx > 0 {
+                            self.doc = Some(doc_text(&trimmed[3..idx + 2]));
+                        }
+                        self.pos += idx + 4;
+                    }
+                    None => self.pos += trimmed.len(),
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> CliError {
+        let line = self.src[..self.pos].matches('\n').count() + 1;
+        CliError::Schema(format!(
+            "{}, line {}: {}",
+            self.path.display(),
+            line,
+            message
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Writes the files into a fresh directory of the test and returns it
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("idl-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    fn parse(test: &str, src: &str) -> Result<JsonValue, CliError> {
+        let dir = write_files(test, &[("main.avdl", src)]);
+        parse_idl_file(&dir.join("main.avdl"))
+    }
+
+    #[test]
+    fn parses_records_enums_and_fixed() {
+        let protocol = parse(
+            "types",
+            r#"
+            @namespace("shop")
+            protocol Shop {
+                /** An order */
+                record Order {
+                    string id;
+                    int? quantity = 1;
+                    array<Item> items;
+                    map<long> counts = {};
+                    union { null, string } note = null;
+                    decimal(9, 2) price;
+                    timestamp_ms created;
+                    Kind @aliases(["kind"]) `type`;
+                }
+                enum Kind { NEW, USED } = NEW;
+                fixed Hash(16);
+                record Item { string name; }
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(protocol["protocol"], "Shop");
+        assert_eq!(protocol["namespace"], "shop");
+        let order = &protocol["types"][0];
+        assert_eq!(order["name"], "Order");
+        assert_eq!(order["doc"], "An order");
+        assert_eq!(
+            order["fields"],
+            json!([
+                {"name": "id", "type": "string"},
+                {"name": "quantity", "type": ["int", "null"], "default": 1},
+                {"name": "items", "type": {"type": "array", "items": "shop.Item"}},
+                {"name": "counts", "type": {"type": "map", "values": "long"}, "default": {}},
+                {"name": "note", "type": ["null", "string"], "default": null},
+                {"name": "price", "type": {"type": "bytes", "logicalType": "decimal", "precision": 9, "scale": 2}},
+                {"name": "created", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                {"name": "type", "type": "shop.Kind", "aliases": ["kind"]}
+            ])
+        );
+        assert_eq!(
+            protocol["types"][1],
+            json!({"type": "enum", "name": "Kind", "symbols": ["NEW", "USED"], "default": "NEW"})
+        );
+        assert_eq!(
+            protocol["types"][2],
+            json!({"type": "fixed", "name": "Hash", "size": 16})
+        );
+    }
+
+    #[test]
+    fn parses_messages() {
+        let protocol = parse(
+            "messages",
+            r#"
+            protocol Api {
+                error Failure { string reason; }
+                string hello(string greeting, int times = 1) throws Failure;
+                void ping() oneway;
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            protocol["messages"]["hello"],
+            json!({
+                "request": [
+                    {"name": "greeting", "type": "string"},
+                    {"name": "times", "type": "int", "default": 1}
+                ],
+                "response": "string",
+                "errors": ["Failure"]
+            })
+        );
+        assert_eq!(protocol["messages"]["ping"]["one-way"], true);
+        assert_eq!(protocol["messages"]["ping"]["response"], "null");
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_the_line() {
+        let error = parse("syntax", "protocol P {\n record R { string }\n}").unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{}", error);
+    }
+
+    #[test]
+    fn declares_types_of_an_import_reached_twice_once() {
+        let dir = write_files(
+            "diamond",
+            &[
+                (
+                    "main.avdl",
+                    r#"protocol Main { import idl "b.avdl"; import idl "c.avdl"; }"#,
+                ),
+                (
+                    "b.avdl",
+                    r#"protocol B { import idl "common.avdl"; record B { Common c; } }"#,
+                ),
+                (
+                    "c.avdl",
+                    r#"protocol C { import idl "common.avdl"; record C { Common c; } }"#,
+                ),
+                (
+                    "common.avdl",
+                    "protocol Common { record Common { int x; } }",
+                ),
+            ],
+        );
+
+        let protocol = parse_idl_file(&dir.join("main.avdl")).unwrap();
+        let names: Vec<&str> = protocol["types"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Common", "B", "C"]);
+    }
+
+    #[test]
+    fn reports_import_cycles() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("a.avdl", r#"protocol A { import idl "b.avdl"; }"#),
+                ("b.avdl", r#"protocol B { import idl "a.avdl"; }"#),
+            ],
+        );
+
+        match parse_idl_file(&dir.join("a.avdl")) {
+            Err(CliError::Schema(message)) => {
+                assert!(message.contains("import cycle"), "{}", message)
+            }
+            other => panic!("cycle expected, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn imports_protocols_and_schemas() {
+        let dir = write_files(
+            "kinds",
+            &[
+                (
+                    "main.avdl",
+                    r#"@namespace("m") protocol Main { import protocol "p.avpr"; import schema "s.avsc"; }"#,
+                ),
+                (
+                    "p.avpr",
+                    r#"{"protocol": "P", "namespace": "p", "types": [{"type": "fixed", "name": "F", "size": 1}]}"#,
+                ),
+                (
+                    "s.avsc",
+                    r#"{"type": "enum", "name": "E", "symbols": ["A"]}"#,
+                ),
+            ],
+        );
+
+        let protocol = parse_idl_file(&dir.join("main.avdl")).unwrap();
+        assert_eq!(protocol["types"][0]["namespace"], "p");
+        assert_eq!(protocol["types"][1]["name"], "E");
+    }
+}
@@ -0,0 +1,306 @@
+use crate::error::CliError;
+
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashSet;
+
+/// Observed shape of JSON values
+#[derive(Default)]
+struct Shape {
+    null: bool,
+    boolean: bool,
+    number: Option<NumberKind>,
+    string: bool,
+    array: Option<Box<Shape>>,
+    record: Option<Vec<(String, Shape)>>,
+}
+
+/// Numbers are widened int -> long -> double
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum NumberKind {
+    Int,
+    Long,
+    Double,
+}
+
+impl Shape {
+    fn observe(&mut self, json: &JsonValue) {
+        match json {
+            JsonValue::Null => self.null = true,
+            JsonValue::Bool(_) => self.boolean = true,
+            JsonValue::Number(n) => {
+                let kind = match n.as_i64() {
+                    Some(n) if n >= i32::MIN as i64 && n <= i32::MAX as i64 => NumberKind::Int,
+                    Some(_) => NumberKind::Long,
+                    None => NumberKind::Double,
+                };
+                self.number = match self.number {
+                    Some(seen) if seen > kind => Some(seen),
+                    _ => Some(kind),
+                };
+            }
+            JsonValue::String(_) => self.string = true,
+            JsonValue::Array(items) => {
+                let items_shape = self.array.get_or_insert_with(Default::default);
+                items.iter().for_each(|item| items_shape.observe(item));
+            }
+            JsonValue::Object(map) => self.observe_record(map),
+        }
+    }
+
+    fn observe_record(&mut self, map: &Map<String, JsonValue>) {
+        let first_seen = self.record.is_none();
+        let fields = self.record.get_or_insert_with(Vec::new);
+
+        // fields missing in this object become nullable
+        for (name, shape) in fields.iter_mut() {
+            if !map.contains_key(name) {
+                shape.null = true;
+            }
+        }
+
+        for (name, value) in map {
+            match fields.iter_mut().find(|(field, _)| field == name) {
+                Some((_, shape)) => shape.observe(value),
+                None => {
+                    // fields missing in previous objects become nullable too
+                    let mut shape = Shape {
+                        null: !first_seen,
+                        ..Default::default()
+                    };
+                    shape.observe(value);
+                    fields.push((name.clone(), shape));
+                }
+            }
+        }
+    }
+
+    fn to_schema(&self, name: &str, names: &mut HashSet<String>) -> Result<JsonValue, CliError> {
+        let mut variants: Vec<JsonValue> = Vec::new();
+
+        if self.boolean {
+            variants.push(json!("boolean"));
+        }
+        match self.number {
+            Some(NumberKind::Int) => variants.push(json!("int")),
+            Some(NumberKind::Long) => variants.push(json!("long")),
+            Some(NumberKind::Double) => variants.push(json!("double")),
+            None => {}
+        }
+        if self.string {
+            variants.push(json!("string"));
+        }
+        if let Some(ref items) = self.array {
+            let items = items.to_schema(&format!("{}Item", name), names)?;
+            variants.push(json!({"type": "array", "items": items}));
+        }
+        if let Some(ref fields) = self.record {
+            variants.push(record_schema(fields, name, None, names)?);
+        }
+
+        Ok(match (self.null, variants.len()) {
+            (_, 0) => json!("null"),
+            (false, 1) => variants.remove(0),
+            (false, _) => JsonValue::Array(variants),
+            (true, _) => {
+                variants.insert(0, json!("null"));
+                JsonValue::Array(variants)
+            }
+        })
+    }
+}
+
+fn record_schema(
+    fields: &[(String, Shape)],
+    name: &str,
+    namespace: Option<&str>,
+    names: &mut HashSet<String>,
+) -> Result<JsonValue, CliError> {
+    let name = unique_name(name, names);
+
+    let fields = fields
+        .iter()
+        .map(|(field_name, shape)| {
+            if !is_valid_name(field_name) {
+                return Err(CliError::Schema(format!(
+                    "`{}` is not a valid Avro field name",
+                    field_name
+                )));
+            }
+
+            let field_type =
+                shape.to_schema(&format!("{}{}", name, camel_case(field_name)), names)?;
+            let mut field = json!({"name": field_name, "type": field_type});
+            // a union starting with null may default to null
+            if shape.null {
+                field["default"] = JsonValue::Null;
+            }
+            Ok(field)
+        })
+        .collect::<Result<Vec<JsonValue>, CliError>>()?;
+
+    Ok(match namespace {
+        Some(namespace) => {
+            json!({"type": "record", "name": name, "namespace": namespace, "fields": fields})
+        }
+        None => json!({"type": "record", "name": name, "fields": fields}),
+    })
+}
+
+/// Infers an Avro schema from JSON samples.
+/// Returns the schema of a record named `name`, every sample is expected to be an object.
+pub fn infer_schema(
+    samples: &[JsonValue],
+    name: &str,
+    namespace: Option<&str>,
+) -> Result<JsonValue, CliError> {
+    if !is_valid_name(name) {
+        return Err(CliError::Schema(format!(
+            "`{}` is not a valid Avro name",
+            name
+        )));
+    }
+
+    let mut shape = Shape::default();
+    for sample in samples {
+        match sample {
+            JsonValue::Object(map) => shape.observe_record(map),
+            other => {
+                return Err(CliError::Schema(format!(
+                    "JSON object expected, got {}",
+                    other
+                )))
+            }
+        }
+    }
+
+    let fields = shape.record.unwrap_or_default();
+    record_schema(&fields, name, namespace, &mut HashSet::new())
+}
+
+fn unique_name(name: &str, names: &mut HashSet<String>) -> String {
+    let mut unique = name.to_string();
+    let mut idx = 1;
+    while names.contains(&unique) {
+        idx += 1;
+        unique = format!("{}{}", name, idx);
+    }
+    names.insert(unique.clone());
+    unique
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Avro names: `[A-Za-z_][A-Za-z0-9_]*`
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avro;
+
+    fn samples() -> Vec<JsonValue> {
+        vec![
+            json!({"id": 1, "name": "a", "price": 1, "address": {"city": "x"}}),
+            json!({"id": 3_000_000_000i64, "price": 2.5, "note": null, "address": {"city": "y", "zip": "1"}}),
+            json!({"id": 2, "name": null, "price": 3, "tags": [{"city": "z"}], "address": {"city": "z"}}),
+        ]
+    }
+
+    fn field<'j>(schema: &'j JsonValue, name: &str) -> &'j JsonValue {
+        schema["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|field| field["name"] == name)
+            .unwrap_or_else(|| panic!("no field {} in {}", name, schema))
+    }
+
+    #[test]
+    fn makes_missing_and_null_fields_nullable() {
+        let schema = infer_schema(&samples(), "Order", None).unwrap();
+
+        let name = field(&schema, "name");
+        assert_eq!(name["type"], json!(["null", "string"]));
+        assert_eq!(name["default"], JsonValue::Null);
+
+        let zip = field(&field(&schema, "address")["type"], "zip");
+        assert_eq!(zip["type"], json!(["null", "string"]));
+        assert_eq!(zip["default"], JsonValue::Null);
+
+        assert_eq!(field(&schema, "note")["type"], json!("null"));
+
+        let id = field(&schema, "id");
+        assert_eq!(id.get("default"), None);
+    }
+
+    #[test]
+    fn widens_numbers() {
+        let schema = infer_schema(&samples(), "Order", None).unwrap();
+        assert_eq!(field(&schema, "id")["type"], json!("long"));
+        assert_eq!(field(&schema, "price")["type"], json!("double"));
+
+        let ints = infer_schema(&[json!({"n": 1}), json!({"n": -2})], "Ints", None).unwrap();
+        assert_eq!(field(&ints, "n")["type"], json!("int"));
+    }
+
+    #[test]
+    fn names_nested_records_uniquely() {
+        let schema = infer_schema(&samples(), "Order", Some("shop")).unwrap();
+        assert_eq!(schema["namespace"], json!("shop"));
+
+        let address = &field(&schema, "address")["type"];
+        assert_eq!(address["name"], json!("OrderAddress"));
+        let tag = &field(&schema, "tags")["type"][1]["items"];
+        assert_eq!(tag["name"], json!("OrderTagsItem"));
+
+        let nested = infer_schema(&[json!({"a": {"b": {}}, "a_b": {}})], "R", None).unwrap();
+        let inner = &field(&field(&nested, "a")["type"], "b")["type"];
+        assert_eq!(inner["name"], json!("RAB"));
+        assert_eq!(field(&nested, "a_b")["type"]["name"], json!("RAB2"));
+    }
+
+    #[test]
+    fn infers_schemas_which_map_the_samples() {
+        let samples = samples();
+        let inferred = infer_schema(&samples, "Order", None).unwrap();
+        let schema = avro::parse_schema(&inferred.to_string()).unwrap();
+        for sample in &samples {
+            avro::map_with_schema(sample, &schema.root()).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(matches!(
+            infer_schema(&[json!({"a-b": 1})], "R", None),
+            Err(CliError::Schema(_))
+        ));
+        assert!(matches!(
+            infer_schema(&[json!({})], "1R", None),
+            Err(CliError::Schema(_))
+        ));
+        assert!(matches!(
+            infer_schema(&[json!(1)], "R", None),
+            Err(CliError::Schema(_))
+        ));
+    }
+}
@@ -0,0 +1,629 @@
+use crate::error::CliError;
+
+use regex::Regex;
+use serde_json::{Map, Value as JsonValue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Regular expressions of `pattern`s and `patternProperties` by their source
+type Patterns = HashMap<String, Regex>;
+
+/// JSON Schema with its patterns compiled, so values are validated without compiling them again.
+/// Patterns are Rust regular expressions: ECMA-262 lookaround and backreferences are not supported
+/// and schemas using them are rejected, `\d` and `\w` match Unicode digits and word characters.
+pub struct Schema {
+    schema: JsonValue,
+    patterns: Patterns,
+}
+
+impl Schema {
+    pub fn compile(schema: JsonValue) -> Result<Schema, CliError> {
+        let mut patterns = Patterns::new();
+        collect_patterns(&schema, &mut patterns)?;
+        Ok(Schema { schema, patterns })
+    }
+
+    /// Validates a JSON value against the schema. Keywords of drafts 4 to 7 are supported,
+    /// except `format`, remote `$ref`s and `dependencies`.
+    /// Returns violations, each prefixed with the path of the value, `$` is the root.
+    pub fn validate(&self, value: &JsonValue) -> Result<Vec<String>, CliError> {
+        let mut violations = Vec::new();
+        let validator = Validator {
+            root: &self.schema,
+            patterns: &self.patterns,
+            resolving: RefCell::new(Vec::new()),
+        };
+        validator.check(&self.schema, value, "$", &mut violations)?;
+        Ok(violations)
+    }
+}
+
+/// Validates a JSON value against a JSON Schema compiled for this value only
+pub fn validate(schema: &JsonValue, value: &JsonValue) -> Result<Vec<String>, CliError> {
+    Schema::compile(schema.clone())?.validate(value)
+}
+
+struct Validator<'s> {
+    root: &'s JsonValue,
+    patterns: &'s Patterns,
+    /// References being resolved and addresses of the values they are resolved for.
+    /// A reference resolved again for the same value is circular
+    resolving: RefCell<Vec<(&'s str, *const JsonValue)>>,
+}
+
+impl<'s> Validator<'s> {
+    fn check(
+        &self,
+        schema: &'s JsonValue,
+        value: &JsonValue,
+        path: &str,
+        violations: &mut Vec<String>,
+    ) -> Result<(), CliError> {
+        let schema = match schema {
+            JsonValue::Bool(true) => return Ok(()),
+            JsonValue::Bool(false) => {
+                violations.push(format!("{}: no value is allowed", path));
+                return Ok(());
+            }
+            JsonValue::Object(schema) => schema,
+            _ => {
+                return Err(CliError::Schema(format!(
+                    "{}: schema is not an object",
+                    path
+                )))
+            }
+        };
+
+        // siblings of `$ref` are ignored
+        if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+            let key = (reference, value as *const JsonValue);
+            if self.resolving.borrow().contains(&key) {
+                return Err(CliError::Schema(format!(
+                    "{}: circular reference `{}`",
+                    path, reference
+                )));
+            }
+            self.resolving.borrow_mut().push(key);
+            let result = self.check(self.resolve(reference)?, value, path, violations);
+            self.resolving.borrow_mut().pop();
+            return result;
+        }
+
+        let mut fail = |message: String| violations.push(format!("{}: {}", path, message));
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                JsonValue::String(t) => vec![t.as_str()],
+                JsonValue::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            if !types.iter().any(|t| is_type(value, t)) {
+                fail(format!(
+                    "{} is not of type {}",
+                    kind(value),
+                    types.join(" or ")
+                ));
+            }
+        }
+        if let Some(symbols) = schema.get("enum").and_then(|e| e.as_array()) {
+            if !symbols.contains(value) {
+                fail(format!(
+                    "{} is not one of {}",
+                    value,
+                    JsonValue::from(symbols.clone())
+                ));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                fail(format!("{} is not {}", value, constant));
+            }
+        }
+
+        match value {
+            JsonValue::Number(n) => check_number(schema, n.as_f64().unwrap_or_default(), &mut fail),
+            JsonValue::String(s) => check_string(schema, s, self.patterns, &mut fail)?,
+            _ => {}
+        }
+
+        match value {
+            JsonValue::Array(items) => self.check_array(schema, items, path, violations)?,
+            JsonValue::Object(object) => self.check_object(schema, object, path, violations)?,
+            _ => {}
+        }
+
+        self.check_combinators(schema, value, path, violations)
+    }
+
+    fn check_array(
+        &self,
+        schema: &'s Map<String, JsonValue>,
+        items: &[JsonValue],
+        path: &str,
+        violations: &mut Vec<String>,
+    ) -> Result<(), CliError> {
+        if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+            if (items.len() as u64) < min {
+                violations.push(format!("{}: fewer than {} items", path, min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()) {
+            if items.len() as u64 > max {
+                violations.push(format!("{}: more than {} items", path, max));
+            }
+        }
+        if schema.get("uniqueItems") == Some(&JsonValue::Bool(true)) {
+            let has_duplicates = items
+                .iter()
+                .enumerate()
+                .any(|(idx, item)| items[idx + 1..].contains(item));
+            if has_duplicates {
+                violations.push(format!("{}: items are not unique", path));
+            }
+        }
+
+        match schema.get("items") {
+            // tuple, the rest is checked against `additionalItems`
+            Some(JsonValue::Array(tuple)) => {
+                for (idx, item) in items.iter().enumerate() {
+                    let item_schema = match (tuple.get(idx), schema.get("additionalItems")) {
+                        (Some(item_schema), _) | (None, Some(item_schema)) => item_schema,
+                        (None, None) => break,
+                    };
+                    self.check(item_schema, item, &format!("{}[{}]", path, idx), violations)?;
+                }
+            }
+            Some(item_schema) => {
+                for (idx, item) in items.iter().enumerate() {
+                    self.check(item_schema, item, &format!("{}[{}]", path, idx), violations)?;
+                }
+            }
+            None => {}
+        }
+
+        if let Some(contains) = schema.get("contains") {
+            let mut found = false;
+            for item in items {
+                if self.is_valid(contains, item, path)? {
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                violations.push(format!("{}: no item matches `contains`", path));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_object(
+        &self,
+        schema: &'s Map<String, JsonValue>,
+        object: &Map<String, JsonValue>,
+        path: &str,
+        violations: &mut Vec<String>,
+    ) -> Result<(), CliError> {
+        for required in schema
+            .get("required")
+            .and_then(|r| r.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r.as_str())
+        {
+            if !object.contains_key(required) {
+                violations.push(format!(
+                    "{}: required property `{}` is missing",
+                    path, required
+                ));
+            }
+        }
+        if let Some(min) = schema.get("minProperties").and_then(|m| m.as_u64()) {
+            if (object.len() as u64) < min {
+                violations.push(format!("{}: fewer than {} properties", path, min));
+            }
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(|m| m.as_u64()) {
+            if object.len() as u64 > max {
+                violations.push(format!("{}: more than {} properties", path, max));
+            }
+        }
+
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        let pattern_properties = schema.get("patternProperties").and_then(|p| p.as_object());
+
+        for (name, property) in object {
+            let property_path = format!("{}.{}", path, name);
+            if let Some(names) = schema.get("propertyNames") {
+                let name_value = JsonValue::String(name.clone());
+                self.check(names, &name_value, &property_path, violations)?;
+            }
+
+            let mut matched = false;
+            if let Some(property_schema) = properties.and_then(|p| p.get(name)) {
+                matched = true;
+                self.check(property_schema, property, &property_path, violations)?;
+            }
+            for (pattern, property_schema) in pattern_properties.into_iter().flatten() {
+                if is_match(self.patterns, pattern, name)? {
+                    matched = true;
+                    self.check(property_schema, property, &property_path, violations)?;
+                }
+            }
+
+            match schema.get("additionalProperties") {
+                Some(JsonValue::Bool(false)) if !matched => violations.push(format!(
+                    "{}: additional property `{}` is not allowed",
+                    path, name
+                )),
+                Some(additional @ JsonValue::Object(_)) if !matched => {
+                    self.check(additional, property, &property_path, violations)?
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn check_combinators(
+        &self,
+        schema: &'s Map<String, JsonValue>,
+        value: &JsonValue,
+        path: &str,
+        violations: &mut Vec<String>,
+    ) -> Result<(), CliError> {
+        let subschemas = |keyword: &str| {
+            schema
+                .get(keyword)
+                .and_then(|s| s.as_array())
+                .map(|s| s.iter().collect::<Vec<&JsonValue>>())
+        };
+
+        if let Some(all_of) = subschemas("allOf") {
+            for subschema in all_of {
+                self.check(subschema, value, path, violations)?;
+            }
+        }
+        if let Some(any_of) = subschemas("anyOf") {
+            let mut matched = false;
+            for subschema in any_of {
+                if self.is_valid(subschema, value, path)? {
+                    matched = true;
+                    break;
+                }
+            }
+            if !matched {
+                violations.push(format!("{}: value matches none of `anyOf`", path));
+            }
+        }
+        if let Some(one_of) = subschemas("oneOf") {
+            let mut matched = 0;
+            for subschema in one_of {
+                if self.is_valid(subschema, value, path)? {
+                    matched += 1;
+                }
+            }
+            if matched != 1 {
+                violations.push(format!(
+                    "{}: value matches {} schemas of `oneOf` instead of one",
+                    path, matched
+                ));
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.is_valid(not, value, path)? {
+                violations.push(format!("{}: value matches `not`", path));
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = if self.is_valid(condition, value, path)? {
+                schema.get("then")
+            } else {
+                schema.get("else")
+            };
+            if let Some(branch) = branch {
+                self.check(branch, value, path, violations)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_valid(
+        &self,
+        schema: &'s JsonValue,
+        value: &JsonValue,
+        path: &str,
+    ) -> Result<bool, CliError> {
+        let mut violations = Vec::new();
+        self.check(schema, value, path, &mut violations)?;
+        Ok(violations.is_empty())
+    }
+
+    /// Resolves a reference within the schema, `#` or a JSON pointer like `#/definitions/Address`
+    fn resolve(&self, reference: &str) -> Result<&'s JsonValue, CliError> {
+        let pointer = match reference.strip_prefix('#') {
+            Some(pointer) => pointer,
+            None => {
+                return Err(CliError::Schema(format!(
+                    "only local references are supported, `{}` found",
+                    reference
+                )))
+            }
+        };
+
+        pointer
+            .split('/')
+            .skip(1)
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .try_fold(self.root, |schema, token| match schema {
+                JsonValue::Object(object) => object.get(&token),
+                JsonValue::Array(items) => token.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            })
+            .ok_or_else(|| CliError::Schema(format!("cannot resolve reference `{}`", reference)))
+    }
+}
+
+fn check_number<F: FnMut(String)>(schema: &Map<String, JsonValue>, n: f64, fail: &mut F) {
+    let limit = |keyword: &str| schema.get(keyword).and_then(|l| l.as_f64());
+    // draft 4 has boolean exclusive limits which modify `minimum` and `maximum`
+    let is_exclusive = |keyword: &str| schema.get(keyword) == Some(&JsonValue::Bool(true));
+
+    if let Some(min) = limit("minimum") {
+        if n < min || (n == min && is_exclusive("exclusiveMinimum")) {
+            fail(format!("{} is less than the minimum of {}", n, min));
+        }
+    }
+    if let Some(max) = limit("maximum") {
+        if n > max || (n == max && is_exclusive("exclusiveMaximum")) {
+            fail(format!("{} is greater than the maximum of {}", n, max));
+        }
+    }
+    if let Some(min) = limit("exclusiveMinimum") {
+        if n <= min {
+            fail(format!("{} is not greater than {}", n, min));
+        }
+    }
+    if let Some(max) = limit("exclusiveMaximum") {
+        if n >= max {
+            fail(format!("{} is not less than {}", n, max));
+        }
+    }
+    if let Some(divisor) = limit("multipleOf") {
+        let quotient = n / divisor;
+        if divisor > 0.0 && (quotient - quotient.round()).abs() > 1e-9 {
+            fail(format!("{} is not a multiple of {}", n, divisor));
+        }
+    }
+}
+
+fn check_string<F: FnMut(String)>(
+    schema: &Map<String, JsonValue>,
+    s: &str,
+    patterns: &Patterns,
+    fail: &mut F,
+) -> Result<(), CliError> {
+    let length = s.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+        if length < min {
+            fail(format!("string is shorter than {} characters", min));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()) {
+        if length > max {
+            fail(format!("string is longer than {} characters", max));
+        }
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(|p| p.as_str()) {
+        if !is_match(patterns, pattern, s)? {
+            fail(format!("`{}` does not match `{}`", s, pattern));
+        }
+    }
+    Ok(())
+}
+
+/// Patterns of schemas and subschemas, values of `enum`, `const`, `default` and `examples` are data
+fn collect_patterns(schema: &JsonValue, patterns: &mut Patterns) -> Result<(), CliError> {
+    match schema {
+        JsonValue::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("enum", _) | ("const", _) | ("default", _) | ("examples", _) => {}
+                    ("pattern", JsonValue::String(pattern)) => add_pattern(pattern, patterns)?,
+                    ("patternProperties", JsonValue::Object(properties)) => {
+                        for (pattern, property_schema) in properties {
+                            add_pattern(pattern, patterns)?;
+                            collect_patterns(property_schema, patterns)?;
+                        }
+                    }
+                    _ => collect_patterns(value, patterns)?,
+                }
+            }
+            Ok(())
+        }
+        JsonValue::Array(items) => items
+            .iter()
+            .try_for_each(|item| collect_patterns(item, patterns)),
+        _ => Ok(()),
+    }
+}
+
+fn add_pattern(pattern: &str, patterns: &mut Patterns) -> Result<(), CliError> {
+    if !patterns.contains_key(pattern) {
+        patterns.insert(pattern.to_string(), compile(pattern)?);
+    }
+    Ok(())
+}
+
+/// Patterns are compiled with the schema, ones missed by it are compiled on the spot
+fn is_match(patterns: &Patterns, pattern: &str, s: &str) -> Result<bool, CliError> {
+    match patterns.get(pattern) {
+        Some(regex) => Ok(regex.is_match(s)),
+        None => compile(pattern).map(|regex| regex.is_match(s)),
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, CliError> {
+    Regex::new(pattern).map_err(|e| {
+        CliError::Schema(format!(
+            "invalid pattern `{}`, lookaround and backreferences are not supported: {}",
+            pattern, e
+        ))
+    })
+}
+
+fn is_type(value: &JsonValue, type_name: &str) -> bool {
+    match (type_name, value) {
+        ("null", JsonValue::Null)
+        | ("boolean", JsonValue::Bool(_))
+        | ("number", JsonValue::Number(_))
+        | ("string", JsonValue::String(_))
+        | ("array", JsonValue::Array(_))
+        | ("object", JsonValue::Object(_)) => true,
+        ("integer", JsonValue::Number(n)) => {
+            n.is_i64() || n.is_u64() || matches!(n.as_f64(), Some(f) if f.fract() == 0.0)
+        }
+        _ => false,
+    }
+}
+
+fn kind(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn violations(schema: JsonValue, value: JsonValue) -> Vec<String> {
+        validate(&schema, &value).unwrap()
+    }
+
+    #[test]
+    fn resolves_references() {
+        let schema = json!({
+            "definitions": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": {"type": "integer"},
+                        "next": {"$ref": "#/definitions/node"}
+                    },
+                    "required": ["value"]
+                }
+            },
+            "$ref": "#/definitions/node"
+        });
+
+        assert!(violations(schema.clone(), json!({"value": 1, "next": {"value": 2}})).is_empty());
+        assert_eq!(
+            violations(schema, json!({"value": 1, "next": {"value": "2"}})),
+            vec!["$.next.value: string is not of type integer"]
+        );
+    }
+
+    #[test]
+    fn rejects_circular_references() {
+        let to_root = json!({"$ref": "#"});
+        assert!(validate(&to_root, &json!(1)).is_err());
+
+        let to_itself = json!({
+            "definitions": {"loop": {"$ref": "#/definitions/loop"}},
+            "properties": {"a": {"$ref": "#/definitions/loop"}}
+        });
+        assert!(validate(&to_itself, &json!({"a": 1})).is_err());
+
+        let through_all_of = json!({"allOf": [{"$ref": "#"}]});
+        assert!(validate(&through_all_of, &json!(1)).is_err());
+
+        let unresolved = json!({"$ref": "#/definitions/missing"});
+        assert!(validate(&unresolved, &json!(1)).is_err());
+    }
+
+    #[test]
+    fn matches_exactly_one_of() {
+        let schema = json!({
+            "oneOf": [
+                {"type": "integer"},
+                {"type": "number", "minimum": 10}
+            ]
+        });
+
+        assert!(violations(schema.clone(), json!(5)).is_empty());
+        assert!(violations(schema.clone(), json!(10.5)).is_empty());
+        assert_eq!(
+            violations(schema.clone(), json!(20)),
+            vec!["$: value matches 2 schemas of `oneOf` instead of one"]
+        );
+        assert_eq!(
+            violations(schema, json!("a")),
+            vec!["$: value matches 0 schemas of `oneOf` instead of one"]
+        );
+    }
+
+    #[test]
+    fn checks_additional_properties() {
+        let closed = json!({
+            "properties": {"name": {"type": "string"}},
+            "patternProperties": {"^x-": {"type": "string"}},
+            "additionalProperties": false
+        });
+        assert!(violations(closed.clone(), json!({"name": "a", "x-b": "c"})).is_empty());
+        assert_eq!(
+            violations(closed, json!({"name": "a", "age": 1})),
+            vec!["$: additional property `age` is not allowed"]
+        );
+
+        let typed = json!({
+            "properties": {"name": {"type": "string"}},
+            "additionalProperties": {"type": "integer"}
+        });
+        assert!(violations(typed.clone(), json!({"name": "a", "age": 1})).is_empty());
+        assert_eq!(
+            violations(typed, json!({"name": "a", "age": "1"})),
+            vec!["$.age: string is not of type integer"]
+        );
+    }
+
+    #[test]
+    fn compiles_patterns_once() {
+        let schema = Schema::compile(json!({
+            "properties": {"id": {"type": "string", "pattern": "^[a-z]+$"}},
+            "patternProperties": {"^x-": {"pattern": "[0-9]"}},
+            "default": {"pattern": "("}
+        }))
+        .unwrap();
+        assert_eq!(schema.patterns.len(), 3);
+
+        assert!(schema
+            .validate(&json!({"id": "abc", "x-a": "1"}))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            schema.validate(&json!({"id": "ABC", "x-a": "b"})).unwrap(),
+            vec![
+                "$.id: `ABC` does not match `^[a-z]+$`",
+                "$.x-a: `b` does not match `[0-9]`"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_patterns() {
+        assert!(matches!(
+            Schema::compile(json!({"pattern": "^(?!admin)"})),
+            Err(CliError::Schema(_))
+        ));
+        assert!(matches!(
+            Schema::compile(json!({"patternProperties": {"(a)\\1": {}}})),
+            Err(CliError::Schema(_))
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+//! Serialization of JSON payloads into Kafka messages and back.
+//!
+//! - [`avro`] maps JSON to Avro values and back, encodes datums and decodes messages
+//! - [`wire`] frames datums with schema ids or fingerprints and splits messages into both
+//! - [`encoder`] serializes JSON into framed Avro, JSON Schema or Protobuf messages of local
+//!   schemas or ones registered under a subject
+//! - [`registry`] is a client of Confluent, Apicurio and AWS Glue schema registries, and a
+//!   [`registry::RegistryServer`] of the Confluent API
+//! - [`producer`] and [`consumer`] send and read messages of a topic
+//! - [`admin`] lists, describes, creates and deletes topics and alters their configs
+//!
+//! Clients are configured with [`registry::RegistryConfig`], [`producer::ProducerConfig`] and
+//! [`consumer::ConsumerConfig`], topics with [`admin::TopicConfig`].
+//!
+//! Messages are built the way the command line tool does
+//! ```
+//! use kafka_avro_cli::encoder::Encoder;
+//! use kafka_avro_cli::registry::RegistryType;
+//! use kafka_avro_cli::wire::{self, Header};
+//! # fn main() -> Result<(), kafka_avro_cli::error::CliError> {
+//! # let raw_schema = r#"{"type":"record","name":"Order","fields":[{"name":"id","type":"string"}]}"#;
+//!
+//! let schema = kafka_avro_cli::avro::parse_schema(raw_schema)?;
+//! let encoder = Encoder::Avro(Header::Confluent(42), schema);
+//! let message = encoder.encode(&serde_json::json!({"id": "42"}))?;
+//!
+//! let (header, datum) = wire::split(&message, Some(RegistryType::Confluent))?;
+//! assert!(header == Header::Confluent(42));
+//! # Ok(())
+//! # }
+//! ```
+//! Clients are built from configs, independently of command line arguments
+//! ```no_run
+//! use kafka_avro_cli::consumer::{Consumer, ConsumerConfig};
+//! use kafka_avro_cli::producer::{Producer, ProducerConfig};
+//! use kafka_avro_cli::registry::{RegistryClient, RegistryConfig};
+//! use std::time::Duration;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!
+//! let registry = RegistryClient::new(
+//!     RegistryConfig::new("https://registry:8081")
+//!         .basic_auth("user", "secret")
+//!         .timeout(Duration::from_secs(10)),
+//! )?;
+//! let producer = Producer::new(ProducerConfig::new("localhost:9092", "orders").set("linger.ms", "5"))?;
+//! let consumer = Consumer::new(ConsumerConfig::new("localhost:9092", "orders").start_offset(42))?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use avro_rs;
+
+pub mod admin;
+pub mod avro;
+pub mod canonical;
+pub mod compat;
+pub mod consumer;
+pub mod encoder;
+pub mod error;
+pub mod json_schema;
+pub mod producer;
+pub mod protobuf;
+pub mod registry;
+pub mod ssl;
+pub mod wire;
+
+// used by the command line tool, not a part of the API
+#[doc(hidden)]
+pub mod context;
+#[doc(hidden)]
+pub mod data;
+#[doc(hidden)]
+pub mod generate;
+#[doc(hidden)]
+pub mod infer;
+#[doc(hidden)]
+pub mod ocf;
+#[doc(hidden)]
+pub mod template;
+
+mod idl;
+mod named;
@@ -1,73 +1,532 @@
-use crate::context::{parse_app_ctx, AppCmd, AppCtx};
-use crate::error::CliError;
-use crate::producer::Producer;
 use avro_rs::types::Value as AvroValue;
-use avro_rs::{AvroResult, Schema};
-use clap::{crate_version, App, AppSettings, Arg, ArgMatches};
+use avro_rs::{Schema, Writer};
+use clap::{crate_version, App, AppSettings, Arg, ArgGroup, ArgMatches};
+use kafka_avro_cli::admin::TopicAdmin;
+use kafka_avro_cli::compat::Level;
+use kafka_avro_cli::consumer::Consumer;
+use kafka_avro_cli::context::{
+    parse_app_ctx, parse_basic_auth, parse_config_entry, parse_duration, AppCmd, AppCtx, CompatCtx,
+    ConsumeCtx, GenerateCtx, InferCtx, RegistryCmd, SchemaCmd, ServeCtx, TopicCmd,
+};
+use kafka_avro_cli::encoder::{Encoder, Format};
+use kafka_avro_cli::error::CliError;
+use kafka_avro_cli::generate::{parse_field_generator, Generator};
+use kafka_avro_cli::producer::Producer;
+use kafka_avro_cli::registry::{self, parse_reference, RegistryClient, RegistryServer, SchemaId};
+use kafka_avro_cli::wire;
+use kafka_avro_cli::{avro, canonical, compat, data, infer, ocf, template};
 use serde_json::Value as JsonValue;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+use std::time::Instant;
 
-pub mod error;
-pub mod registry;
-
-mod avro;
-mod context;
-mod data;
-mod producer;
-
-fn main() -> Result<(), CliError> {
-    let arg_matches = match_args();
-    let app_ctx = &parse_app_ctx(&arg_matches)?;
+fn main() {
+    let arg_matches = match match_args() {
+        Ok(arg_matches) => arg_matches,
+        // help and version
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(CliError::Usage(e.to_string()).exit_code());
+        }
+    };
+    if let Err(e) = parse_app_ctx(&arg_matches).and_then(|app_ctx| run(&app_ctx)) {
+        eprintln!("Error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
 
+fn run(app_ctx: &AppCtx) -> Result<(), CliError> {
     match app_ctx.command {
-        AppCmd::Produce => produce(&app_ctx),
-        AppCmd::Consume => Ok(()),
+        AppCmd::Produce => produce(app_ctx),
+        AppCmd::Generate(ref generate_ctx) => generate(app_ctx, generate_ctx),
+        AppCmd::Schema(SchemaCmd::Infer(ref infer_ctx)) => infer_schema(app_ctx, infer_ctx),
+        AppCmd::Schema(SchemaCmd::Describe) => describe_schema(app_ctx),
+        AppCmd::Schema(SchemaCmd::Compat(ref compat_ctx)) => check_compatibility(compat_ctx),
+        AppCmd::Registry(ref registry_cmd) => registry(app_ctx, registry_cmd),
+        AppCmd::ServeRegistry(ref serve_ctx) => serve_registry(serve_ctx),
+        AppCmd::Topic(ref topic_cmd) => topic(app_ctx, topic_cmd),
+        AppCmd::Consume(ref consume_ctx) => consume(app_ctx, consume_ctx),
     }
 }
 
 fn produce(ctx: &AppCtx) -> Result<(), CliError> {
+    if ctx.is_ocf {
+        return produce_ocf(ctx);
+    }
+
     // read payload
     let payload = match (&ctx.payload, &ctx.payload_file) {
         (Some(ref raw_payload), _) => vec![raw_payload.clone()],
         (None, Some(ref path)) => data::read_payload(path)?,
-        _ => panic!("payload expected"),
+        _ => return Err(CliError::Usage("payload expected".to_string())),
     };
 
-    //is it Avro?
-    let encoded: Vec<Vec<u8>> = if ctx.is_avro {
-        let jsons = payload
+    let encoder = match ctx.format {
+        None => None,
+        Some(format) => Some(encoder(ctx, format, ctx.avro_ctx.schema.as_deref())?),
+    };
+
+    let producer = producer(ctx)?;
+    let started = Instant::now();
+    let mut seq: u64 = 0;
+    let mut passes: u64 = 0;
+
+    while ctx.repeat.is_due(passes, started.elapsed()) {
+        let rendered: Vec<String> = payload
             .iter()
-            .map(|raw_line| data::parse_json(raw_line))
-            .collect::<serde_json::Result<Vec<JsonValue>>>()?;
-
-        // use schema-registry?
-        if ctx.avro_ctx.registry_url.is_none() {
-            let schema =
-                avro::parse_schema(ctx.avro_ctx.schema.as_ref().expect("schema expected"))?;
-            let avros = jsons_to_avro(jsons, &schema)?;
-            encode(avros, |avro: AvroValue| avro::encode(avro, &schema))?
-        } else {
-            let (schema_id, schema) = avro::get_registered_schema(&ctx)?;
-            let avros = jsons_to_avro(jsons, &schema)?;
-            encode(avros, |avro: AvroValue| {
-                avro::encode_with_schema_id(avro, &schema, schema_id)
-            })?
+            .map(|raw| {
+                seq += 1;
+                template::render(raw, seq - 1)
+            })
+            .collect();
+
+        producer.produce(encode_payload(rendered, encoder.as_ref())?)?;
+        passes += 1;
+    }
+
+    Ok(())
+}
+
+fn generate(ctx: &AppCtx, generate_ctx: &GenerateCtx) -> Result<(), CliError> {
+    let encoder = encoder(ctx, Format::Avro, ctx.avro_ctx.schema.as_deref())?;
+    let schema = encoder.avro_schema().expect("Avro schema expected");
+    let mut generator = Generator::new(generate_ctx.seed, &generate_ctx.generators);
+
+    let avros = (0..generate_ctx.count)
+        .map(|_| generator.generate(&schema.root()))
+        .collect::<Result<Vec<AvroValue>, CliError>>()?;
+
+    // print if there is nowhere to send
+    if ctx.kafka_ctx.hosts.is_none() {
+        for avro in &avros {
+            println!("{}", avro::to_json(avro));
         }
+        return Ok(());
+    }
+
+    producer(ctx)?
+        .produce(encode_avros(avros, &encoder)?)
+        .map_err(|e| e.into())
+}
+
+fn infer_schema(ctx: &AppCtx, infer_ctx: &InferCtx) -> Result<(), CliError> {
+    let payload = data::read_payload(ctx.payload_file.as_ref().expect("payload file expected"))?;
+    let jsons = payload
+        .iter()
+        .map(|raw_line| data::parse_json(raw_line))
+        .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+
+    let schema = infer::infer_schema(&jsons, &infer_ctx.name, infer_ctx.namespace.as_deref())?;
+    let raw_schema = serde_json::to_string_pretty(&schema)?;
+
+    // make sure the schema is accepted and maps all the samples
+    let parsed = avro::parse_schema(&raw_schema)?;
+    jsons_to_avro(jsons, &parsed)?;
+
+    println!("{}", raw_schema);
+    Ok(())
+}
+
+fn describe_schema(ctx: &AppCtx) -> Result<(), CliError> {
+    let raw_schema = ctx.avro_ctx.schema.as_ref().ok_or_else(schema_expected)?;
+    // make sure the schema is valid
+    avro::parse_schema(raw_schema)?;
+
+    let schema: JsonValue = serde_json::from_str(raw_schema)?;
+    let canonical_form = canonical::canonical_form(&schema);
+    let bytes = canonical_form.as_bytes();
+
+    println!("canonical form: {}", canonical_form);
+    println!("crc-64-avro: {:016x}", canonical::fingerprint64(bytes));
+    println!("md5: {}", canonical::md5_fingerprint(bytes));
+    println!("sha-256: {}", canonical::sha256_fingerprint(bytes));
+    println!(
+        "normalized:\n{}",
+        serde_json::to_string_pretty(&canonical::normalize(&schema))?
+    );
+    Ok(())
+}
+
+/// Prints each incompatibility of the new schema and fails if there are any
+fn check_compatibility(compat_ctx: &CompatCtx) -> Result<(), CliError> {
+    let schemas = compat_ctx
+        .schemas
+        .iter()
+        .map(|(_, raw_schema)| {
+            // make sure the schema is valid
+            avro::parse_schema(raw_schema)?;
+            serde_json::from_str(raw_schema).map_err(|e| e.into())
+        })
+        .collect::<Result<Vec<JsonValue>, CliError>>()?;
+
+    let (new, previous) = schemas.split_last().expect("schemas expected");
+    let incompatibilities = compat::check(previous, new, compat_ctx.level);
+
+    for incompatibility in &incompatibilities {
+        println!(
+            "{} with {}: {}: {}",
+            incompatibility.direction,
+            compat_ctx.schemas[incompatibility.previous].0,
+            incompatibility.path,
+            incompatibility.message
+        );
+    }
+
+    if incompatibilities.is_empty() {
+        println!("compatible");
+        Ok(())
     } else {
-        payload.into_iter().map(|s| s.into_bytes()).collect()
+        Err(CliError::Schema(format!(
+            "{} incompatibilities found",
+            incompatibilities.len()
+        )))
+    }
+}
+
+fn registry(ctx: &AppCtx, registry_cmd: &RegistryCmd) -> Result<(), CliError> {
+    let config = ctx
+        .registry_config()
+        .ok_or_else(|| CliError::Usage("--registry-url expected".to_string()))?;
+    let client = RegistryClient::new(config)?;
+
+    match registry_cmd {
+        RegistryCmd::Subjects => {
+            for subject in client.list_subjects()? {
+                println!("{}", subject);
+            }
+        }
+        RegistryCmd::Versions { subject } => {
+            for version in client.list_versions(subject)? {
+                println!("{}", version);
+            }
+        }
+        RegistryCmd::Get { subject, version } => {
+            let registered = client.get_schema_by_version(subject, version)?;
+            eprintln!(
+                "subject: {}, version: {}, id: {}",
+                registered.subject, registered.version, registered.id
+            );
+            for reference in &registered.references {
+                eprintln!(
+                    "reference: {} -> {}:{}",
+                    reference.name, reference.subject, reference.version
+                );
+            }
+            println!("{}", pretty_schema(&registered.schema)?);
+        }
+        RegistryCmd::GetById(id) => {
+            println!("{}", pretty_schema(&client.get_schema_by_id(*id)?.schema)?);
+        }
+        RegistryCmd::Delete {
+            subject,
+            version: Some(version),
+            permanent,
+        } => {
+            println!("{}", client.delete_version(subject, version, *permanent)?);
+        }
+        RegistryCmd::Delete {
+            subject,
+            version: None,
+            permanent,
+        } => {
+            for version in client.delete_subject(subject, *permanent)? {
+                println!("{}", version);
+            }
+        }
+        RegistryCmd::Compatibility { subject, level } => {
+            let level = match level {
+                Some(level) => client.set_compatibility(subject.as_deref(), level)?,
+                None => client.get_compatibility(subject.as_deref())?,
+            };
+            println!("{}", level);
+        }
+        RegistryCmd::Mode {
+            subject,
+            mode,
+            force,
+        } => {
+            let mode = match mode {
+                Some(mode) => client.set_mode(subject.as_deref(), mode, *force)?,
+                None => client.get_mode(subject.as_deref())?,
+            };
+            println!("{}", mode);
+        }
+    }
+
+    Ok(())
+}
+
+fn topic(ctx: &AppCtx, topic_cmd: &TopicCmd) -> Result<(), CliError> {
+    let admin = topic_admin(ctx)?;
+    let topic = || {
+        ctx.kafka_ctx
+            .topic
+            .as_deref()
+            .ok_or_else(|| CliError::Usage("topic expected".to_string()))
+    };
+
+    match topic_cmd {
+        TopicCmd::List => {
+            for topic in admin.list_topics()? {
+                println!("{}", topic);
+            }
+        }
+        TopicCmd::Describe => {
+            let description = admin.describe_topic(topic()?)?;
+            println!(
+                "topic: {}, partitions: {}",
+                description.name,
+                description.partitions.len()
+            );
+            for partition in &description.partitions {
+                println!(
+                    "partition: {}, leader: {}, replicas: {:?}, isr: {:?}",
+                    partition.id, partition.leader, partition.replicas, partition.isr
+                );
+            }
+            for (key, value) in &description.configs {
+                println!("{}={}", key, value);
+            }
+        }
+        TopicCmd::Create(config) => admin.create_topic(config)?,
+        TopicCmd::Delete => admin.delete_topic(topic()?)?,
+        TopicCmd::AlterConfig { set, delete } => admin.alter_topic_config(topic()?, set, delete)?,
+    }
+
+    Ok(())
+}
+
+fn topic_admin(ctx: &AppCtx) -> Result<TopicAdmin, CliError> {
+    let hosts = ctx
+        .kafka_ctx
+        .hosts
+        .as_deref()
+        .ok_or_else(|| CliError::Usage("--hosts expected".to_string()))?;
+    Ok(TopicAdmin::new(hosts, ctx.ssl.as_ref())?)
+}
+
+/// Producer of the topic, created first if asked to
+fn producer(ctx: &AppCtx) -> Result<Producer, CliError> {
+    let config = ctx.producer_config()?;
+    if let Some(ref topic_config) = ctx.create_topic {
+        if topic_admin(ctx)?.create_topic_if_missing(topic_config)? {
+            eprintln!("created topic {}", topic_config.name());
+        }
+    }
+    Ok(Producer::new(config)?)
+}
+
+fn serve_registry(serve_ctx: &ServeCtx) -> Result<(), CliError> {
+    let mut server =
+        RegistryServer::bind(&serve_ctx.listen, serve_ctx.dir.as_deref().map(Path::new))?;
+    for (user, password) in &serve_ctx.basic_auth {
+        server = server.basic_auth(user, password);
+    }
+    for (user, password) in &serve_ctx.read_only_basic_auth {
+        server = server.read_only_basic_auth(user, password);
+    }
+    eprintln!(
+        "schema registry listening on http://{}",
+        server.local_addr()?
+    );
+    server.run()?;
+    Ok(())
+}
+
+/// Produces records of an Avro Object Container File as they are, without mapping them from JSON
+fn produce_ocf(ctx: &AppCtx) -> Result<(), CliError> {
+    let path = ctx.payload_file.as_ref().expect("payload file expected");
+
+    // records are resolved to the given schema, if any
+    let writer_schema = ocf::read_writer_schema(path)?;
+    let raw_schema = ctx.avro_ctx.schema.as_deref().unwrap_or(&writer_schema);
+    let encoder = encoder(ctx, Format::Avro, Some(raw_schema))?;
+
+    let avros = ocf::read_values(path, encoder.avro_schema().expect("Avro schema expected"))?;
+    let payload = encode_avros(avros, &encoder)?;
+
+    let producer = producer(ctx)?;
+    let started = Instant::now();
+    let mut passes: u64 = 0;
+
+    while ctx.repeat.is_due(passes, started.elapsed()) {
+        producer.produce(payload.clone())?;
+        passes += 1;
+    }
+
+    Ok(())
+}
+
+fn consume(ctx: &AppCtx, consume_ctx: &ConsumeCtx) -> Result<(), CliError> {
+    let reader_schema = match consume_ctx.reader_schema {
+        Some(ref raw_schema) => Some(avro::parse_schema(raw_schema)?),
+        None => None,
     };
+    let registry_client = match ctx.registry_config() {
+        Some(config) => Some(RegistryClient::new(config)?),
+        None => None,
+    };
+    let mut decoder =
+        avro::Decoder::new(registry_client, &consume_ctx.writer_schemas, reader_schema)?;
+    if let Some(wire_format) = consume_ctx.wire_format {
+        decoder = decoder.wire_format(wire_format);
+    }
+    let mut consumer = Consumer::new(ctx.consumer_config(consume_ctx)?)?;
 
-    Producer::produce(&ctx, encoded).map_err(|e| e.into())
+    match consume_ctx.output {
+        Some(ref path) => export_ocf(&mut consumer, &mut decoder, path, consume_ctx),
+        None => {
+            while let Some(message) = consumer.next_payload()? {
+                let (_, value) = decoder.decode(&message)?;
+                println!("{}", avro::to_json(&value));
+            }
+            Ok(())
+        }
+    }
 }
 
-fn encode<F>(avros: Vec<AvroValue>, mut map: F) -> Result<Vec<Vec<u8>>, CliError>
-where
-    F: FnMut(AvroValue) -> AvroResult<Vec<u8>>,
-{
+/// Writes consumed records into Avro Object Container Files.
+/// Unless records are resolved to the reader schema, a new file is started each time
+/// the writer schema changes.
+fn export_ocf(
+    consumer: &mut Consumer,
+    decoder: &mut avro::Decoder,
+    path: &str,
+    consume_ctx: &ConsumeCtx,
+) -> Result<(), CliError> {
+    let mut next = |decoder: &mut avro::Decoder| match consumer.next_payload()? {
+        Some(message) => decoder.decode(&message).map(Some),
+        None => Ok(None),
+    };
+
+    let mut pending = next(decoder)?;
+    let mut files = 0;
+
+    while let Some((header, value)) = pending.take() {
+        let file_path = ocf::rolled_path(path, files);
+        let schema = decoder.value_schema(header)?.clone();
+        let file = BufWriter::new(File::create(&file_path)?);
+        let mut writer = Writer::with_codec(&schema, file, consume_ctx.codec);
+
+        writer.append(value)?;
+        let mut count: u64 = 1;
+        loop {
+            match next(decoder)? {
+                Some((next_header, value)) if next_header == header => {
+                    writer.append(value)?;
+                    count += 1;
+                }
+                other => {
+                    pending = other;
+                    break;
+                }
+            }
+        }
+        writer.flush()?;
+
+        match header {
+            Some(wire::Header::Confluent(_))
+            | Some(wire::Header::Apicurio(_))
+            | Some(wire::Header::Glue(_)) => eprintln!(
+                "{}: {} records of schema {}",
+                file_path,
+                count,
+                header
+                    .and_then(wire::Header::schema_id)
+                    .expect("schema id expected")
+            ),
+            Some(wire::Header::Fingerprint(fingerprint)) => eprintln!(
+                "{}: {} records of schema with fingerprint {:016x}",
+                file_path, count, fingerprint
+            ),
+            Some(wire::Header::Raw) => eprintln!("{}: {} raw records", file_path, count),
+            None => eprintln!("{}: {} records of the reader schema", file_path, count),
+        }
+        files += 1;
+    }
+
+    Ok(())
+}
+
+/// JSON schemas pretty-printed, Protobuf ones as they are
+fn pretty_schema(raw_schema: &str) -> Result<String, CliError> {
+    match serde_json::from_str::<JsonValue>(raw_schema) {
+        Ok(schema) => serde_json::to_string_pretty(&schema).map_err(|e| e.into()),
+        Err(_) => Ok(raw_schema.to_string()),
+    }
+}
+
+/// Encoder of the schema, which is registered under the subject of the topic if there is a registry.
+/// The latest schema of the subject is used if none is given.
+fn encoder(ctx: &AppCtx, format: Format, raw_schema: Option<&str>) -> Result<Encoder, CliError> {
+    let avro_ctx = &ctx.avro_ctx;
+    let config = match ctx.registry_config() {
+        Some(config) => config,
+        None => {
+            let raw_schema = raw_schema.ok_or_else(schema_expected)?;
+            return Encoder::from_schema(
+                raw_schema,
+                format,
+                avro_ctx.wire_format,
+                avro_ctx.record.as_deref(),
+            );
+        }
+    };
+
+    let topic = ctx
+        .kafka_ctx
+        .topic
+        .as_ref()
+        .ok_or_else(|| CliError::Usage("--topic expected".to_string()))?;
+    let normalized = match raw_schema {
+        Some(raw_schema) if avro_ctx.normalize => {
+            let schema: JsonValue = serde_json::from_str(raw_schema)?;
+            Some(canonical::normalize(&schema).to_string())
+        }
+        _ => None,
+    };
+
+    Encoder::from_registry(
+        &RegistryClient::new(config)?,
+        &registry::get_subject(topic),
+        normalized.as_deref().or(raw_schema),
+        &avro_ctx.references,
+        format,
+        avro_ctx.wire_format,
+        avro_ctx.record.as_deref(),
+    )
+}
+
+fn schema_expected() -> CliError {
+    CliError::Usage(
+        "one of --schema, --schema-file, --schema-dir or --registry-url expected".to_string(),
+    )
+}
+
+fn encode_payload(
+    payload: Vec<String>,
+    encoder: Option<&Encoder>,
+) -> Result<Vec<Vec<u8>>, CliError> {
+    match encoder {
+        None => Ok(payload.into_iter().map(|s| s.into_bytes()).collect()),
+        Some(encoder) => {
+            let jsons = payload
+                .iter()
+                .map(|raw_line| data::parse_json(raw_line))
+                .collect::<serde_json::Result<Vec<JsonValue>>>()?;
+            jsons.iter().map(|json| encoder.encode(json)).collect()
+        }
+    }
+}
+
+fn encode_avros(avros: Vec<AvroValue>, encoder: &Encoder) -> Result<Vec<Vec<u8>>, CliError> {
     avros
         .into_iter()
-        .map(|avro| map(avro))
-        .collect::<Result<Vec<Vec<u8>>, avro_rs::Error>>()
-        .map_err(|e| e.into())
+        .map(|avro| encoder.encode_value(avro))
+        .collect()
 }
 
 fn jsons_to_avro(jsons: Vec<JsonValue>, schema: &Schema) -> Result<Vec<AvroValue>, CliError> {
@@ -77,7 +536,7 @@ fn jsons_to_avro(jsons: Vec<JsonValue>, schema: &Schema) -> Result<Vec<AvroValue
         .collect::<Result<Vec<AvroValue>, CliError>>()
 }
 
-fn match_args() -> ArgMatches {
+fn match_args() -> clap::Result<ArgMatches> {
     App::new("Kafka Avro CLI")
         .version(crate_version!())
         .about("Produces/consumes Avro serialized messages into Kafka")
@@ -85,10 +544,384 @@ fn match_args() -> ArgMatches {
         .setting(AppSettings::SubcommandRequired)
         .setting(AppSettings::ArgRequiredElseHelp)
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            App::new("generate")
+                .about("Generates random messages which conform to an Avro schema")
+                .args(ssl_args())
+                .args(registry_ssl_args())
+                .arg(
+                    Arg::new("hosts")
+                        .about("Kafka hosts. Messages are printed as JSON if not present")
+                        .short('h')
+                        .long("hosts")
+                        .takes_value(true)
+                        .value_name("host:port[,host:port[...]]")
+                        .requires("topic"),
+                )
+                .arg(
+                    Arg::new("topic")
+                        .about("Topic name")
+                        .short('t')
+                        .long("topic")
+                        .takes_value(true)
+                        .value_name("TOPIC"),
+                )
+                .arg(
+                    Arg::new("count")
+                        .about("Number of messages to generate")
+                        .short('n')
+                        .long("count")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(|s| s.parse::<u64>()),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .about("Seed of the random generator to get reproducible output")
+                        .long("seed")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(|s| s.parse::<u64>()),
+                )
+                .arg(
+                    Arg::new("generator")
+                        .about("Generator of a field, where KIND:ARG is one of range:MIN..MAX, regex:PATTERN or choice:A|B|C")
+                        .short('g')
+                        .long("generator")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_name("FIELD.PATH=KIND:ARG")
+                        .validator(parse_field_generator),
+                )
+                .arg(
+                    Arg::new("schema")
+                        .about("Avro schema of generated messages")
+                        .short('s')
+                        .long("schema")
+                        .multiple_values(false)
+                        .value_name("SCHEMA JSON")
+                        .required_unless_present_any(&["schema-file", "schema-dir", "registry-url"])
+                        .conflicts_with_all(&["schema-file", "schema-dir"])
+                )
+                .arg(
+                    Arg::new("schema-file")
+                        .about("File containing the Avro schema of generated messages, .avsc, .avpr or .avdl. Repeat for schemas which refer to each other")
+                        .long("schema-file")
+                        .multiple_values(false)
+                        .multiple_occurrences(true)
+                        .value_name("PATH")
+                        .required_unless_present_any(&["schema", "schema-dir", "registry-url"])
+                        .conflicts_with("schema"),
+                )
+                .arg(
+                    Arg::new("schema-dir")
+                        .about("Directory with .avsc, .avpr and .avdl files which refer to each other")
+                        .long("schema-dir")
+                        .multiple_values(false)
+                        .value_name("PATH")
+                        .required_unless_present_any(&["schema", "schema-file", "registry-url"])
+                        .conflicts_with("schema"),
+                )
+                .arg(
+                    Arg::new("record")
+                        .about("Fullname of the named type to use, when schema files declare several of them")
+                        .long("record")
+                        .multiple_values(false)
+                        .value_name("FULLNAME")
+                        .conflicts_with("schema"),
+                )
+                .arg(wire_format_arg())
+                .arg(
+                    Arg::new("normalize")
+                        .about("Registers the schema normalized: with fullnames, a fixed order of attributes and without whitespaces. Avoids new versions which differ in formatting only")
+                        .long("normalize")
+                        .takes_value(false)
+                        .requires("registry-url"),
+                )
+                .arg(
+                    Arg::new("registry-url")
+                        .about("Schema-registry url, comma-separated urls of the cluster nodes, or file:///path of a directory the tool keeps a registry of its own in")
+                        .long("registry-url")
+                        .multiple_values(false)
+                        .value_name("http[s]://host:port")
+                        .requires("topic"),
+                )
+                .args(registry_type_args()),
+        )
+        .subcommand(
+            App::new("schema")
+                .about("Avro schema tools")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("infer")
+                        .about("Infers an Avro schema from sample JSON payloads")
+                        .arg(
+                            Arg::new("payload-file")
+                                .about("New-line delimited file. Each row is a sample JSON object")
+                                .long("payload-file")
+                                .multiple_values(false)
+                                .value_name("PATH")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .about("Name of the root record")
+                                .long("name")
+                                .takes_value(true)
+                                .value_name("NAME")
+                                .default_value("Record"),
+                        )
+                        .arg(
+                            Arg::new("namespace")
+                                .about("Namespace of the schema")
+                                .long("namespace")
+                                .takes_value(true)
+                                .value_name("NAMESPACE"),
+                        ),
+                )
+                .subcommand(
+                    App::new("describe")
+                        .about("Prints Parsing Canonical Form, fingerprints and normalized form of a schema")
+                        .args(schema_file_args()),
+                )
+                .subcommand(
+                    App::new("compat")
+                        .about("Checks compatibility of a new schema with previous ones, without schema-registry")
+                        .arg(
+                            Arg::new("schemas")
+                                .about("Schema files from the oldest to the new one")
+                                .index(1)
+                                .multiple_values(true)
+                                .min_values(2)
+                                .value_name("SCHEMA FILE")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("level")
+                                .about("Compatibility level: backward, forward, full, backward_transitive, forward_transitive or full_transitive. Non-transitive levels check against the latest previous schema only")
+                                .long("level")
+                                .takes_value(true)
+                                .value_name("LEVEL")
+                                .default_value("backward")
+                                .validator(Level::parse),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("registry")
+                .about("Schema-registry subjects and versions management")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("subjects")
+                        .about("Lists subjects")
+                        .args(registry_args()),
+                )
+                .subcommand(
+                    App::new("versions")
+                        .about("Lists versions of a subject")
+                        .args(registry_args())
+                        .arg(subject_arg()),
+                )
+                .subcommand(
+                    App::new("get")
+                        .about("Prints a schema registered under a subject")
+                        .args(registry_args())
+                        .arg(subject_arg())
+                        .arg(
+                            Arg::new("version")
+                                .about("Version of the schema")
+                                .long("version")
+                                .takes_value(true)
+                                .value_name("VERSION|latest")
+                                .default_value("latest")
+                                .validator(validate_version),
+                        ),
+                )
+                .subcommand(
+                    App::new("get-id")
+                        .about("Prints a schema by its id")
+                        .args(registry_args())
+                        .arg(
+                            Arg::new("id")
+                                .about("Schema id. Global id in Apicurio, schema version UUID in Glue")
+                                .index(1)
+                                .value_name("ID")
+                                .required(true)
+                                .validator(SchemaId::parse),
+                        ),
+                )
+                .subcommand(
+                    App::new("delete")
+                        .about("Deletes a subject or one of its versions. Prints deleted versions")
+                        .args(registry_args())
+                        .arg(subject_arg())
+                        .arg(
+                            Arg::new("version")
+                                .about("Version to delete. All versions are deleted if not present")
+                                .long("version")
+                                .takes_value(true)
+                                .value_name("VERSION|latest")
+                                .validator(validate_version),
+                        )
+                        .arg(
+                            Arg::new("permanent")
+                                .about("Hard delete. The subject or version has to be soft deleted first")
+                                .long("permanent")
+                                .takes_value(false),
+                        ),
+                )
+                .subcommand(
+                    App::new("config")
+                        .about("Prints or sets the compatibility level of a subject or the global one")
+                        .args(registry_args())
+                        .arg(optional_subject_arg())
+                        .arg(
+                            Arg::new("set")
+                                .about("Compatibility level to set")
+                                .long("set")
+                                .takes_value(true)
+                                .value_name("LEVEL")
+                                .possible_values(&[
+                                    "NONE",
+                                    "BACKWARD",
+                                    "BACKWARD_TRANSITIVE",
+                                    "FORWARD",
+                                    "FORWARD_TRANSITIVE",
+                                    "FULL",
+                                    "FULL_TRANSITIVE",
+                                ]),
+                        ),
+                )
+                .subcommand(
+                    App::new("mode")
+                        .about("Prints or sets the mode of a subject or the global one")
+                        .args(registry_args())
+                        .arg(optional_subject_arg())
+                        .arg(
+                            Arg::new("set")
+                                .about("Mode to set")
+                                .long("set")
+                                .takes_value(true)
+                                .value_name("MODE")
+                                .possible_values(&[
+                                    "READWRITE",
+                                    "READONLY",
+                                    "READONLY_OVERRIDE",
+                                    "IMPORT",
+                                ]),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .about("Switch into IMPORT mode even if schemas are already registered")
+                                .long("force")
+                                .takes_value(false)
+                                .requires("set"),
+                        ),
+                )
+                .subcommand(
+                    App::new("serve")
+                        .about("Runs a schema-registry speaking the Confluent API, with schemas kept in memory or in a directory")
+                        .arg(
+                            Arg::new("listen")
+                                .about("Address to listen on")
+                                .long("listen")
+                                .takes_value(true)
+                                .value_name("ADDRESS")
+                                .default_value("127.0.0.1:8081"),
+                        )
+                        .arg(
+                            Arg::new("dir")
+                                .about("Directory to keep schemas in, they are lost on exit if none")
+                                .long("dir")
+                                .takes_value(true)
+                                .value_name("PATH"),
+                        )
+                        .arg(
+                            Arg::new("basic-auth")
+                                .about("Credentials of a user who may do anything. Requests without credentials of a user are answered with 401 if there are users. Repeatable")
+                                .long("basic-auth")
+                                .takes_value(true)
+                                .multiple_occurrences(true)
+                                .value_name("USER:PASSWORD")
+                                .validator(parse_basic_auth),
+                        )
+                        .arg(
+                            Arg::new("read-only-basic-auth")
+                                .about("Credentials of a user who may only read schemas and settings, other requests are answered with 403. Repeatable")
+                                .long("read-only-basic-auth")
+                                .takes_value(true)
+                                .multiple_occurrences(true)
+                                .value_name("USER:PASSWORD")
+                                .validator(parse_basic_auth),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("topic")
+                .about("Kafka topics management")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("list")
+                        .about("Lists topics")
+                        .args(kafka_admin_args()),
+                )
+                .subcommand(
+                    App::new("describe")
+                        .about("Prints partitions, replicas and config overrides of a topic")
+                        .args(kafka_admin_args())
+                        .arg(topic_name_arg()),
+                )
+                .subcommand(
+                    App::new("create")
+                        .about("Creates a topic")
+                        .args(kafka_admin_args())
+                        .arg(topic_name_arg())
+                        .args(topic_config_args()),
+                )
+                .subcommand(
+                    App::new("delete")
+                        .about("Deletes a topic")
+                        .args(kafka_admin_args())
+                        .arg(topic_name_arg()),
+                )
+                .subcommand(
+                    App::new("alter-config")
+                        .about("Sets or deletes config overrides of a topic, keeping the other ones")
+                        .args(kafka_admin_args())
+                        .arg(topic_name_arg())
+                        .args(topic_override_args())
+                        .arg(
+                            Arg::new("delete-config")
+                                .about("Config override to delete, the broker default applies again")
+                                .long("delete-config")
+                                .takes_value(true)
+                                .multiple_occurrences(true)
+                                .value_name("KEY"),
+                        )
+                        .group(
+                            ArgGroup::new("changes")
+                                .args(&["config", "cleanup-policy", "retention", "delete-config"])
+                                .multiple(true)
+                                .required(true),
+                        ),
+                ),
+        )
         .subcommand(
             App::new("produce")
                 .about("Produces a Kafka message")
                 .args(ssl_args())
+                .args(registry_ssl_args())
+                .arg(
+                    Arg::new("create-topic")
+                        .about("Creates the topic before producing if it does not exist")
+                        .long("create-topic")
+                        .takes_value(false),
+                )
+                .args(
+                    topic_config_args()
+                        .into_iter()
+                        .map(|arg| arg.requires("create-topic")),
+                )
                 .arg(
                     Arg::new("text")
                         .about("Message input is just a plain text. (JSON by default)")
@@ -126,41 +959,424 @@ fn match_args() -> ArgMatches {
                 )
                 .arg(
                     Arg::new("payload-file")
-                        .about("New-line delimited file. Each row is a message payload. (JSON or plain text in respect with `text`)")
+                        .about("New-line delimited file. Each row is a message payload. (JSON or plain text in respect with `text`). Avro payload files with .avro extension are read as Object Container Files")
                         .long("payload-file")
                         .multiple_values(false)
                         .value_name("PATH")
                         .conflicts_with("payload")
                         .required_unless_present("payload")
                 )
+                .arg(
+                    Arg::new("input-format")
+                        .about("Format of the payload. Records of an Avro Object Container File (ocf) are resolved to the given schema, if any, or produced with the embedded one")
+                        .long("input-format")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["json", "ocf"])
+                        .requires_if("ocf", "payload-file")
+                        .conflicts_with("text"),
+                )
+                .arg(
+                    Arg::new("repeat")
+                        .about("Sends the payload N times. Placeholders {{seq}}, {{timestamp}} and {{uuid}} are substituted into each message")
+                        .long("repeat")
+                        .takes_value(true)
+                        .value_name("N")
+                        .validator(|s| s.parse::<u64>())
+                        .conflicts_with_all(&["loop", "duration"]),
+                )
+                .arg(
+                    Arg::new("loop")
+                        .about("Sends the payload repeatedly until interrupted")
+                        .long("loop")
+                        .takes_value(false)
+                        .conflicts_with_all(&["repeat", "duration"]),
+                )
+                .arg(
+                    Arg::new("duration")
+                        .about("Sends the payload repeatedly during the given time. (e.g. 30s, 10m, 1h)")
+                        .long("duration")
+                        .takes_value(true)
+                        .value_name("DURATION")
+                        .validator(parse_duration)
+                        .conflicts_with_all(&["repeat", "loop"]),
+                )
+                .arg(
+                    Arg::new("format")
+                        .about("Format of the JSON payload: mapped to Avro (avro), validated against a JSON Schema (json-schema) or encoded to Protobuf with a .proto schema (protobuf). avro by default")
+                        .long("format")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["avro", "json-schema", "protobuf"])
+                        .conflicts_with("text"),
+                )
                 .arg(
                     Arg::new("schema")
-                        .about("Avro schema used to serialize payload")
+                        .about("Schema used to serialize payload, of the --format")
                         .short('s')
                         .long("schema")
                         .multiple_values(false)
                         .value_name("SCHEMA JSON")
-                        .required_unless_present_any(&["text", "schema-file", "registry-url"])
-                        .conflicts_with("schema-file")
+                        .conflicts_with_all(&["schema-file", "schema-dir"])
                 )
                 .arg(
                     Arg::new("schema-file")
-                        .about("File containing the Avro schema used to serialize payload")
+                        .about("File containing the schema used to serialize payload: .avsc, .avpr or .avdl, repeated for Avro schemas which refer to each other, a JSON Schema or a .proto file")
                         .long("schema-file")
                         .multiple_values(false)
+                        .multiple_occurrences(true)
                         .value_name("PATH")
-                        .required_unless_present_any(&["text", "schema", "registry-url"])
+                        .conflicts_with("schema"),
+                )
+                .arg(
+                    Arg::new("schema-dir")
+                        .about("Directory with .avsc, .avpr and .avdl files which refer to each other")
+                        .long("schema-dir")
+                        .multiple_values(false)
+                        .value_name("PATH")
+                        .conflicts_with("schema"),
+                )
+                .arg(
+                    Arg::new("record")
+                        .about("Fullname of the named type to use, when schema files declare several of them, or the message of a .proto file, the first one by default")
+                        .long("record")
+                        .multiple_values(false)
+                        .value_name("FULLNAME")
                         .conflicts_with("schema"),
                 )
                 .arg(
                     Arg::new("registry-url")
-                        .about("Schema-registry url")
+                        .about("Schema-registry url, comma-separated urls of the cluster nodes, or file:///path of a directory the tool keeps a registry of its own in")
                         .long("registry-url")
                         .multiple_values(false)
                         .value_name("http[s]://host:port"),
+                )
+                .args(registry_type_args())
+                .arg(wire_format_arg())
+                .arg(
+                    Arg::new("normalize")
+                        .about("Registers the schema normalized: with fullnames, a fixed order of attributes and without whitespaces. Avoids new versions which differ in formatting only")
+                        .long("normalize")
+                        .takes_value(false)
+                        .requires("registry-url"),
+                )
+                .arg(
+                    Arg::new("reference")
+                        .about("Registered schema the schema refers to. NAME is the fullname of the referenced type")
+                        .long("reference")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_name("NAME=SUBJECT:VERSION")
+                        .validator(parse_reference)
+                        .requires("registry-url"),
                 ),
         )
-        .get_matches()
+        .subcommand(
+            App::new("consume")
+                .about("Reads Avro serialized messages of a topic range. Prints them as JSON or writes into Avro Object Container Files")
+                .args(ssl_args())
+                .args(registry_ssl_args())
+                .arg(
+                    Arg::new("hosts")
+                        .about("Kafka hosts")
+                        .short('h')
+                        .long("hosts")
+                        .takes_value(true)
+                        .value_name("host:port[,host:port[...]]")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("topic")
+                        .about("Topic name")
+                        .short('t')
+                        .long("topic")
+                        .takes_value(true)
+                        .value_name("TOPIC")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("registry-url")
+                        .about("Schema-registry url, comma-separated urls of the cluster nodes, or file:///path of a directory the tool keeps a registry of its own in")
+                        .long("registry-url")
+                        .multiple_values(false)
+                        .value_name("http[s]://host:port")
+                        .required_unless_present("writer-schema-file"),
+                )
+                .args(registry_type_args())
+                .arg(wire_format_arg().about("How messages are framed: schema-registry id (confluent, apicurio or glue), Avro single-object encoding with the schema fingerprint (single-object) or the datum only (raw). Detected by the message header if not present; schema-registry ids are detected only with --registry-url"))
+                .arg(
+                    Arg::new("writer-schema-file")
+                        .about("File containing a writer schema of single-object encoded messages, which are looked up by fingerprint. Raw messages are read with the only writer schema given")
+                        .long("writer-schema-file")
+                        .multiple_values(false)
+                        .multiple_occurrences(true)
+                        .value_name("PATH"),
+                )
+                .arg(
+                    Arg::new("partition")
+                        .about("Partition to read. All partitions by default")
+                        .long("partition")
+                        .takes_value(true)
+                        .value_name("PARTITION")
+                        .validator(|s| s.parse::<i32>()),
+                )
+                .arg(
+                    Arg::new("from")
+                        .about("Offset to start from, inclusive. The beginning of partitions by default")
+                        .long("from")
+                        .takes_value(true)
+                        .value_name("OFFSET")
+                        .validator(|s| s.parse::<i64>()),
+                )
+                .arg(
+                    Arg::new("to")
+                        .about("Offset to stop at, exclusive. The current end of partitions by default")
+                        .long("to")
+                        .takes_value(true)
+                        .value_name("OFFSET")
+                        .validator(|s| s.parse::<i64>()),
+                )
+                .arg(
+                    Arg::new("reader-schema")
+                        .about("Avro schema records are resolved to. Writer schemas are used by default")
+                        .long("reader-schema")
+                        .multiple_values(false)
+                        .value_name("SCHEMA JSON")
+                        .conflicts_with("reader-schema-file"),
+                )
+                .arg(
+                    Arg::new("reader-schema-file")
+                        .about("File containing the Avro schema records are resolved to, .avsc, .avpr or .avdl")
+                        .long("reader-schema-file")
+                        .multiple_values(false)
+                        .value_name("PATH")
+                        .conflicts_with("reader-schema"),
+                )
+                .arg(
+                    Arg::new("record")
+                        .about("Fullname of the named type to use, when the reader schema file declares several of them")
+                        .long("record")
+                        .multiple_values(false)
+                        .value_name("FULLNAME")
+                        .requires("reader-schema-file"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .about("Avro Object Container File to write. When the writer schema changes, the next file is numbered, e.g. dump.1.avro")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("PATH"),
+                )
+                .arg(
+                    Arg::new("codec")
+                        .about("Compression codec of the output file. null by default")
+                        .long("codec")
+                        .takes_value(true)
+                        .value_name("CODEC")
+                        .possible_values(&["null", "deflate", "snappy", "zstd"])
+                        .requires("output"),
+                ),
+        )
+        .try_get_matches()
+}
+
+/// Schema given inline or by files
+fn schema_file_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("schema")
+            .about("Avro schema")
+            .short('s')
+            .long("schema")
+            .multiple_values(false)
+            .value_name("SCHEMA JSON")
+            .required_unless_present_any(&["schema-file", "schema-dir"])
+            .conflicts_with_all(&["schema-file", "schema-dir"]),
+        Arg::new("schema-file")
+            .about("File containing the Avro schema, .avsc, .avpr or .avdl. Repeat for schemas which refer to each other")
+            .long("schema-file")
+            .multiple_values(false)
+            .multiple_occurrences(true)
+            .value_name("PATH")
+            .conflicts_with("schema"),
+        Arg::new("schema-dir")
+            .about("Directory with .avsc, .avpr and .avdl files which refer to each other")
+            .long("schema-dir")
+            .multiple_values(false)
+            .value_name("PATH")
+            .conflicts_with("schema"),
+        Arg::new("record")
+            .about("Fullname of the named type to use, when schema files declare several of them")
+            .long("record")
+            .multiple_values(false)
+            .value_name("FULLNAME")
+            .conflicts_with("schema"),
+    ]
+}
+
+fn wire_format_arg() -> Arg<'static> {
+    Arg::new("wire-format")
+        .about("How messages are framed: schema-registry id (confluent, apicurio or glue), Avro single-object encoding with the schema fingerprint (single-object) or the datum only (raw). The format of the registry type if schema-registry is used, raw otherwise")
+        .long("wire-format")
+        .takes_value(true)
+        .value_name("FORMAT")
+        .possible_values(&["confluent", "apicurio", "glue", "single-object", "raw"])
+        .requires_ifs(&[
+            ("confluent", "registry-url"),
+            ("apicurio", "registry-url"),
+            ("glue", "registry-url"),
+        ])
+}
+
+/// Which API the schema-registry speaks, how to authenticate and to connect
+fn registry_type_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("registry-type")
+            .about("Schema-registry API: confluent, apicurio (Apicurio Registry v2) or glue (AWS Glue Schema Registry). confluent by default. Glue requests are not signed with SigV4, so they go to a local Glue-compatible registry or a signing proxy")
+            .long("registry-type")
+            .takes_value(true)
+            .value_name("TYPE")
+            .possible_values(&["confluent", "apicurio", "glue"])
+            .requires("registry-url"),
+        Arg::new("registry-basic-auth")
+            .about("Credentials of the schema-registry basic authentication")
+            .long("registry-basic-auth")
+            .takes_value(true)
+            .value_name("USER:PASSWORD")
+            .validator(parse_basic_auth)
+            .requires("registry-url"),
+        Arg::new("registry-connect-timeout")
+            .about("Timeout of connecting to the schema-registry. (e.g. 500ms, 5s)")
+            .long("registry-connect-timeout")
+            .takes_value(true)
+            .value_name("DURATION")
+            .validator(parse_duration)
+            .requires("registry-url"),
+        Arg::new("registry-read-timeout")
+            .about("Timeout of reading a schema-registry response. (e.g. 500ms, 5s)")
+            .long("registry-read-timeout")
+            .takes_value(true)
+            .value_name("DURATION")
+            .validator(parse_duration)
+            .requires("registry-url"),
+        Arg::new("registry-retries")
+            .about("How many times requests failed with a connection error or a 5xx status are retried, against the next url with exponential backoff. 3 by default")
+            .long("registry-retries")
+            .takes_value(true)
+            .value_name("N")
+            .validator(|s| s.parse::<u32>())
+            .requires("registry-url"),
+        Arg::new("registry-group")
+            .about("Apicurio artifact group, `default` by default, or Glue registry name, `default-registry` by default")
+            .long("registry-group")
+            .takes_value(true)
+            .value_name("NAME")
+            .requires("registry-url"),
+    ]
+}
+
+fn registry_args() -> Vec<Arg<'static>> {
+    let mut args = vec![Arg::new("registry-url")
+        .about("Schema-registry url, comma-separated urls of the cluster nodes, or file:///path of a directory the tool keeps a registry of its own in")
+        .long("registry-url")
+        .multiple_values(false)
+        .value_name("http[s]://host:port")
+        .required(true)];
+    args.extend(registry_type_args());
+    args.extend(ssl_args());
+    args
+}
+
+fn subject_arg() -> Arg<'static> {
+    Arg::new("subject")
+        .about("Subject name")
+        .index(1)
+        .value_name("SUBJECT")
+        .required(true)
+}
+
+fn optional_subject_arg() -> Arg<'static> {
+    Arg::new("subject")
+        .about("Subject name. Global setting is used if not present")
+        .long("subject")
+        .takes_value(true)
+        .value_name("SUBJECT")
+}
+
+/// Brokers and their TLS settings
+fn kafka_admin_args() -> Vec<Arg<'static>> {
+    let mut args = vec![Arg::new("hosts")
+        .about("Kafka hosts")
+        .short('h')
+        .long("hosts")
+        .takes_value(true)
+        .value_name("host:port[,host:port[...]]")
+        .required(true)];
+    args.extend(ssl_args());
+    args
+}
+
+fn topic_name_arg() -> Arg<'static> {
+    Arg::new("topic")
+        .about("Topic name")
+        .index(1)
+        .value_name("TOPIC")
+        .required(true)
+}
+
+/// Partitions, replication and config overrides of a new topic
+fn topic_config_args() -> Vec<Arg<'static>> {
+    let mut args = vec![
+        Arg::new("partitions")
+            .about("Number of partitions. 1 by default")
+            .long("partitions")
+            .takes_value(true)
+            .value_name("N")
+            .validator(|s| s.parse::<u16>()),
+        Arg::new("replication-factor")
+            .about("Number of replicas of each partition. 1 by default")
+            .long("replication-factor")
+            .takes_value(true)
+            .value_name("N")
+            .validator(|s| s.parse::<u16>()),
+    ];
+    args.extend(topic_override_args());
+    args
+}
+
+/// Topic configs, generic and shorthands of common ones
+fn topic_override_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("config")
+            .about("Topic config override, e.g. cleanup.policy=compact or retention.ms=86400000")
+            .long("config")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("KEY=VALUE")
+            .validator(parse_config_entry),
+        Arg::new("cleanup-policy")
+            .about("Whether old segments are deleted or compacted. Sets cleanup.policy")
+            .long("cleanup-policy")
+            .takes_value(true)
+            .value_name("POLICY")
+            .possible_values(&["delete", "compact", "compact,delete"]),
+        Arg::new("retention")
+            .about("How long messages are kept, e.g. 7d. Sets retention.ms")
+            .long("retention")
+            .takes_value(true)
+            .value_name("DURATION")
+            .validator(parse_duration),
+    ]
+}
+
+fn validate_version(version: &str) -> Result<(), String> {
+    match version {
+        "latest" => Ok(()),
+        v => v
+            .parse::<u32>()
+            .map(|_| ())
+            .map_err(|_| format!("`{}` is neither a number nor `latest`", v)),
+    }
 }
 
 fn ssl_args() -> Vec<Arg<'static>> {
@@ -188,6 +1404,18 @@ fn ssl_args() -> Vec<Arg<'static>> {
             .takes_value(true)
             .value_name("PATH")
             .required(false),
+        Arg::new("ssl-certificate-location")
+            .about("Path to client's certificate (PEM), used with the key")
+            .long("ssl.certificate.location")
+            .takes_value(true)
+            .value_name("PATH")
+            .requires("ssl-key-location"),
+        Arg::new("ssl-key-location")
+            .about("Path to client's private key (PEM, PKCS#8)")
+            .long("ssl.key.location")
+            .takes_value(true)
+            .value_name("PATH")
+            .requires("ssl-certificate-location"),
         Arg::new("ssl-keystore-location")
             .about("Path to client's keystore (PKCS#12)")
             .long("ssl.keystore.location")
@@ -202,3 +1430,49 @@ fn ssl_args() -> Vec<Arg<'static>> {
             .required(false),
     ]
 }
+
+/// TLS of the schema-registry, when it differs from the one of brokers
+fn registry_ssl_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("registry-ssl-disable-validate")
+            .about("Do not validate schema-registry's certificate")
+            .long("registry-ssl.disable.validate")
+            .takes_value(false)
+            .requires("registry-url"),
+        Arg::new("registry-ssl-host-validate")
+            .about("To validate schema-registry's hostname")
+            .long("registry-ssl.host.validate")
+            .takes_value(false)
+            .requires("registry-url"),
+        Arg::new("registry-ssl-ca-location")
+            .about("Path to CA certificate(s) for verifying the schema-registry's key. (PEM)")
+            .long("registry-ssl.ca.location")
+            .takes_value(true)
+            .value_name("PATH")
+            .requires("registry-url"),
+        Arg::new("registry-ssl-certificate-location")
+            .about("Path to client's certificate for the schema-registry (PEM), used with the key")
+            .long("registry-ssl.certificate.location")
+            .takes_value(true)
+            .value_name("PATH")
+            .requires_all(&["registry-url", "registry-ssl-key-location"]),
+        Arg::new("registry-ssl-key-location")
+            .about("Path to client's private key for the schema-registry (PEM, PKCS#8)")
+            .long("registry-ssl.key.location")
+            .takes_value(true)
+            .value_name("PATH")
+            .requires_all(&["registry-url", "registry-ssl-certificate-location"]),
+        Arg::new("registry-ssl-keystore-location")
+            .about("Path to client's keystore for the schema-registry (PKCS#12)")
+            .long("registry-ssl.keystore.location")
+            .takes_value(true)
+            .value_name("PATH")
+            .requires("registry-url"),
+        Arg::new("registry-ssl-keystore-password")
+            .about("Client's keystore (PKCS#12) password for the schema-registry")
+            .long("registry-ssl.keystore.password")
+            .takes_value(true)
+            .value_name("PASSWORD")
+            .requires("registry-url"),
+    ]
+}
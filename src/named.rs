@@ -0,0 +1,414 @@
+use crate::error::CliError;
+
+use serde_json::{Map, Value as JsonValue};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+const PRIMITIVES: [&str; 8] = [
+    "null", "boolean", "int", "long", "float", "double", "bytes", "string",
+];
+const COMPLEX_TYPES: [&str; 2] = ["array", "map"];
+const NAMED_TYPES: [&str; 4] = ["record", "error", "enum", "fixed"];
+
+/// Named type definition together with the namespace it was defined in
+pub type Definition = (JsonValue, Option<String>);
+
+/// Returns the fullname of a named type. Names containing dots are already full
+pub fn fullname(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') && !namespace.is_empty() => {
+            format!("{}.{}", namespace, name)
+        }
+        _ => name.to_string(),
+    }
+}
+
+pub fn is_keyword(name: &str) -> bool {
+    PRIMITIVES.contains(&name) || COMPLEX_TYPES.contains(&name) || NAMED_TYPES.contains(&name)
+}
+
+/// Definition a reference resolves to with its fullname: the type of the enclosing namespace,
+/// or else the one of the null namespace
+pub fn lookup<'d>(
+    name: &str,
+    namespace: Option<&str>,
+    definitions: &'d HashMap<String, Definition>,
+) -> Option<(String, &'d Definition)> {
+    let fullname = fullname(name, namespace);
+    match definitions.get(&fullname) {
+        Some(definition) => Some((fullname, definition)),
+        None => definitions
+            .get(name)
+            .map(|definition| (name.to_string(), definition)),
+    }
+}
+
+fn namespace_of(fullname: &str) -> Option<String> {
+    fullname.rfind('.').map(|idx| fullname[..idx].to_string())
+}
+
+/// Fullname and namespace of a named type definition, if the object is one
+pub fn definition_name(
+    object: &Map<String, JsonValue>,
+    namespace: Option<&str>,
+) -> Option<(String, Option<String>)> {
+    let is_named = matches!(
+        object.get("type").and_then(|t| t.as_str()),
+        Some(t) if NAMED_TYPES.contains(&t)
+    );
+    let name = object.get("name").and_then(|n| n.as_str());
+
+    match (is_named, name) {
+        (true, Some(name)) => {
+            let namespace = object
+                .get("namespace")
+                .and_then(|ns| ns.as_str())
+                .or(namespace);
+            let fullname = fullname(name, namespace);
+            let namespace = namespace_of(&fullname);
+            Some((fullname, namespace))
+        }
+        _ => None,
+    }
+}
+
+/// Collects every named type defined in a schema, nested ones included
+pub fn collect_definitions(
+    schema: &JsonValue,
+    namespace: Option<&str>,
+    definitions: &mut HashMap<String, Definition>,
+) {
+    match schema {
+        JsonValue::Array(variants) => variants
+            .iter()
+            .for_each(|variant| collect_definitions(variant, namespace, definitions)),
+        JsonValue::Object(object) => {
+            let (inner_namespace, is_named) = match definition_name(object, namespace) {
+                Some((fullname, inner_namespace)) => {
+                    definitions
+                        .entry(fullname)
+                        .or_insert_with(|| (schema.clone(), namespace.map(|ns| ns.to_string())));
+                    (inner_namespace, true)
+                }
+                None => (namespace.map(|ns| ns.to_string()), false),
+            };
+            let inner_namespace = inner_namespace.as_deref();
+
+            if let Some(JsonValue::Array(fields)) = object.get("fields") {
+                for field in fields {
+                    if let Some(field_type) = field.get("type") {
+                        collect_definitions(field_type, inner_namespace, definitions);
+                    }
+                }
+            }
+            for key in &["items", "values"] {
+                if let Some(inner) = object.get(*key) {
+                    collect_definitions(inner, inner_namespace, definitions);
+                }
+            }
+            if !is_named {
+                if let Some(inner @ JsonValue::Object(_)) = object.get("type") {
+                    collect_definitions(inner, inner_namespace, definitions);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Makes a standalone schema out of a schema which references named types
+/// defined in dependencies. Each referenced type is inlined where it is used for
+/// the first time, all further usages refer to it by fullname.
+pub fn inline_references(
+    schema: &JsonValue,
+    dependencies: &[JsonValue],
+) -> Result<JsonValue, CliError> {
+    let mut available = HashMap::new();
+    for dependency in dependencies {
+        collect_definitions(dependency, None, &mut available);
+    }
+
+    let mut inliner = Inliner {
+        available,
+        defined: HashSet::new(),
+        unresolved: BTreeSet::new(),
+        fallbacks: BTreeSet::new(),
+    };
+    let inlined = inliner.walk(schema, None);
+    inliner.into_result(inlined)
+}
+
+/// Fullnames of named types referenced, but not defined, in a schema
+pub fn referenced_names(schema: &JsonValue) -> BTreeSet<String> {
+    let mut inliner = Inliner {
+        available: HashMap::new(),
+        defined: HashSet::new(),
+        unresolved: BTreeSet::new(),
+        fallbacks: BTreeSet::new(),
+    };
+    inliner.walk(schema, None);
+    inliner.unresolved.append(&mut inliner.fallbacks);
+    inliner.unresolved
+}
+
+/// Combines schemas which refer to each other's named types into a standalone
+/// schema. The root is the only schema none of the others refers to.
+pub fn combine(schemas: &[JsonValue]) -> Result<JsonValue, CliError> {
+    let referenced: Vec<BTreeSet<String>> = schemas.iter().map(referenced_names).collect();
+
+    let roots: Vec<usize> = (0..schemas.len())
+        .filter(|&idx| {
+            let mut definitions = HashMap::new();
+            collect_definitions(&schemas[idx], None, &mut definitions);
+
+            !referenced.iter().enumerate().any(|(other, names)| {
+                other != idx && names.iter().any(|name| definitions.contains_key(name))
+            })
+        })
+        .collect();
+
+    match roots.as_slice() {
+        [root] => {
+            let dependencies: Vec<JsonValue> = schemas
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| idx != root)
+                .map(|(_, schema)| schema.clone())
+                .collect();
+            inline_references(&schemas[*root], &dependencies)
+        }
+        _ => Err(CliError::Schema(format!(
+            "cannot determine the root schema, candidates: [{}]",
+            roots
+                .iter()
+                .map(|idx| top_level_name(&schemas[*idx]))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Makes a standalone schema of the named type `name` defined in one of the schemas.
+/// The other schemas provide the types it refers to.
+pub fn select(schemas: &[JsonValue], name: &str) -> Result<JsonValue, CliError> {
+    let mut available = HashMap::new();
+    for schema in schemas {
+        collect_definitions(schema, None, &mut available);
+    }
+
+    if !available.contains_key(name) {
+        let mut names: Vec<&String> = available.keys().collect();
+        names.sort();
+        return Err(CliError::Schema(format!(
+            "named type `{}` not found, available: [{}]",
+            name,
+            names
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )));
+    }
+
+    let mut inliner = Inliner {
+        available,
+        defined: HashSet::new(),
+        unresolved: BTreeSet::new(),
+        fallbacks: BTreeSet::new(),
+    };
+    let selected = inliner.walk_reference(name, None);
+    inliner.into_result(selected)
+}
+
+fn top_level_name(schema: &JsonValue) -> String {
+    match schema {
+        JsonValue::Object(object) => definition_name(object, None)
+            .map(|(fullname, _)| fullname)
+            .unwrap_or_else(|| schema.to_string()),
+        _ => schema.to_string(),
+    }
+}
+
+struct Inliner {
+    available: HashMap<String, Definition>,
+    defined: HashSet<String>,
+    unresolved: BTreeSet<String>,
+    /// Names of the null namespace unresolved references may refer to as well
+    fallbacks: BTreeSet<String>,
+}
+
+impl Inliner {
+    /// The walked schema, unless some of the named types were not resolved
+    fn into_result(self, schema: JsonValue) -> Result<JsonValue, CliError> {
+        if self.unresolved.is_empty() {
+            Ok(schema)
+        } else {
+            Err(CliError::Schema(format!(
+                "unresolved named types: {}",
+                self.unresolved
+                    .into_iter()
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )))
+        }
+    }
+
+    fn walk(&mut self, schema: &JsonValue, namespace: Option<&str>) -> JsonValue {
+        match schema {
+            JsonValue::String(name) if is_keyword(name) => schema.clone(),
+            JsonValue::String(name) => self.walk_reference(name, namespace),
+            JsonValue::Array(variants) => JsonValue::Array(
+                variants
+                    .iter()
+                    .map(|variant| self.walk(variant, namespace))
+                    .collect(),
+            ),
+            JsonValue::Object(object) => self.walk_object(object, namespace),
+            _ => schema.clone(),
+        }
+    }
+
+    fn walk_reference(&mut self, name: &str, namespace: Option<&str>) -> JsonValue {
+        let fullname = fullname(name, namespace);
+
+        if self.defined.contains(&fullname) {
+            return JsonValue::String(fullname);
+        }
+        if let Some((_, (definition, namespace))) = lookup(name, namespace, &self.available) {
+            let (definition, namespace) = (definition.clone(), namespace.clone());
+            return self.walk(&definition, namespace.as_deref());
+        }
+        // a type of the null namespace, defined already
+        if self.defined.contains(name) {
+            return JsonValue::String(name.to_string());
+        }
+
+        if fullname != name {
+            self.fallbacks.insert(name.to_string());
+        }
+        self.unresolved.insert(fullname.clone());
+        JsonValue::String(fullname)
+    }
+
+    fn walk_object(
+        &mut self,
+        object: &Map<String, JsonValue>,
+        namespace: Option<&str>,
+    ) -> JsonValue {
+        let mut result = object.clone();
+
+        match definition_name(object, namespace) {
+            Some((fullname, inner_namespace)) => {
+                // already defined types are referred to by fullname
+                if !self.defined.insert(fullname.clone()) {
+                    return JsonValue::String(fullname);
+                }
+
+                result.insert("name".to_string(), JsonValue::String(fullname.clone()));
+                result.remove("namespace");
+
+                if let Some(JsonValue::Array(fields)) = object.get("fields") {
+                    let fields = fields
+                        .iter()
+                        .map(|field| self.walk_field(field, inner_namespace.as_deref()))
+                        .collect();
+                    result.insert("fields".to_string(), JsonValue::Array(fields));
+                }
+            }
+            None => {
+                for key in &["type", "items", "values"] {
+                    if let Some(inner) = object.get(*key) {
+                        result.insert(key.to_string(), self.walk(inner, namespace));
+                    }
+                }
+            }
+        }
+
+        JsonValue::Object(result)
+    }
+
+    fn walk_field(&mut self, field: &JsonValue, namespace: Option<&str>) -> JsonValue {
+        match (field, field.get("type")) {
+            (JsonValue::Object(object), Some(field_type)) => {
+                let mut result = object.clone();
+                result.insert("type".to_string(), self.walk(field_type, namespace));
+                JsonValue::Object(result)
+            }
+            _ => field.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn inlines_references_of_the_enclosing_namespace() {
+        let schema = json!({"type": "record", "name": "Order", "namespace": "shop", "fields": [
+            {"name": "item", "type": "Item"},
+            {"name": "other", "type": "Item"}
+        ]});
+        let item = json!({"type": "record", "name": "Item", "namespace": "shop", "fields": []});
+
+        let inlined = inline_references(&schema, &[item]).unwrap();
+        assert_eq!(inlined["name"], "shop.Order");
+        assert_eq!(inlined["fields"][0]["type"]["name"], "shop.Item");
+        assert_eq!(inlined["fields"][1]["type"], "shop.Item");
+    }
+
+    #[test]
+    fn falls_back_to_the_null_namespace() {
+        let schema = json!({"type": "record", "name": "Order", "namespace": "shop", "fields": [
+            {"name": "money", "type": "Money"},
+            {"name": "total", "type": "Money"}
+        ]});
+        let money = json!({"type": "fixed", "name": "Money", "size": 8});
+
+        let inlined = inline_references(&schema, std::slice::from_ref(&money)).unwrap();
+        assert_eq!(inlined["fields"][0]["type"]["name"], "Money");
+        assert_eq!(inlined["fields"][1]["type"], "Money");
+
+        let combined = combine(&[money, schema]).unwrap();
+        assert_eq!(combined["name"], "shop.Order");
+    }
+
+    #[test]
+    fn prefers_the_enclosing_namespace() {
+        let schema = json!({"type": "record", "name": "Order", "namespace": "shop", "fields": [
+            {"name": "id", "type": "Id"}
+        ]});
+        let ids = [
+            json!({"type": "fixed", "name": "Id", "size": 4}),
+            json!({"type": "fixed", "name": "Id", "namespace": "shop", "size": 16}),
+        ];
+
+        let inlined = inline_references(&schema, &ids).unwrap();
+        assert_eq!(inlined["fields"][0]["type"]["size"], 16);
+    }
+
+    #[test]
+    fn reports_unresolved_names() {
+        let schema = json!({"type": "record", "name": "Order", "namespace": "shop", "fields": [
+            {"name": "item", "type": "Item"}
+        ]});
+        assert!(inline_references(&schema, &[]).is_err());
+        assert_eq!(
+            referenced_names(&schema)
+                .into_iter()
+                .collect::<Vec<String>>(),
+            vec!["Item", "shop.Item"]
+        );
+    }
+
+    #[test]
+    fn selects_a_named_type() {
+        let schemas = [
+            json!({"type": "record", "name": "A", "fields": [{"name": "b", "type": "B"}]}),
+            json!({"type": "enum", "name": "B", "symbols": ["X"]}),
+        ];
+        let selected = select(&schemas, "A").unwrap();
+        assert_eq!(selected["fields"][0]["type"]["symbols"], json!(["X"]));
+        assert!(select(&schemas, "C").is_err());
+    }
+}
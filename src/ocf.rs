@@ -0,0 +1,185 @@
+use crate::error::CliError;
+
+use avro_rs::types::Value as AvroValue;
+use avro_rs::{AvroResult, Codec, Reader, Schema};
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"Obj\x01";
+const SCHEMA_KEY: &[u8] = b"avro.schema";
+
+/// Reads the raw writer schema from the header of an Avro Object Container File
+pub fn read_writer_schema(path: &str) -> Result<String, CliError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(CliError::Schema(format!(
+            "{} is not an Avro object container file",
+            path
+        )));
+    }
+
+    // metadata is an Avro map of bytes
+    loop {
+        let mut count = read_long(&mut reader)?;
+        if count == 0 {
+            break;
+        }
+        if count < 0 {
+            // negative count is followed by the block size
+            count = count.checked_neg().ok_or_else(malformed_header)?;
+            read_long(&mut reader)?;
+        }
+
+        for _ in 0..count {
+            let key = read_bytes(&mut reader)?;
+            let value = read_bytes(&mut reader)?;
+            if key == SCHEMA_KEY {
+                return String::from_utf8(value).map_err(|e| CliError::Schema(e.to_string()));
+            }
+        }
+    }
+
+    Err(CliError::Schema(format!(
+        "no schema in the header of {}",
+        path
+    )))
+}
+
+/// Reads all records of an Avro Object Container File, resolved to the reader schema
+pub fn read_values(path: &str, reader_schema: &Schema) -> Result<Vec<AvroValue>, CliError> {
+    let file = BufReader::new(File::open(path)?);
+
+    Reader::with_schema(reader_schema, file)?
+        .collect::<AvroResult<Vec<AvroValue>>>()
+        .map_err(|e| e.into())
+}
+
+/// Parses a codec name: null, deflate, snappy or zstd
+pub fn parse_codec(raw: &str) -> Result<Codec, String> {
+    match raw {
+        "null" => Ok(Codec::Null),
+        "deflate" => Ok(Codec::Deflate),
+        "snappy" => Ok(Codec::Snappy),
+        "zstd" => Ok(Codec::Zstd),
+        _ => Err(format!("unknown codec `{}`", raw)),
+    }
+}
+
+/// Path of the n-th rolled file. The first one is the path itself, the next ones are
+/// numbered before the extension, e.g. `dump.avro`, `dump.1.avro`, `dump.2.avro`
+pub fn rolled_path(path: &str, n: usize) -> String {
+    if n == 0 {
+        return path.to_string();
+    }
+
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let file_name = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}.{}.{}", stem, n, ext),
+        None => format!("{}.{}", stem, n),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Reads a zig-zag encoded variable-length long
+fn read_long<R: Read>(reader: &mut R) -> Result<i64, CliError> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        if shift > 63 {
+            return Err(malformed_header());
+        }
+
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+        shift += 7;
+    }
+}
+
+/// Reads length-prefixed bytes. The buffer grows with the bytes actually read,
+/// so a corrupt length fails on the end of the file instead of allocating it
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, CliError> {
+    let len = read_long(reader)?;
+    if len < 0 {
+        return Err(malformed_header());
+    }
+
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len as u64 {
+        return Err(malformed_header());
+    }
+    Ok(bytes)
+}
+
+fn malformed_header() -> CliError {
+    CliError::Schema("malformed file header".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_file(test: &str, content: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("ocf-{}-{}.avro", std::process::id(), test));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn reads_the_writer_schema() {
+        let mut content = MAGIC.to_vec();
+        // a block of 2 entries: the codec and the schema
+        content.extend(&[4, 20]);
+        content.extend(b"avro.codec");
+        content.extend(&[8]);
+        content.extend(b"null");
+        content.extend(&[22]);
+        content.extend(SCHEMA_KEY);
+        content.extend(&[16]);
+        content.extend(b"\"string\"");
+        content.push(0);
+
+        let path = write_file("schema", &content);
+        assert_eq!(read_writer_schema(&path).unwrap(), "\"string\"");
+    }
+
+    #[test]
+    fn rejects_lengths_beyond_the_file() {
+        let mut content = MAGIC.to_vec();
+        // one entry with a key of 2^62 bytes
+        content.extend(&[
+            2, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01,
+        ]);
+        content.extend(b"avro.schema");
+
+        let path = write_file("length", &content);
+        assert!(matches!(
+            read_writer_schema(&path),
+            Err(CliError::Schema(_))
+        ));
+    }
+
+    #[test]
+    fn numbers_rolled_files() {
+        assert_eq!(rolled_path("dump.avro", 0), "dump.avro");
+        assert_eq!(rolled_path("out/dump.avro", 2), "out/dump.2.avro");
+        assert_eq!(rolled_path("dump", 1), "dump.1");
+    }
+}
@@ -1,47 +1,99 @@
-use crate::context::{AppCtx, KafkaCtx, SslCtx};
+use crate::ssl::SslConfig;
 use rdkafka::config::FromClientConfigAndContext;
 use rdkafka::error::KafkaError;
 use rdkafka::message::DeliveryResult;
 use rdkafka::producer::{BaseRecord, ProducerContext, ThreadedProducer};
 use rdkafka::{ClientConfig, ClientContext};
-use std::sync::mpsc::{channel, Sender};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Mutex;
 
 const PRODUCER_MAX_RETRIES: &str = "0";
 
-pub struct Producer;
+/// Brokers, the topic and client settings of a producer
+#[derive(Clone)]
+pub struct ProducerConfig {
+    hosts: String,
+    topic: String,
+    ssl: Option<SslConfig>,
+    /// librdkafka properties, set over the ones derived from other settings
+    properties: Vec<(String, String)>,
+}
+
+impl ProducerConfig {
+    /// Hosts are `host:port[,host:port[...]]`
+    pub fn new(hosts: &str, topic: &str) -> ProducerConfig {
+        ProducerConfig {
+            hosts: hosts.to_string(),
+            topic: topic.to_string(),
+            ssl: None,
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn ssl(mut self, ssl: SslConfig) -> ProducerConfig {
+        self.ssl = Some(ssl);
+        self
+    }
+
+    /// Sets a librdkafka property, e.g. `message.max.bytes`
+    pub fn set(mut self, key: &str, value: &str) -> ProducerConfig {
+        self.properties.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Sends messages to the topic, one at a time and without retries
+pub struct Producer {
+    topic: String,
+    producer: ThreadedProducer<BlockingProducerContext>,
+    receiver: Receiver<Result<(), KafkaError>>,
+}
 
 impl Producer {
-    pub fn produce(ctx: &AppCtx, payloads: Vec<Vec<u8>>) -> Result<(), KafkaError> {
+    pub fn new(config: ProducerConfig) -> Result<Producer, KafkaError> {
         //config
-        let client_cfg = build_kafka_config(&ctx.kafka_ctx, &ctx.ssl);
+        let mut client_cfg = build_kafka_config(&config.hosts, config.ssl.as_ref());
+        client_cfg.set("retries", PRODUCER_MAX_RETRIES);
+        for (key, value) in &config.properties {
+            client_cfg.set(key, value);
+        }
 
         //context
         let (ctx_sender, ctx_receiver) = channel::<Result<(), KafkaError>>();
         let context = BlockingProducerContext::new(ctx_sender);
 
         //producer
-        let prod = ThreadedProducer::from_config_and_context(&client_cfg, context)?;
+        let producer = ThreadedProducer::from_config_and_context(&client_cfg, context)?;
+
+        Ok(Producer {
+            topic: config.topic,
+            producer,
+            receiver: ctx_receiver,
+        })
+    }
 
+    /// Sends payloads and blocks until all of them are confirmed by librdkafka
+    pub fn produce(&self, payloads: Vec<Vec<u8>>) -> Result<(), KafkaError> {
         for payload in &payloads {
             //actual send
-            prod.send(BaseRecord::<(), [u8]>::to(&ctx.kafka_ctx.topic).payload(payload.as_slice()))
+            self.producer
+                .send(BaseRecord::<(), [u8]>::to(&self.topic).payload(payload.as_slice()))
                 .map_err(|(kafka_err, _)| kafka_err)?;
         }
 
         // wait for send confirmation by librdkafka
         (0..payloads.len())
-            .map(|_| ctx_receiver.recv().unwrap())
+            .map(|_| self.receiver.recv().unwrap_or(Err(KafkaError::Canceled)))
             .collect()
     }
 }
 
-fn build_kafka_config(kafka_ctx: &KafkaCtx, ssl: &SslCtx) -> ClientConfig {
+/// Client config shared by the producer and the consumer
+pub fn build_kafka_config(hosts: &str, ssl: Option<&SslConfig>) -> ClientConfig {
     let mut client_cfg = ClientConfig::new();
-    client_cfg.set("bootstrap.servers", &kafka_ctx.hosts);
-    client_cfg.set("retries", PRODUCER_MAX_RETRIES);
+    client_cfg.set("bootstrap.servers", hosts);
 
-    if ssl.enabled {
+    if let Some(ssl) = ssl {
         client_cfg.set("security.protocol", "ssl");
         client_cfg.set(
             "enable.ssl.certificate.verification",
@@ -54,6 +106,12 @@ fn build_kafka_config(kafka_ctx: &KafkaCtx, ssl: &SslCtx) -> ClientConfig {
         if let Some(ref path) = ssl.ca_location {
             client_cfg.set("ssl.ca.location", &path);
         }
+        if let Some(ref path) = ssl.certificate_location {
+            client_cfg.set("ssl.certificate.location", &path);
+        }
+        if let Some(ref path) = ssl.key_location {
+            client_cfg.set("ssl.key.location", &path);
+        }
         if let Some(ref path) = ssl.keystore_location {
             client_cfg.set("ssl.keystore.location", &path);
         }
@@ -87,15 +145,14 @@ impl ProducerContext for BlockingProducerContext {
         delivery_result: &DeliveryResult<'a>,
         _delivery_opaque: Self::DeliveryOpaque,
     ) {
-        self.sender
-            .lock()
-            .unwrap()
-            .send(
+        // the receiver is gone only if the producer is dropped
+        if let Ok(sender) = self.sender.lock() {
+            let _ = sender.send(
                 delivery_result
                     .as_ref()
                     .map_err(|(kafka_err, _)| kafka_err.clone())
                     .map(|_| ()),
-            )
-            .unwrap();
+            );
+        }
     }
 }
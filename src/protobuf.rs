@@ -0,0 +1,1118 @@
+use crate::error::CliError;
+
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LEN: u32 = 2;
+const WIRE_FIXED32: u32 = 5;
+
+/// Messages and enums declared by a `.proto` file, by full name
+pub struct Proto {
+    proto3: bool,
+    package: Option<String>,
+    messages: HashMap<String, Message>,
+    /// Full names of top-level messages, in the order of declaration
+    top_level: Vec<String>,
+    enums: HashMap<String, Vec<(String, i32)>>,
+}
+
+struct Message {
+    /// Sorted by number
+    fields: Vec<Field>,
+    /// Path to the message among the messages of the file and nested ones
+    indexes: Vec<i32>,
+}
+
+struct Field {
+    name: String,
+    json_name: String,
+    number: u32,
+    label: Label,
+    field_type: FieldType,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Label {
+    /// Proto3 field without presence, default values are not written
+    Singular,
+    /// Field with presence: `optional`, `required` or a member of `oneof`
+    Optional,
+    Repeated,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scalar {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+}
+
+enum FieldType {
+    Scalar(Scalar),
+    Message(String),
+    Enum(String),
+    Map(Scalar, Box<FieldType>),
+    /// Type name as written, until it is resolved to a message or an enum
+    Named(String),
+}
+
+/// Parses a `.proto` file of syntax proto2 or proto3. Imports are not followed,
+/// so fields may only use scalars and types declared in the file.
+pub fn parse_proto(src: &str) -> Result<Proto, CliError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        proto: Proto {
+            proto3: false,
+            package: None,
+            messages: HashMap::new(),
+            top_level: Vec::new(),
+            enums: HashMap::new(),
+        },
+    };
+    parser.file()?;
+    parser.proto.resolve()?;
+    Ok(parser.proto)
+}
+
+/// Message indexes which follow the Confluent header of Protobuf messages:
+/// the count and the indexes as zigzag varints, just 0 for the first message of the file
+pub fn encode_message_indexes(indexes: &[i32]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if indexes == [0] {
+        buf.push(0);
+        return buf;
+    }
+
+    write_varint(&mut buf, zigzag(indexes.len() as i64));
+    for index in indexes {
+        write_varint(&mut buf, zigzag(*index as i64));
+    }
+    buf
+}
+
+impl Proto {
+    /// Full name of a message given by its full or short name, the first message if no name is given
+    pub fn message_name(&self, name: Option<&str>) -> Result<String, CliError> {
+        let name = match name {
+            Some(name) => name.trim_start_matches('.'),
+            None => {
+                return self
+                    .top_level
+                    .first()
+                    .cloned()
+                    .ok_or_else(|| CliError::Schema("no message declared".to_string()))
+            }
+        };
+
+        let qualified = qualify(self.package.as_deref().unwrap_or_default(), name);
+        for full_name in &[name.to_string(), qualified] {
+            if self.messages.contains_key(full_name) {
+                return Ok(full_name.clone());
+            }
+        }
+
+        let suffix = format!(".{}", name);
+        let mut matching = self
+            .messages
+            .keys()
+            .filter(|full_name| full_name.ends_with(&suffix));
+        match (matching.next(), matching.next()) {
+            (Some(full_name), None) => Ok(full_name.clone()),
+            (Some(_), Some(_)) => Err(CliError::Schema(format!(
+                "message name `{}` is ambiguous, use the full name",
+                name
+            ))),
+            (None, _) => Err(CliError::Schema(format!("message `{}` not found", name))),
+        }
+    }
+
+    pub fn message_indexes(&self, message: &str) -> &[i32] {
+        &self.messages[message].indexes
+    }
+
+    /// Encodes JSON in the proto3 JSON mapping into the binary format of the message
+    pub fn encode(&self, message: &str, json: &JsonValue) -> Result<Vec<u8>, CliError> {
+        let mut buf = Vec::new();
+        self.write_message(message, json, "$", &mut buf)?;
+        Ok(buf)
+    }
+
+    fn write_message(
+        &self,
+        name: &str,
+        json: &JsonValue,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CliError> {
+        let message = &self.messages[name];
+        let object = json.as_object().ok_or_else(|| mismatch(name, path, json))?;
+
+        if let Some(unknown) = object.keys().find(|key| {
+            !message
+                .fields
+                .iter()
+                .any(|field| field.json_name == **key || field.name == **key)
+        }) {
+            return Err(CliError::Mapping(
+                format!("{} at {}", name, path),
+                format!("unknown field `{}`", unknown),
+            ));
+        }
+
+        for field in &message.fields {
+            let value = match object
+                .get(&field.json_name)
+                .or_else(|| object.get(&field.name))
+            {
+                Some(JsonValue::Null) | None => continue,
+                Some(value) => value,
+            };
+            let path = format!("{}.{}", path, field.json_name);
+            self.write_field(field, value, &path, buf)?;
+        }
+        Ok(())
+    }
+
+    fn write_field(
+        &self,
+        field: &Field,
+        value: &JsonValue,
+        path: &str,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), CliError> {
+        match (&field.field_type, field.label) {
+            (FieldType::Map(key_type, value_type), _) => {
+                let entries = value
+                    .as_object()
+                    .ok_or_else(|| mismatch("map", path, value))?;
+                for (key, value) in entries {
+                    let entry_path = format!("{}.{}", path, key);
+                    let key = match key_type {
+                        Scalar::Bool => match key.as_str() {
+                            "true" => JsonValue::Bool(true),
+                            "false" => JsonValue::Bool(false),
+                            _ => {
+                                return Err(mismatch(
+                                    "bool",
+                                    &entry_path,
+                                    &JsonValue::from(key.as_str()),
+                                ))
+                            }
+                        },
+                        _ => JsonValue::String(key.clone()),
+                    };
+
+                    let mut entry = Vec::new();
+                    let (key_wire, key_bytes) =
+                        self.value_bytes(&FieldType::Scalar(*key_type), &key, &entry_path)?;
+                    write_tag(&mut entry, 1, key_wire);
+                    entry.extend(key_bytes);
+                    let (value_wire, value_bytes) =
+                        self.value_bytes(value_type, value, &entry_path)?;
+                    write_tag(&mut entry, 2, value_wire);
+                    entry.extend(value_bytes);
+
+                    write_tag(buf, field.number, WIRE_LEN);
+                    write_varint(buf, entry.len() as u64);
+                    buf.extend(entry);
+                }
+            }
+            (field_type, Label::Repeated) => {
+                let items = value
+                    .as_array()
+                    .ok_or_else(|| mismatch("array", path, value))?;
+                let mut packed = Vec::new();
+                for (idx, item) in items.iter().enumerate() {
+                    let (wire_type, bytes) =
+                        self.value_bytes(field_type, item, &format!("{}[{}]", path, idx))?;
+                    // proto3 packs repeated numbers by default
+                    if self.proto3 && wire_type != WIRE_LEN {
+                        packed.extend(bytes);
+                    } else {
+                        write_tag(buf, field.number, wire_type);
+                        buf.extend(bytes);
+                    }
+                }
+                if !packed.is_empty() {
+                    write_tag(buf, field.number, WIRE_LEN);
+                    write_varint(buf, packed.len() as u64);
+                    buf.extend(packed);
+                }
+            }
+            (field_type, label) => {
+                let (wire_type, bytes) = self.value_bytes(field_type, value, path)?;
+                // default values of all scalars and enums are encoded as zero bytes only
+                let is_default = bytes.iter().all(|b| *b == 0);
+                if label == Label::Singular
+                    && is_default
+                    && !matches!(field_type, FieldType::Message(_))
+                {
+                    return Ok(());
+                }
+                write_tag(buf, field.number, wire_type);
+                buf.extend(bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// Wire type and the encoded value, length-prefixed if it is of the `LEN` wire type
+    fn value_bytes(
+        &self,
+        field_type: &FieldType,
+        value: &JsonValue,
+        path: &str,
+    ) -> Result<(u32, Vec<u8>), CliError> {
+        let mut buf = Vec::new();
+        let wire_type = match field_type {
+            FieldType::Scalar(scalar) => write_scalar(*scalar, value, path, &mut buf)?,
+            FieldType::Enum(name) => {
+                let values = &self.enums[name];
+                let number = match value {
+                    JsonValue::String(symbol) => values
+                        .iter()
+                        .find(|(value_name, _)| value_name == symbol)
+                        .map(|(_, number)| *number),
+                    JsonValue::Number(n) => n
+                        .as_i64()
+                        .filter(|n| *n as i32 as i64 == *n)
+                        .map(|n| n as i32),
+                    _ => None,
+                }
+                .ok_or_else(|| mismatch(name, path, value))?;
+                write_varint(&mut buf, number as i64 as u64);
+                WIRE_VARINT
+            }
+            FieldType::Message(name) => {
+                let mut message = Vec::new();
+                self.write_message(name, value, path, &mut message)?;
+                write_varint(&mut buf, message.len() as u64);
+                buf.extend(message);
+                WIRE_LEN
+            }
+            // map values are scalars, messages or enums resolved when parsing
+            FieldType::Map(_, _) | FieldType::Named(_) => {
+                return Err(CliError::Schema(format!(
+                    "{}: map values or unresolved types are not values of fields",
+                    path
+                )))
+            }
+        };
+        Ok((wire_type, buf))
+    }
+
+    /// Resolves type names of fields to full names of messages and enums,
+    /// from the scope of the message outwards
+    fn resolve(&mut self) -> Result<(), CliError> {
+        let kinds: HashMap<String, bool> = self
+            .messages
+            .keys()
+            .map(|name| (name.clone(), true))
+            .chain(self.enums.keys().map(|name| (name.clone(), false)))
+            .collect();
+
+        for (scope, message) in self.messages.iter_mut() {
+            for field in message.fields.iter_mut() {
+                let field_type =
+                    std::mem::replace(&mut field.field_type, FieldType::Named(String::new()));
+                field.field_type = resolve_type(field_type, scope, &kinds)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn resolve_type(
+    field_type: FieldType,
+    scope: &str,
+    kinds: &HashMap<String, bool>,
+) -> Result<FieldType, CliError> {
+    let name = match field_type {
+        FieldType::Named(name) => name,
+        FieldType::Map(key_type, value_type) => {
+            return Ok(FieldType::Map(
+                key_type,
+                Box::new(resolve_type(*value_type, scope, kinds)?),
+            ))
+        }
+        resolved => return Ok(resolved),
+    };
+
+    let mut candidates = Vec::new();
+    match name.strip_prefix('.') {
+        Some(full_name) => candidates.push(full_name.to_string()),
+        None => {
+            let mut current = scope;
+            loop {
+                candidates.push(qualify(current, &name));
+                if current.is_empty() {
+                    break;
+                }
+                current = current.rfind('.').map_or("", |idx| &current[..idx]);
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .find_map(|candidate| match kinds.get(&candidate) {
+            Some(true) => Some(FieldType::Message(candidate)),
+            Some(false) => Some(FieldType::Enum(candidate)),
+            None => None,
+        })
+        .ok_or_else(|| {
+            CliError::Schema(format!(
+                "unknown type `{}` in {}, imported types are not supported",
+                name, scope
+            ))
+        })
+}
+
+/// Encodes a scalar, returns its wire type
+fn write_scalar(
+    scalar: Scalar,
+    value: &JsonValue,
+    path: &str,
+    buf: &mut Vec<u8>,
+) -> Result<u32, CliError> {
+    let type_name = scalar_name(scalar);
+    let fail = || mismatch(type_name, path, value);
+
+    match scalar {
+        Scalar::Bool => {
+            write_varint(buf, value.as_bool().ok_or_else(fail)? as u64);
+            Ok(WIRE_VARINT)
+        }
+        Scalar::String => {
+            let s = value.as_str().ok_or_else(fail)?;
+            write_varint(buf, s.len() as u64);
+            buf.extend_from_slice(s.as_bytes());
+            Ok(WIRE_LEN)
+        }
+        Scalar::Bytes => {
+            let encoded = value.as_str().ok_or_else(fail)?;
+            let bytes = base64::decode(encoded)
+                .or_else(|_| base64::decode_config(encoded, base64::URL_SAFE))
+                .map_err(|_| fail())?;
+            write_varint(buf, bytes.len() as u64);
+            buf.extend(bytes);
+            Ok(WIRE_LEN)
+        }
+        Scalar::Double => {
+            buf.extend_from_slice(&float(value).ok_or_else(fail)?.to_le_bytes());
+            Ok(WIRE_FIXED64)
+        }
+        Scalar::Float => {
+            buf.extend_from_slice(&(float(value).ok_or_else(fail)? as f32).to_le_bytes());
+            Ok(WIRE_FIXED32)
+        }
+        integral => {
+            let n = integer(value).ok_or_else(fail)?;
+            let (min, max) = match integral {
+                Scalar::Int32 | Scalar::Sint32 | Scalar::Sfixed32 => {
+                    (i32::MIN as i128, i32::MAX as i128)
+                }
+                Scalar::Uint32 | Scalar::Fixed32 => (0, u32::MAX as i128),
+                Scalar::Uint64 | Scalar::Fixed64 => (0, u64::MAX as i128),
+                _ => (i64::MIN as i128, i64::MAX as i128),
+            };
+            if n < min || n > max {
+                return Err(fail());
+            }
+
+            match integral {
+                Scalar::Int32 | Scalar::Int64 => write_varint(buf, n as i64 as u64),
+                Scalar::Uint32 | Scalar::Uint64 => write_varint(buf, n as u64),
+                Scalar::Sint32 | Scalar::Sint64 => write_varint(buf, zigzag(n as i64)),
+                Scalar::Fixed32 => buf.extend_from_slice(&(n as u32).to_le_bytes()),
+                Scalar::Sfixed32 => buf.extend_from_slice(&(n as i32).to_le_bytes()),
+                Scalar::Fixed64 => buf.extend_from_slice(&(n as u64).to_le_bytes()),
+                _ => buf.extend_from_slice(&(n as i64).to_le_bytes()),
+            }
+            Ok(match integral {
+                Scalar::Fixed32 | Scalar::Sfixed32 => WIRE_FIXED32,
+                Scalar::Fixed64 | Scalar::Sfixed64 => WIRE_FIXED64,
+                _ => WIRE_VARINT,
+            })
+        }
+    }
+}
+
+/// Integer given as a number or, for 64-bit values, as a string
+fn integer(value: &JsonValue) -> Option<i128> {
+    match value {
+        JsonValue::Number(n) => n
+            .as_i64()
+            .map(|n| n as i128)
+            .or_else(|| n.as_u64().map(|n| n as i128))
+            .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i128)),
+        JsonValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Floating point number given as a number, a numeric string, `NaN`, `Infinity` or `-Infinity`
+fn float(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(n) => n.as_f64(),
+        JsonValue::String(s) => match s.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            s => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn write_tag(buf: &mut Vec<u8>, number: u32, wire_type: u32) {
+    write_varint(buf, ((number << 3) | wire_type) as u64);
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn mismatch(type_name: &str, path: &str, value: &JsonValue) -> CliError {
+    CliError::Mapping(format!("{} at {}", type_name, path), value.to_string())
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// JSON name of a field: lowerCamelCase of the field name
+fn json_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                result.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn parse_scalar(name: &str) -> Option<Scalar> {
+    Some(match name {
+        "double" => Scalar::Double,
+        "float" => Scalar::Float,
+        "int32" => Scalar::Int32,
+        "int64" => Scalar::Int64,
+        "uint32" => Scalar::Uint32,
+        "uint64" => Scalar::Uint64,
+        "sint32" => Scalar::Sint32,
+        "sint64" => Scalar::Sint64,
+        "fixed32" => Scalar::Fixed32,
+        "fixed64" => Scalar::Fixed64,
+        "sfixed32" => Scalar::Sfixed32,
+        "sfixed64" => Scalar::Sfixed64,
+        "bool" => Scalar::Bool,
+        "string" => Scalar::String,
+        "bytes" => Scalar::Bytes,
+        _ => return None,
+    })
+}
+
+fn scalar_name(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Double => "double",
+        Scalar::Float => "float",
+        Scalar::Int32 => "int32",
+        Scalar::Int64 => "int64",
+        Scalar::Uint32 => "uint32",
+        Scalar::Uint64 => "uint64",
+        Scalar::Sint32 => "sint32",
+        Scalar::Sint64 => "sint64",
+        Scalar::Fixed32 => "fixed32",
+        Scalar::Fixed64 => "fixed64",
+        Scalar::Sfixed32 => "sfixed32",
+        Scalar::Sfixed64 => "sfixed64",
+        Scalar::Bool => "bool",
+        Scalar::String => "string",
+        Scalar::Bytes => "bytes",
+    }
+}
+
+struct Token {
+    text: String,
+    line: usize,
+    /// String literal, its text is unquoted
+    is_string: bool,
+}
+
+/// Splits the source into names, numbers, string literals and punctuation, skipping comments
+fn tokenize(src: &str) -> Result<Vec<Token>, CliError> {
+    let chars: Vec<char> = src.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '+';
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i += 2;
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some(&quote) if quote == c => break,
+                        Some('\\') => {
+                            i += 1;
+                            match chars.get(i) {
+                                Some('n') => text.push('\n'),
+                                Some('t') => text.push('\t'),
+                                Some(&escaped) => text.push(escaped),
+                                None => break,
+                            }
+                        }
+                        Some(&other) => text.push(other),
+                        None => {
+                            return Err(CliError::Schema(format!(
+                                "line {}: unterminated string",
+                                line
+                            )))
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push(Token {
+                    text,
+                    line,
+                    is_string: true,
+                });
+            }
+            c if is_word(c) => {
+                let start = i;
+                while i < chars.len() && is_word(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token {
+                    text: chars[start..i].iter().collect(),
+                    line,
+                    is_string: false,
+                });
+            }
+            c => {
+                tokens.push(Token {
+                    text: c.to_string(),
+                    line,
+                    is_string: false,
+                });
+                i += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser of the `.proto` syntax. Options, services and extensions are skipped.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    proto: Proto,
+}
+
+impl Parser {
+    fn file(&mut self) -> Result<(), CliError> {
+        let mut top_level = 0;
+        while self.pos < self.tokens.len() {
+            match self.word()?.as_str() {
+                "syntax" => {
+                    self.expect("=")?;
+                    self.proto.proto3 = self.string()? == "proto3";
+                    self.expect(";")?;
+                }
+                "package" => {
+                    self.proto.package = Some(self.word()?);
+                    self.expect(";")?;
+                }
+                "import" | "option" => self.skip_statement()?,
+                "message" => {
+                    let package = self.proto.package.clone().unwrap_or_default();
+                    let name = self.message(&package, vec![top_level])?;
+                    self.proto.top_level.push(name);
+                    top_level += 1;
+                }
+                "enum" => {
+                    let package = self.proto.package.clone().unwrap_or_default();
+                    self.enumeration(&package)?;
+                }
+                "service" | "extend" => self.skip_block()?,
+                ";" => {}
+                other => return Err(self.error(&format!("unexpected `{}`", other))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a message and its nested types, returns its full name
+    fn message(&mut self, scope: &str, indexes: Vec<i32>) -> Result<String, CliError> {
+        let full_name = qualify(scope, &self.word()?);
+        let mut fields = Vec::new();
+        let mut nested = 0;
+
+        self.expect("{")?;
+        while !self.eat("}") {
+            match self.word()?.as_str() {
+                "message" => {
+                    let mut nested_indexes = indexes.clone();
+                    nested_indexes.push(nested);
+                    self.message(&full_name, nested_indexes)?;
+                    nested += 1;
+                }
+                "enum" => self.enumeration(&full_name)?,
+                "option" | "reserved" | "extensions" => self.skip_statement()?,
+                "extend" => self.skip_block()?,
+                "oneof" => {
+                    self.word()?;
+                    self.expect("{")?;
+                    while !self.eat("}") {
+                        match self.word()?.as_str() {
+                            "option" => self.skip_statement()?,
+                            type_name => {
+                                let field_type = field_type(type_name);
+                                fields.push(self.field(Label::Optional, field_type)?)
+                            }
+                        }
+                    }
+                }
+                "map" => {
+                    self.expect("<")?;
+                    let key = self.word()?;
+                    let key_type = match parse_scalar(&key) {
+                        Some(Scalar::Double) | Some(Scalar::Float) | Some(Scalar::Bytes) | None => {
+                            return Err(
+                                self.error(&format!("`{}` is not a valid map key type", key))
+                            )
+                        }
+                        Some(scalar) => scalar,
+                    };
+                    self.expect(",")?;
+                    let value_type = field_type(&self.word()?);
+                    self.expect(">")?;
+                    let map_type = FieldType::Map(key_type, Box::new(value_type));
+                    fields.push(self.field(Label::Repeated, map_type)?);
+                }
+                "repeated" => {
+                    let field_type = field_type(&self.word()?);
+                    fields.push(self.field(Label::Repeated, field_type)?);
+                }
+                "optional" | "required" => {
+                    let field_type = field_type(&self.word()?);
+                    fields.push(self.field(Label::Optional, field_type)?);
+                }
+                "group" => return Err(self.error("groups are not supported")),
+                ";" => {}
+                type_name => {
+                    let label = if self.proto.proto3 {
+                        Label::Singular
+                    } else {
+                        Label::Optional
+                    };
+                    let field_type = field_type(type_name);
+                    fields.push(self.field(label, field_type)?);
+                }
+            }
+        }
+
+        fields.sort_by_key(|field| field.number);
+        self.proto
+            .messages
+            .insert(full_name.clone(), Message { fields, indexes });
+        Ok(full_name)
+    }
+
+    /// `NAME = NUMBER [OPTIONS];` after the label and the type
+    fn field(&mut self, label: Label, field_type: FieldType) -> Result<Field, CliError> {
+        let name = self.word()?;
+        self.expect("=")?;
+        let number = self.number()? as u32;
+
+        let mut custom_json_name = None;
+        if self.eat("[") {
+            while !self.eat("]") {
+                if self.eat("json_name") {
+                    self.expect("=")?;
+                    custom_json_name = Some(self.string()?);
+                } else {
+                    self.next()?;
+                }
+            }
+        }
+        self.expect(";")?;
+
+        Ok(Field {
+            json_name: custom_json_name.unwrap_or_else(|| json_name(&name)),
+            name,
+            number,
+            label,
+            field_type,
+        })
+    }
+
+    fn enumeration(&mut self, scope: &str) -> Result<(), CliError> {
+        let full_name = qualify(scope, &self.word()?);
+        let mut values = Vec::new();
+
+        self.expect("{")?;
+        while !self.eat("}") {
+            match self.word()?.as_str() {
+                "option" | "reserved" => self.skip_statement()?,
+                ";" => {}
+                name => {
+                    let name = name.to_string();
+                    self.expect("=")?;
+                    let number = self.number()? as i32;
+                    if self.eat("[") {
+                        while !self.eat("]") {
+                            self.next()?;
+                        }
+                    }
+                    self.expect(";")?;
+                    values.push((name, number));
+                }
+            }
+        }
+
+        self.proto.enums.insert(full_name, values);
+        Ok(())
+    }
+
+    /// Skips up to the end of a statement, including blocks of aggregate option values
+    fn skip_statement(&mut self) -> Result<(), CliError> {
+        let mut depth = 0;
+        loop {
+            let token = self.next()?;
+            match (token.is_string, token.text.as_str()) {
+                (false, "{") => depth += 1,
+                (false, "}") => depth -= 1,
+                (false, ";") if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips a declaration up to the end of its block
+    fn skip_block(&mut self) -> Result<(), CliError> {
+        while !self.eat("{") {
+            self.next()?;
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            let token = self.next()?;
+            match (token.is_string, token.text.as_str()) {
+                (false, "{") => depth += 1,
+                (false, "}") => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<&Token, CliError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token)
+            }
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    /// Name, number or punctuation
+    fn word(&mut self) -> Result<String, CliError> {
+        let token = self.next()?;
+        if token.is_string {
+            let text = token.text.clone();
+            return Err(self.error(&format!("unexpected string \"{}\"", text)));
+        }
+        Ok(token.text.clone())
+    }
+
+    fn string(&mut self) -> Result<String, CliError> {
+        let token = self.next()?;
+        if token.is_string {
+            Ok(token.text.clone())
+        } else {
+            let text = token.text.clone();
+            Err(self.error(&format!("expected a string, found `{}`", text)))
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, CliError> {
+        let word = self.word()?;
+        let parsed = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => word.parse(),
+        };
+        parsed.map_err(|_| self.error(&format!("expected a number, found `{}`", word)))
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), CliError> {
+        if self.eat(text) {
+            return Ok(());
+        }
+
+        let found = match self.tokens.get(self.pos) {
+            Some(token) => format!("`{}`", token.text),
+            None => "the end of file".to_string(),
+        };
+        Err(self.error(&format!("expected `{}`, found {}", text, found)))
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(token) if !token.is_string && token.text == text => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn error(&self, message: &str) -> CliError {
+        let line = self
+            .tokens
+            .get(self.pos.saturating_sub(1))
+            .map_or(1, |token| token.line);
+        CliError::Schema(format!("line {}: {}", line, message))
+    }
+}
+
+fn field_type(type_name: &str) -> FieldType {
+    match parse_scalar(type_name) {
+        Some(scalar) => FieldType::Scalar(scalar),
+        None => FieldType::Named(type_name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encode(src: &str, json: JsonValue) -> Vec<u8> {
+        let proto = parse_proto(src).unwrap();
+        let message = proto.message_name(None).unwrap();
+        proto.encode(&message, &json).unwrap()
+    }
+
+    const SCALARS: &str = r#"
+        syntax = "proto3";
+        message Scalars {
+            double d = 1;
+            float f = 2;
+            int32 i32 = 3;
+            int64 i64 = 4;
+            uint32 u32 = 5;
+            uint64 u64 = 6;
+            sint32 s32 = 7;
+            sint64 s64 = 8;
+            fixed32 f32 = 9;
+            fixed64 f64 = 10;
+            sfixed32 sf32 = 11;
+            sfixed64 sf64 = 12;
+            bool b = 13;
+            string s = 14;
+            bytes by = 15;
+        }
+    "#;
+
+    #[test]
+    fn encodes_scalars() {
+        let cases = vec![
+            (json!({"d": 1.5}), vec![0x09, 0, 0, 0, 0, 0, 0, 0xf8, 0x3f]),
+            (json!({"f": 1.5}), vec![0x15, 0, 0, 0xc0, 0x3f]),
+            (
+                json!({"i32": -1}),
+                vec![
+                    0x18, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+                ],
+            ),
+            (json!({"i64": "300"}), vec![0x20, 0xac, 0x02]),
+            (json!({"u32": 150}), vec![0x28, 0x96, 0x01]),
+            (
+                json!({"u64": "18446744073709551615"}),
+                vec![
+                    0x30, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+                ],
+            ),
+            (json!({"s32": -1}), vec![0x38, 0x01]),
+            (json!({"s64": -2}), vec![0x40, 0x03]),
+            (json!({"f32": 1}), vec![0x4d, 1, 0, 0, 0]),
+            (json!({"f64": 1}), vec![0x51, 1, 0, 0, 0, 0, 0, 0, 0]),
+            (json!({"sf32": -2}), vec![0x5d, 0xfe, 0xff, 0xff, 0xff]),
+            (
+                json!({"sf64": -2}),
+                vec![0x61, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (json!({"b": true}), vec![0x68, 0x01]),
+            (json!({"s": "hi"}), vec![0x72, 0x02, b'h', b'i']),
+            (json!({"by": "AQI="}), vec![0x7a, 0x02, 0x01, 0x02]),
+        ];
+
+        for (json, bytes) in cases {
+            assert_eq!(encode(SCALARS, json.clone()), bytes, "{}", json);
+        }
+    }
+
+    #[test]
+    fn skips_proto3_defaults() {
+        assert!(encode(SCALARS, json!({"i32": 0, "s": "", "b": false, "d": 0.0})).is_empty());
+    }
+
+    #[test]
+    fn rejects_values_out_of_range() {
+        let proto = parse_proto(SCALARS).unwrap();
+        for json in &[
+            json!({"i32": 2147483648i64}),
+            json!({"u32": -1}),
+            json!({"s": 1}),
+            json!({"unknown": 1}),
+        ] {
+            assert!(proto.encode("Scalars", json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn packs_repeated_numbers_in_proto3() {
+        let src = r#"
+            syntax = "proto3";
+            message Repeated {
+                repeated int32 numbers = 1;
+                repeated string names = 2;
+            }
+        "#;
+        assert_eq!(
+            encode(src, json!({"numbers": [1, 2, 300], "names": ["a", "b"]})),
+            vec![0x0a, 0x04, 0x01, 0x02, 0xac, 0x02, 0x12, 0x01, b'a', 0x12, 0x01, b'b']
+        );
+    }
+
+    #[test]
+    fn does_not_pack_repeated_numbers_in_proto2() {
+        let src = r#"
+            syntax = "proto2";
+            message Repeated {
+                repeated int32 numbers = 1;
+            }
+        "#;
+        assert_eq!(
+            encode(src, json!({"numbers": [1, 2, 300]})),
+            vec![0x08, 0x01, 0x08, 0x02, 0x08, 0xac, 0x02]
+        );
+    }
+
+    #[test]
+    fn encodes_map_entries() {
+        let src = r#"
+            syntax = "proto3";
+            message Maps {
+                map<string, int32> counts = 1;
+                map<bool, Item> items = 2;
+                message Item {
+                    string name = 1;
+                }
+            }
+        "#;
+        assert_eq!(
+            encode(src, json!({"counts": {"a": 1}})),
+            vec![0x0a, 0x05, 0x0a, 0x01, b'a', 0x10, 0x01]
+        );
+        assert_eq!(
+            encode(src, json!({"items": {"true": {"name": "x"}}})),
+            vec![0x12, 0x07, 0x08, 0x01, 0x12, 0x03, 0x0a, 0x01, b'x']
+        );
+    }
+
+    #[test]
+    fn encodes_message_indexes() {
+        assert_eq!(encode_message_indexes(&[0]), vec![0]);
+        assert_eq!(encode_message_indexes(&[1]), vec![2, 2]);
+        assert_eq!(encode_message_indexes(&[0, 2]), vec![4, 0, 4]);
+        assert_eq!(encode_message_indexes(&[70]), vec![2, 0x8c, 0x01]);
+    }
+
+    #[test]
+    fn finds_indexes_of_nested_messages() {
+        let proto = parse_proto(
+            r#"
+            syntax = "proto3";
+            package shop;
+            message Order {
+                message Line {}
+                message Address {
+                    message Geo {}
+                }
+            }
+            message Customer {}
+        "#,
+        )
+        .unwrap();
+
+        let indexes = |name: &str| {
+            let full_name = proto.message_name(Some(name)).unwrap();
+            proto.message_indexes(&full_name).to_vec()
+        };
+        assert_eq!(indexes("Order"), vec![0]);
+        assert_eq!(indexes("Order.Line"), vec![0, 0]);
+        assert_eq!(indexes("shop.Order.Address"), vec![0, 1]);
+        assert_eq!(indexes("Geo"), vec![0, 1, 0]);
+        assert_eq!(indexes("Customer"), vec![1]);
+    }
+}
@@ -1,18 +1,31 @@
-use crate::context::{AppCtx, SslCtx};
+use crate::encoder::Format;
+use crate::ssl::SslConfig;
 
 use native_tls::{Certificate, Identity, TlsConnector};
 use serde::de::DeserializeOwned;
 
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{fs, io};
-use ureq::Request;
+use std::time::Duration;
+use std::{fmt, fs, io, thread};
+use ureq::{Request, Response};
+use uuid::Uuid;
 
-const ACCEPT_HEADER_VALUE: &str =
-    "application/vnd.schemaregistry.v1+json, application/vnd.schemaregistry+json, application/json";
+/// Retries are not delayed longer, unless the first delay is
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+mod apicurio;
+mod confluent;
+mod glue;
+mod local;
+mod server;
+
+pub use server::RegistryServer;
 
 #[derive(thiserror::Error, Debug)]
 pub enum RegistryError {
@@ -20,122 +33,610 @@ pub enum RegistryError {
     Io(#[from] io::Error),
     #[error(transparent)]
     Tls(#[from] native_tls::Error),
-    #[error("Schema conflict")]
-    Conflict,
-    #[error("Schema or subject invalid")]
-    Invalid,
-    #[error("Schema not found")]
-    NotFound,
+    #[error("Schema conflict: {0}")]
+    Conflict(ApiError),
+    #[error("Schema or subject invalid: {0}")]
+    Invalid(ApiError),
+    #[error("Not found: {0}")]
+    NotFound(ApiError),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(ApiError),
+    #[error("Forbidden: {0}")]
+    Forbidden(ApiError),
     #[error("Schema registry: `{0}`")]
     Internal(String),
-    #[error("Schema registry internal")]
-    RegistryInternal,
+    #[error("Schema registry internal: {0}")]
+    RegistryInternal(ApiError),
+    #[error("Request rejected: {0}")]
+    Rejected(ApiError),
+    #[error("Schema registry does not support {0}")]
+    Unsupported(&'static str),
+    /// Settings of the client are not valid, nothing is requested
+    #[error("Schema registry config: {0}")]
+    Config(&'static str),
+}
+
+impl RegistryError {
+    /// Error of a response status, 4xx or 5xx
+    fn of_status(error: ApiError) -> RegistryError {
+        match error.status {
+            401 => RegistryError::Unauthorized(error),
+            403 => RegistryError::Forbidden(error),
+            404 => RegistryError::NotFound(error),
+            409 => RegistryError::Conflict(error),
+            422 => RegistryError::Invalid(error),
+            500..=599 => RegistryError::RegistryInternal(error),
+            _ => RegistryError::Rejected(error),
+        }
+    }
+}
+
+/// Error response of a registry: HTTP status, error code and message,
+/// e.g. 40401 subject not found or 42201 invalid schema of Confluent
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub error_code: Option<i64>,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: u16, message: &str) -> ApiError {
+        ApiError {
+            status,
+            error_code: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// Reads `{"error_code": .., "message": ..}` body of Confluent and Apicurio,
+    /// other bodies are taken as the message
+    fn of_response(resp: Response) -> ApiError {
+        let status = resp.status();
+        let status_text = resp.status_text().to_string();
+        let body = resp.into_string().unwrap_or_default();
+
+        match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(ErrorBody {
+                error_code,
+                message: Some(message),
+            }) => ApiError {
+                status,
+                error_code,
+                message,
+            },
+            _ if body.trim().is_empty() => ApiError::new(status, &status_text),
+            _ => ApiError::new(status, body.trim()),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error_code {
+            Some(error_code) => write!(f, "{} (error code {})", self.message, error_code),
+            None => write!(f, "{} (HTTP {})", self.message, self.status),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error_code: Option<i64>,
+    message: Option<String>,
 }
 
 type RegistryResult<T> = Result<T, RegistryError>;
 
+/// Schema registry flavour, selects the REST API the client speaks
+#[derive(Clone, Copy)]
+pub enum RegistryType {
+    Confluent,
+    Apicurio,
+    Glue,
+}
+
+impl RegistryType {
+    pub fn parse(raw: &str) -> Result<RegistryType, String> {
+        match raw {
+            "confluent" => Ok(RegistryType::Confluent),
+            "apicurio" => Ok(RegistryType::Apicurio),
+            "glue" => Ok(RegistryType::Glue),
+            _ => Err(format!("unknown registry type `{}`", raw)),
+        }
+    }
+}
+
+/// Id of a registered schema: a number, or a schema version UUID of Glue
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaId {
+    Number(u64),
+    Uuid(Uuid),
+}
+
+impl SchemaId {
+    pub fn parse(raw: &str) -> Result<SchemaId, String> {
+        match raw.parse::<u64>() {
+            Ok(number) => Ok(SchemaId::Number(number)),
+            Err(_) => Uuid::parse_str(raw)
+                .map(SchemaId::Uuid)
+                .map_err(|_| format!("`{}` is neither a number nor a UUID", raw)),
+        }
+    }
+}
+
+impl fmt::Display for SchemaId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaId::Number(number) => write!(f, "{}", number),
+            SchemaId::Uuid(uuid) => write!(f, "{}", uuid),
+        }
+    }
+}
+
+/// REST API of a schema registry
+trait Backend {
+    fn register_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SchemaId>;
+
+    /// Version is either a number or `latest`
+    fn get_schema_by_version(&self, subject: &str, version: &str) -> RegistryResult<SubjectSchema>;
+
+    fn get_schema_by_id(&self, id: SchemaId) -> RegistryResult<IdSchema>;
+
+    fn list_subjects(&self) -> RegistryResult<Vec<String>>;
+
+    fn list_versions(&self, subject: &str) -> RegistryResult<Vec<u32>>;
+
+    fn delete_subject(&self, subject: &str, permanent: bool) -> RegistryResult<Vec<u32>>;
+
+    fn delete_version(&self, subject: &str, version: &str, permanent: bool) -> RegistryResult<u32>;
+
+    fn get_compatibility(&self, subject: Option<&str>) -> RegistryResult<String>;
+
+    fn set_compatibility(&self, subject: Option<&str>, level: &str) -> RegistryResult<String>;
+
+    fn get_mode(&self, _subject: Option<&str>) -> RegistryResult<String> {
+        Err(RegistryError::Unsupported("modes"))
+    }
+
+    fn set_mode(
+        &self,
+        _subject: Option<&str>,
+        _mode: &str,
+        _force: bool,
+    ) -> RegistryResult<String> {
+        Err(RegistryError::Unsupported("modes"))
+    }
+}
+
+/// Connection settings of a schema registry client
+#[derive(Clone)]
+pub struct RegistryConfig {
+    urls: Vec<String>,
+    registry_type: RegistryType,
+    group: Option<String>,
+    ssl: Option<SslConfig>,
+    basic_auth: Option<(String, String)>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl RegistryConfig {
+    /// Confluent Schema Registry at the url, or comma-separated urls of a cluster,
+    /// without TLS, auth and timeouts. Failed requests are retried 3 times.
+    pub fn new(url: &str) -> RegistryConfig {
+        RegistryConfig {
+            urls: url
+                .split(',')
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty())
+                .collect(),
+            registry_type: RegistryType::Confluent,
+            group: None,
+            ssl: None,
+            basic_auth: None,
+            timeout: None,
+            connect_timeout: None,
+            read_timeout: None,
+            retries: 3,
+            backoff: Duration::from_millis(100),
+        }
+    }
+
+    pub fn registry_type(mut self, registry_type: RegistryType) -> RegistryConfig {
+        self.registry_type = registry_type;
+        self
+    }
+
+    /// Apicurio artifact group or Glue registry name
+    pub fn group(mut self, group: &str) -> RegistryConfig {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    pub fn ssl(mut self, ssl: SslConfig) -> RegistryConfig {
+        self.ssl = Some(ssl);
+        self
+    }
+
+    pub fn basic_auth(mut self, user: &str, password: &str) -> RegistryConfig {
+        self.basic_auth = Some((user.to_string(), password.to_string()));
+        self
+    }
+
+    /// Timeout of each request, including connecting
+    pub fn timeout(mut self, timeout: Duration) -> RegistryConfig {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> RegistryConfig {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of reading each chunk of a response
+    pub fn read_timeout(mut self, timeout: Duration) -> RegistryConfig {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// How many times requests failed with a connection error or a 5xx status are retried,
+    /// each time against the next url. Deletes failed with a 5xx status are not retried
+    /// as they may have deleted already, e.g. the latest version
+    pub fn retries(mut self, retries: u32) -> RegistryConfig {
+        self.retries = retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled before each next one up to 10 seconds
+    pub fn backoff(mut self, backoff: Duration) -> RegistryConfig {
+        self.backoff = backoff;
+        self
+    }
+}
+
+/// Schema registry client, the API is selected by the registry type
 pub struct RegistryClient {
-    url: String,
-    tls_connector: Option<Arc<TlsConnector>>,
+    registry_type: RegistryType,
+    backend: Box<dyn Backend>,
 }
 
 impl RegistryClient {
-    pub fn new(ctx: &AppCtx) -> RegistryResult<RegistryClient> {
-        let tls_connector = if ctx.ssl.enabled {
-            RegistryClient::get_tls_connector(&ctx.ssl)?
-        } else {
-            None
+    /// Client of the registry at the url, or of the one stored in the directory of a `file://` url
+    pub fn new(config: RegistryConfig) -> RegistryResult<RegistryClient> {
+        if config.urls.is_empty() {
+            return Err(RegistryError::Config("no url"));
+        }
+        if let [url] = config.urls.as_slice() {
+            if let Some(dir) = url.strip_prefix("file://") {
+                if !matches!(config.registry_type, RegistryType::Confluent) {
+                    return Err(RegistryError::Unsupported(
+                        "other types than confluent in a directory",
+                    ));
+                }
+                return Ok(RegistryClient {
+                    registry_type: config.registry_type,
+                    backend: Box::new(local::Local::open(Path::new(dir))?),
+                });
+            }
+        }
+
+        let tls_connector = match config.ssl {
+            Some(ref ssl) => Some(Arc::new(Http::get_tls_connector(ssl)?)),
+            None => None,
+        };
+
+        let http = Http {
+            urls: config.urls,
+            current: AtomicUsize::new(0),
+            tls_connector,
+            basic_auth: config.basic_auth,
+            timeout: config.timeout,
+            connect_timeout: config.connect_timeout,
+            read_timeout: config.read_timeout,
+            retries: config.retries,
+            backoff: config.backoff,
+        };
+        let group = config.group;
+
+        let backend: Box<dyn Backend> = match config.registry_type {
+            RegistryType::Confluent => Box::new(confluent::Confluent::new(http)),
+            RegistryType::Apicurio => Box::new(apicurio::Apicurio::new(http, group)),
+            RegistryType::Glue => Box::new(glue::Glue::new(http, group)),
         };
 
         Ok(RegistryClient {
-            url: ctx
-                .avro_ctx
-                .registry_url
-                .as_ref()
-                .expect("registry url expected")
-                .trim()
-                .to_string(),
-            tls_connector: tls_connector.map(|c| Arc::new(c)),
+            registry_type: config.registry_type,
+            backend,
         })
     }
 
-    pub fn get_schema_by_subject(&self, subject: &str) -> RegistryResult<(u32, String)> {
-        self.do_request::<GetResp>(
-            ureq::get,
-            &format!("{}/subjects/{}/versions/latest", self.url, subject),
-            None,
-        )
-        .map(|resp| (resp.id, resp.schema))
+    pub fn registry_type(&self) -> RegistryType {
+        self.registry_type
     }
 
-    pub fn register_schema(&self, subject: &str, raw_schema: &str) -> RegistryResult<u32> {
-        self.do_request::<PostResp>(
-            ureq::post,
-            &format!("{}/subjects/{}/versions", self.url, subject),
-            Some(json!({ "schema": raw_schema })),
-        )
-        .map(|resp| resp.id)
+    pub fn get_schema_by_subject(&self, subject: &str) -> RegistryResult<SubjectSchema> {
+        self.get_schema_by_version(subject, "latest")
     }
 
-    fn do_request<T: DeserializeOwned>(
+    pub fn register_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SchemaId> {
+        self.backend
+            .register_schema(subject, raw_schema, format, references)
+    }
+
+    /// Fetches referenced schemas recursively.
+    /// Returns raw schemas ordered so that dependencies go before their dependents.
+    pub fn resolve_references(
+        &self,
+        references: &[SchemaReference],
+    ) -> RegistryResult<Vec<String>> {
+        let mut visited = HashSet::new();
+        let mut resolved = Vec::new();
+        self.collect_references(references, &mut visited, &mut resolved)?;
+        Ok(resolved)
+    }
+
+    fn collect_references(
+        &self,
+        references: &[SchemaReference],
+        visited: &mut HashSet<(String, u32)>,
+        resolved: &mut Vec<String>,
+    ) -> RegistryResult<()> {
+        for reference in references {
+            if !visited.insert((reference.subject.clone(), reference.version)) {
+                continue;
+            }
+
+            let referenced =
+                self.get_schema_by_version(&reference.subject, &reference.version.to_string())?;
+            self.collect_references(&referenced.references, visited, resolved)?;
+            resolved.push(referenced.schema);
+        }
+
+        Ok(())
+    }
+
+    pub fn list_subjects(&self) -> RegistryResult<Vec<String>> {
+        self.backend.list_subjects()
+    }
+
+    pub fn list_versions(&self, subject: &str) -> RegistryResult<Vec<u32>> {
+        self.backend.list_versions(subject)
+    }
+
+    /// Returns a schema registered under the subject. Version is either a number or `latest`
+    pub fn get_schema_by_version(
+        &self,
+        subject: &str,
+        version: &str,
+    ) -> RegistryResult<SubjectSchema> {
+        self.backend.get_schema_by_version(subject, version)
+    }
+
+    pub fn get_schema_by_id(&self, id: SchemaId) -> RegistryResult<IdSchema> {
+        self.backend.get_schema_by_id(id)
+    }
+
+    /// Deletes all versions of the subject. Returns deleted versions
+    pub fn delete_subject(&self, subject: &str, permanent: bool) -> RegistryResult<Vec<u32>> {
+        self.backend.delete_subject(subject, permanent)
+    }
+
+    /// Deletes a version of the subject. Returns the deleted version
+    pub fn delete_version(
+        &self,
+        subject: &str,
+        version: &str,
+        permanent: bool,
+    ) -> RegistryResult<u32> {
+        self.backend.delete_version(subject, version, permanent)
+    }
+
+    /// Returns the compatibility level of the subject, or the global one
+    pub fn get_compatibility(&self, subject: Option<&str>) -> RegistryResult<String> {
+        self.backend.get_compatibility(subject)
+    }
+
+    /// Sets the compatibility level of the subject, or the global one
+    pub fn set_compatibility(&self, subject: Option<&str>, level: &str) -> RegistryResult<String> {
+        self.backend.set_compatibility(subject, level)
+    }
+
+    /// Returns the mode of the subject, or the global one
+    pub fn get_mode(&self, subject: Option<&str>) -> RegistryResult<String> {
+        self.backend.get_mode(subject)
+    }
+
+    /// Sets the mode of the subject, or the global one.
+    /// `force` allows to switch into IMPORT mode when schemas are already registered
+    pub fn set_mode(
+        &self,
+        subject: Option<&str>,
+        mode: &str,
+        force: bool,
+    ) -> RegistryResult<String> {
+        self.backend.set_mode(subject, mode, force)
+    }
+}
+
+/// HTTP transport of backends
+struct Http {
+    urls: Vec<String>,
+    /// Index of the url requests are sent to, the next one is tried on failures
+    current: AtomicUsize,
+    tls_connector: Option<Arc<TlsConnector>>,
+    basic_auth: Option<(String, String)>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Http {
+    /// Sends a request to the path under the registry url, 4xx and 5xx statuses are errors
+    fn send(
+        &self,
+        func: fn(&str) -> Request,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+        idempotent: bool,
+    ) -> RegistryResult<Response> {
+        let resp = self.exchange(func, path, headers, body, idempotent)?;
+        if resp.status() >= 400 {
+            return Err(RegistryError::of_status(ApiError::of_response(resp)));
+        }
+
+        Ok(resp)
+    }
+
+    /// Sends a request to the path under the registry url and returns the response of any status.
+    /// Connection errors and 5xx statuses are retried against the next url with exponential backoff.
+    /// Requests which are not `idempotent`, such as deletes, are retried only if they were not sent.
+    fn exchange(
+        &self,
+        func: fn(&str) -> Request,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+        idempotent: bool,
+    ) -> RegistryResult<Response> {
+        let max_backoff = MAX_BACKOFF.max(self.backoff);
+        let mut backoff = self.backoff;
+        let mut retries = self.retries;
+        let resp = loop {
+            let current = self.current.load(Ordering::Relaxed);
+            let resp = self.call(func, &self.urls[current], path, headers, body);
+            if retries == 0 || !Http::is_retryable(&resp, idempotent) {
+                break resp;
+            }
+
+            self.current
+                .store((current + 1) % self.urls.len(), Ordering::Relaxed);
+            thread::sleep(backoff);
+            backoff = backoff.saturating_mul(2).min(max_backoff);
+            retries -= 1;
+        };
+
+        match resp.synthetic_error() {
+            Some(err) => Err(RegistryError::Internal(err.body_text())),
+            None => Ok(resp),
+        }
+    }
+
+    fn call(
         &self,
         func: fn(&str) -> Request,
         url: &str,
-        json: Option<JsonValue>,
-    ) -> RegistryResult<T> {
-        let mut req = func(url).set("Accept", ACCEPT_HEADER_VALUE).build();
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Option<&str>,
+    ) -> Response {
+        let mut req = func(&format!("{}{}", url, path)).build();
+        for (name, value) in headers {
+            req.set(name, value);
+        }
 
         if let Some(connector) = self.tls_connector.as_ref() {
             req.set_tls_connector(connector.clone());
         }
+        if let Some((ref user, ref password)) = self.basic_auth {
+            req.auth(user, password);
+        }
+        if let Some(timeout) = self.timeout {
+            req.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            req.timeout_connect(timeout.as_millis() as u64);
+        }
+        if let Some(timeout) = self.read_timeout {
+            req.timeout_read(timeout.as_millis() as u64);
+        }
 
-        let resp = match json {
+        match body {
             None => req.call(),
-            Some(body) => req.send_json(body),
-        };
+            Some(body) => req.send_string(body),
+        }
+    }
 
-        if let Some(err) = resp.synthetic_error() {
-            return Err(RegistryError::Internal(err.body_text()));
-        };
+    /// Whether the registry may answer if asked again: it was not reached or failed internally.
+    /// A request which is not idempotent may have been done when the connection broke
+    /// after it was sent or when the registry failed, so it is not sent again
+    fn is_retryable(resp: &Response, idempotent: bool) -> bool {
+        match resp.synthetic_error() {
+            Some(ureq::Error::DnsFailed(_)) | Some(ureq::Error::ConnectionFailed(_)) => true,
+            Some(ureq::Error::Io(_)) => idempotent,
+            Some(_) => false,
+            None => resp.status() >= 500 && idempotent,
+        }
+    }
 
-        match resp.status() {
-            404 => {
-                return Err(RegistryError::NotFound);
-            }
-            422 => {
-                return Err(RegistryError::Invalid);
-            }
-            409 => {
-                return Err(RegistryError::Conflict);
-            }
-            500 => {
-                return Err(RegistryError::RegistryInternal);
-            }
-            _ => {}
+    /// Sends a JSON body, if any, and deserializes the JSON response
+    fn do_request<T: DeserializeOwned>(
+        &self,
+        func: fn(&str) -> Request,
+        path: &str,
+        headers: &[(&str, &str)],
+        json: Option<JsonValue>,
+        idempotent: bool,
+    ) -> RegistryResult<T> {
+        let body = json.map(|json| json.to_string());
+        let mut headers = headers.to_vec();
+        if body.is_some() {
+            headers.push(("Content-Type", "application/json"));
         }
 
-        resp.into_json_deserialize::<T>().map_err(|e| e.into())
+        self.send(func, path, &headers, body.as_deref(), idempotent)?
+            .into_json_deserialize::<T>()
+            .map_err(|e| e.into())
     }
 
-    fn get_tls_connector(ssl: &SslCtx) -> RegistryResult<Option<TlsConnector>> {
+    fn get_tls_connector(ssl: &SslConfig) -> RegistryResult<TlsConnector> {
         let mut builder = TlsConnector::builder();
 
         builder.danger_accept_invalid_certs(!ssl.cert_validate);
         builder.danger_accept_invalid_hostnames(!ssl.host_validate);
 
         if let Some(ref ca_path) = &ssl.ca_location {
-            let cert = RegistryClient::get_root_cert(ca_path)?;
+            let cert = Http::get_root_cert(ca_path)?;
             builder.add_root_certificate(cert);
         }
 
-        if let (Some(ref pkcs_path), Some(ref password)) =
-            (&ssl.keystore_location, &ssl.keystore_password)
-        {
-            let identity = RegistryClient::get_identity(pkcs_path, password)?;
-            builder.identity(identity);
+        match (&ssl.certificate_location, &ssl.key_location) {
+            (Some(ref cert_path), Some(ref key_path)) => {
+                builder.identity(Http::get_pem_identity(cert_path, key_path)?);
+            }
+            (None, None) => {
+                if let Some(ref pkcs_path) = ssl.keystore_location {
+                    let password = ssl.keystore_password.as_deref().unwrap_or_default();
+                    builder.identity(Http::get_identity(pkcs_path, password)?);
+                }
+            }
+            _ => {
+                return Err(RegistryError::Internal(
+                    "client certificate and key are expected together".to_string(),
+                ))
+            }
         }
 
-        builder.build().map(|c| Some(c)).map_err(|e| e.into())
+        builder.build().map_err(|e| e.into())
     }
 
     fn get_root_cert(ca_path: &str) -> RegistryResult<Certificate> {
@@ -149,6 +650,24 @@ impl RegistryClient {
             .map_err(|e| e.into())
             .and_then(|der| Identity::from_pkcs12(&der, password).map_err(|e| e.into()))
     }
+
+    fn get_pem_identity(cert_path: &str, key_path: &str) -> RegistryResult<Identity> {
+        let cert = fs::read(cert_path)?;
+        let key = fs::read(key_path)?;
+        Identity::from_pkcs8(&cert, &key).map_err(|e| e.into())
+    }
+}
+
+/// Percent-encodes a path segment, e.g. a subject which may contain `/` or `?`
+pub(crate) fn encode_segment(raw: &str) -> String {
+    raw.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
 }
 
 /// Returns a subject name using Topic Name strategy
@@ -157,21 +676,156 @@ pub fn get_subject(topic: &str) -> String {
     format!("{}-value", topic)
 }
 
-pub fn append_schema_id(id: u32, encoded_bytes: Vec<u8>) -> Vec<u8> {
-    let mut result: Vec<u8> = vec![0u8];
-    let id_bytes: [u8; 4] = u32::to_be_bytes(id);
-    result.extend_from_slice(&id_bytes);
-    result.extend_from_slice(encoded_bytes.as_slice());
-    result
+/// Parses a reference in `NAME=SUBJECT:VERSION` form
+pub fn parse_reference(raw: &str) -> Result<SchemaReference, String> {
+    let (name, subject_version) = match raw.find('=') {
+        Some(idx) => (&raw[..idx], &raw[idx + 1..]),
+        None => return Err(format!("`{}` is not in a NAME=SUBJECT:VERSION form", raw)),
+    };
+    let (subject, version) = match subject_version.rfind(':') {
+        Some(idx) => (&subject_version[..idx], &subject_version[idx + 1..]),
+        None => {
+            return Err(format!(
+                "`{}` is not in a SUBJECT:VERSION form",
+                subject_version
+            ))
+        }
+    };
+    let version = version
+        .parse::<u32>()
+        .map_err(|_| format!("`{}` is not a valid version", version))?;
+
+    Ok(SchemaReference {
+        name: name.to_string(),
+        subject: subject.to_string(),
+        version,
+    })
 }
 
-#[derive(Deserialize)]
-struct PostResp {
-    id: u32,
+/// Reference to a schema registered under another subject.
+/// For Avro the name is the fullname of the referenced type.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SchemaReference {
+    pub name: String,
+    pub subject: String,
+    pub version: u32,
 }
 
 #[derive(Deserialize)]
-struct GetResp {
-    id: u32,
-    schema: String,
+pub struct IdSchema {
+    pub schema: String,
+    #[serde(default)]
+    pub references: Vec<SchemaReference>,
+}
+
+pub struct SubjectSchema {
+    pub subject: String,
+    pub version: u32,
+    pub id: SchemaId,
+    pub schema: String,
+    /// `AVRO`, `JSON` or `PROTOBUF`
+    pub schema_type: String,
+    pub references: Vec<SchemaReference>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicU32;
+
+    /// Registry which fails every request, or never answers if not `responds`.
+    /// Returns its url and the count of requests
+    fn failing_registry(responds: bool) -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+        thread::spawn(move || {
+            let mut unanswered = Vec::new();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                if !responds {
+                    // kept open until the client times out
+                    unanswered.push(stream);
+                    continue;
+                }
+                stream
+                    .write_all(b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn failing_client(responds: bool) -> (RegistryClient, Arc<AtomicU32>) {
+        let (url, requests) = failing_registry(responds);
+        let config = RegistryConfig::new(&url)
+            .read_timeout(Duration::from_millis(100))
+            .retries(2)
+            .backoff(Duration::from_millis(1));
+        (RegistryClient::new(config).unwrap(), requests)
+    }
+
+    #[test]
+    fn retries_internal_errors() {
+        let (client, requests) = failing_client(true);
+        assert!(matches!(
+            client.list_subjects(),
+            Err(RegistryError::RegistryInternal(_))
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn does_not_retry_failed_deletes() {
+        let (client, requests) = failing_client(true);
+        assert!(matches!(
+            client.delete_version("users-value", "latest", false),
+            Err(RegistryError::RegistryInternal(_))
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retries_timed_out_queries() {
+        let (client, requests) = failing_client(false);
+        assert!(matches!(
+            client.list_subjects(),
+            Err(RegistryError::Internal(_))
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn does_not_retry_timed_out_deletes() {
+        let (client, requests) = failing_client(false);
+        assert!(matches!(
+            client.delete_subject("users-value", false),
+            Err(RegistryError::Internal(_))
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn encodes_path_segments() {
+        assert_eq!(encode_segment("my-topic.value_1~"), "my-topic.value_1~");
+        assert_eq!(encode_segment("a/b?c#d%e f"), "a%2Fb%3Fc%23d%25e%20f");
+        assert_eq!(encode_segment("é"), "%C3%A9");
+    }
+
+    #[test]
+    fn rejects_configs_without_urls() {
+        assert!(matches!(
+            RegistryClient::new(RegistryConfig::new(" , ")),
+            Err(RegistryError::Config(_))
+        ));
+    }
 }
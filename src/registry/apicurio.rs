@@ -0,0 +1,349 @@
+use crate::encoder::Format;
+
+use super::{
+    encode_segment, ApiError, Backend, Http, IdSchema, RegistryError, RegistryResult, SchemaId,
+    SchemaReference, SubjectSchema,
+};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value as JsonValue;
+use ureq::Request;
+
+const API_PATH: &str = "/apis/registry/v2";
+const DEFAULT_GROUP: &str = "default";
+const PAGE_SIZE: usize = 100;
+
+/// Apicurio Registry v2 REST API. Subjects are artifacts of a group, ids are global ids
+pub struct Apicurio {
+    http: Http,
+    group: String,
+}
+
+impl Apicurio {
+    pub fn new(http: Http, group: Option<String>) -> Apicurio {
+        Apicurio {
+            http,
+            group: group.unwrap_or_else(|| DEFAULT_GROUP.to_string()),
+        }
+    }
+
+    /// Sends a query, which is retried as any idempotent request
+    fn do_request<T: DeserializeOwned>(&self, path: &str) -> RegistryResult<T> {
+        self.http.do_request(
+            ureq::get,
+            &format!("{}{}", API_PATH, path),
+            &[("Accept", "application/json")],
+            None,
+            true,
+        )
+    }
+
+    /// Sends a request which has no response body.
+    /// Requests which are not `idempotent` are not retried once sent
+    fn do_command(
+        &self,
+        func: fn(&str) -> Request,
+        path: &str,
+        json: Option<JsonValue>,
+        idempotent: bool,
+    ) -> RegistryResult<()> {
+        let body = json.map(|json| json.to_string());
+        self.http
+            .send(
+                func,
+                &format!("{}{}", API_PATH, path),
+                &[("Content-Type", "application/json")],
+                body.as_deref(),
+                idempotent,
+            )
+            .map(|_| ())
+    }
+
+    fn artifact_path(&self, subject: &str) -> String {
+        format!(
+            "/groups/{}/artifacts/{}",
+            encode_segment(&self.group),
+            encode_segment(subject)
+        )
+    }
+
+    /// Metadata of a version, or of the latest one
+    fn version_meta(&self, subject: &str, version: &str) -> RegistryResult<VersionMeta> {
+        let path = match version {
+            "latest" => format!("{}/meta", self.artifact_path(subject)),
+            version => format!(
+                "{}/versions/{}/meta",
+                self.artifact_path(subject),
+                encode_segment(version)
+            ),
+        };
+        self.do_request::<VersionMeta>(&path)
+    }
+
+    /// Fetches all pages of a listing
+    fn list_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        items: fn(Page) -> (Vec<T>, usize),
+    ) -> RegistryResult<Vec<T>> {
+        let mut result = Vec::new();
+        loop {
+            let page = self.do_request::<Page>(&format!(
+                "{}?limit={}&offset={}",
+                path,
+                PAGE_SIZE,
+                result.len()
+            ))?;
+            let (page_items, count) = items(page);
+            let is_last = page_items.is_empty();
+            result.extend(page_items);
+
+            if is_last || result.len() >= count {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Rules of the artifact, or the global ones
+    fn rules_path(&self, subject: Option<&str>) -> String {
+        match subject {
+            Some(subject) => format!("{}/rules", self.artifact_path(subject)),
+            None => "/admin/rules".to_string(),
+        }
+    }
+}
+
+impl Backend for Apicurio {
+    fn register_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SchemaId> {
+        let artifact_references: Vec<JsonValue> = references
+            .iter()
+            .map(|reference| {
+                json!({
+                    "groupId": self.group,
+                    "artifactId": reference.subject,
+                    "version": reference.version.to_string(),
+                    "name": reference.name,
+                })
+            })
+            .collect();
+
+        // references need the extended content
+        let (content_type, body) = if references.is_empty() {
+            ("application/json", raw_schema.to_string())
+        } else {
+            let content = json!({ "content": raw_schema, "references": artifact_references });
+            ("application/create.extended+json", content.to_string())
+        };
+
+        self.http
+            .send(
+                ureq::post,
+                &format!(
+                    "{}/groups/{}/artifacts?ifExists=RETURN_OR_UPDATE",
+                    API_PATH,
+                    encode_segment(&self.group)
+                ),
+                &[
+                    ("Accept", "application/json"),
+                    ("Content-Type", content_type),
+                    ("X-Registry-ArtifactId", subject),
+                    ("X-Registry-ArtifactType", format.schema_type()),
+                ],
+                Some(&body),
+                // an existing artifact of the same content is returned
+                true,
+            )?
+            .into_json_deserialize::<VersionMeta>()
+            .map(|meta| SchemaId::Number(meta.global_id))
+            .map_err(|e| e.into())
+    }
+
+    fn get_schema_by_version(&self, subject: &str, version: &str) -> RegistryResult<SubjectSchema> {
+        let meta = self.version_meta(subject, version)?;
+        let registered = self.get_schema_by_id(SchemaId::Number(meta.global_id))?;
+
+        Ok(SubjectSchema {
+            subject: subject.to_string(),
+            version: parse_version(&meta.version)?,
+            id: SchemaId::Number(meta.global_id),
+            schema: registered.schema,
+            schema_type: meta.artifact_type,
+            references: registered.references,
+        })
+    }
+
+    fn get_schema_by_id(&self, id: SchemaId) -> RegistryResult<IdSchema> {
+        let global_id = match id {
+            SchemaId::Number(global_id) => global_id,
+            SchemaId::Uuid(_) => {
+                return Err(RegistryError::NotFound(ApiError::new(
+                    404,
+                    "global ids of Apicurio are numbers",
+                )))
+            }
+        };
+
+        let schema = self
+            .http
+            .send(
+                ureq::get,
+                &format!("{}/ids/globalIds/{}", API_PATH, global_id),
+                &[],
+                None,
+                true,
+            )?
+            .into_string()?;
+
+        // registries older than 2.2 have no references
+        let references = match self.do_request::<Vec<ArtifactReference>>(&format!(
+            "/ids/globalIds/{}/references",
+            global_id
+        )) {
+            Ok(references) => references,
+            Err(RegistryError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(IdSchema {
+            schema,
+            references: references
+                .into_iter()
+                .map(|reference| {
+                    Ok(SchemaReference {
+                        name: reference.name,
+                        subject: reference.artifact_id,
+                        version: parse_version(&reference.version)?,
+                    })
+                })
+                .collect::<RegistryResult<Vec<SchemaReference>>>()?,
+        })
+    }
+
+    fn list_subjects(&self) -> RegistryResult<Vec<String>> {
+        self.list_all(
+            &format!("/groups/{}/artifacts", encode_segment(&self.group)),
+            |page| {
+                let artifacts = page.artifacts.into_iter().map(|a| a.id).collect();
+                (artifacts, page.count)
+            },
+        )
+    }
+
+    fn list_versions(&self, subject: &str) -> RegistryResult<Vec<u32>> {
+        let versions = self.list_all(
+            &format!("{}/versions", self.artifact_path(subject)),
+            |page| {
+                let versions = page.versions.into_iter().map(|v| v.version).collect();
+                (versions, page.count)
+            },
+        )?;
+
+        versions.iter().map(|v| parse_version(v)).collect()
+    }
+
+    /// Artifacts are always deleted permanently
+    fn delete_subject(&self, subject: &str, _permanent: bool) -> RegistryResult<Vec<u32>> {
+        let versions = self.list_versions(subject)?;
+        self.do_command(ureq::delete, &self.artifact_path(subject), None, false)?;
+        Ok(versions)
+    }
+
+    fn delete_version(
+        &self,
+        subject: &str,
+        version: &str,
+        _permanent: bool,
+    ) -> RegistryResult<u32> {
+        let meta = self.version_meta(subject, version)?;
+        self.do_command(
+            ureq::delete,
+            &format!("{}/versions/{}", self.artifact_path(subject), meta.version),
+            None,
+            false,
+        )?;
+        parse_version(&meta.version)
+    }
+
+    /// The rule of the artifact, or the global one. `NONE` if there is no rule
+    fn get_compatibility(&self, subject: Option<&str>) -> RegistryResult<String> {
+        let rule = self.do_request::<Rule>(&format!("{}/COMPATIBILITY", self.rules_path(subject)));
+
+        match (rule, subject) {
+            (Ok(rule), _) => Ok(rule.config),
+            (Err(RegistryError::NotFound(_)), Some(_)) => self.get_compatibility(None),
+            (Err(RegistryError::NotFound(_)), None) => Ok("NONE".to_string()),
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    fn set_compatibility(&self, subject: Option<&str>, level: &str) -> RegistryResult<String> {
+        let rule = json!({ "type": "COMPATIBILITY", "config": level });
+        let rules_path = self.rules_path(subject);
+
+        // the rule is updated if it exists, created otherwise
+        match self.do_command(
+            ureq::put,
+            &format!("{}/COMPATIBILITY", rules_path),
+            Some(rule.clone()),
+            true,
+        ) {
+            Err(RegistryError::NotFound(_)) => {
+                self.do_command(ureq::post, &rules_path, Some(rule), false)?
+            }
+            other => other?,
+        }
+        Ok(level.to_string())
+    }
+}
+
+/// Versions are strings in Apicurio, only numeric ones are supported
+fn parse_version(version: &str) -> RegistryResult<u32> {
+    version
+        .parse()
+        .map_err(|_| RegistryError::Internal(format!("version `{}` is not a number", version)))
+}
+
+#[derive(Deserialize)]
+struct VersionMeta {
+    version: String,
+    #[serde(rename = "globalId")]
+    global_id: u64,
+    #[serde(rename = "type")]
+    artifact_type: String,
+}
+
+#[derive(Deserialize)]
+struct ArtifactReference {
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+    version: String,
+    name: String,
+}
+
+/// Page of artifacts or versions
+#[derive(Deserialize)]
+struct Page {
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+    #[serde(default)]
+    versions: Vec<VersionMeta>,
+    count: usize,
+}
+
+#[derive(Deserialize)]
+struct Artifact {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Rule {
+    config: String,
+}
@@ -0,0 +1,238 @@
+use crate::encoder::Format;
+
+use super::{
+    encode_segment, ApiError, Backend, Http, IdSchema, RegistryError, RegistryResult, SchemaId,
+    SchemaReference, SubjectSchema,
+};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value as JsonValue;
+use ureq::Request;
+
+const ACCEPT_HEADER_VALUE: &str =
+    "application/vnd.schemaregistry.v1+json, application/vnd.schemaregistry+json, application/json";
+
+/// Confluent Schema Registry REST API
+pub struct Confluent {
+    http: Http,
+}
+
+impl Confluent {
+    pub fn new(http: Http) -> Confluent {
+        Confluent { http }
+    }
+
+    /// Requests which are not `idempotent` are not retried once sent
+    fn do_request<T: DeserializeOwned>(
+        &self,
+        func: fn(&str) -> Request,
+        path: &str,
+        json: Option<JsonValue>,
+        idempotent: bool,
+    ) -> RegistryResult<T> {
+        self.http.do_request(
+            func,
+            path,
+            &[("Accept", ACCEPT_HEADER_VALUE)],
+            json,
+            idempotent,
+        )
+    }
+}
+
+impl Backend for Confluent {
+    fn register_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SchemaId> {
+        let mut body = json!({ "schema": raw_schema });
+        if !references.is_empty() {
+            body["references"] = json!(references);
+        }
+        // Avro is the default type, older registries know no other
+        if format != Format::Avro {
+            body["schemaType"] = json!(format.schema_type());
+        }
+
+        // the same schema registered again gets the same id
+        self.do_request::<PostResp>(
+            ureq::post,
+            &format!("/subjects/{}/versions", encode_segment(subject)),
+            Some(body),
+            true,
+        )
+        .map(|resp| SchemaId::Number(resp.id as u64))
+    }
+
+    fn get_schema_by_version(&self, subject: &str, version: &str) -> RegistryResult<SubjectSchema> {
+        self.do_request::<SubjectResp>(
+            ureq::get,
+            &format!(
+                "/subjects/{}/versions/{}",
+                encode_segment(subject),
+                encode_segment(version)
+            ),
+            None,
+            true,
+        )
+        .map(|resp| SubjectSchema {
+            subject: resp.subject,
+            version: resp.version,
+            id: SchemaId::Number(resp.id as u64),
+            schema: resp.schema,
+            schema_type: resp.schema_type,
+            references: resp.references,
+        })
+    }
+
+    fn get_schema_by_id(&self, id: SchemaId) -> RegistryResult<IdSchema> {
+        match id {
+            SchemaId::Number(id) => {
+                self.do_request::<IdSchema>(ureq::get, &format!("/schemas/ids/{}", id), None, true)
+            }
+            SchemaId::Uuid(_) => Err(RegistryError::NotFound(ApiError::new(
+                404,
+                "schema ids of Confluent are numbers",
+            ))),
+        }
+    }
+
+    fn list_subjects(&self) -> RegistryResult<Vec<String>> {
+        self.do_request::<Vec<String>>(ureq::get, "/subjects", None, true)
+    }
+
+    fn list_versions(&self, subject: &str) -> RegistryResult<Vec<u32>> {
+        self.do_request::<Vec<u32>>(
+            ureq::get,
+            &format!("/subjects/{}/versions", encode_segment(subject)),
+            None,
+            true,
+        )
+    }
+
+    fn delete_subject(&self, subject: &str, permanent: bool) -> RegistryResult<Vec<u32>> {
+        self.do_request::<Vec<u32>>(
+            ureq::delete,
+            &format!(
+                "/subjects/{}{}",
+                encode_segment(subject),
+                permanent_query(permanent)
+            ),
+            None,
+            false,
+        )
+    }
+
+    fn delete_version(&self, subject: &str, version: &str, permanent: bool) -> RegistryResult<u32> {
+        self.do_request::<u32>(
+            ureq::delete,
+            &format!(
+                "/subjects/{}/versions/{}{}",
+                encode_segment(subject),
+                encode_segment(version),
+                permanent_query(permanent)
+            ),
+            None,
+            false,
+        )
+    }
+
+    fn get_compatibility(&self, subject: Option<&str>) -> RegistryResult<String> {
+        let path = match subject {
+            Some(subject) => format!("/config/{}?defaultToGlobal=true", encode_segment(subject)),
+            None => "/config".to_string(),
+        };
+        self.do_request::<ConfigResp>(ureq::get, &path, None, true)
+            .map(|resp| resp.compatibility_level)
+    }
+
+    fn set_compatibility(&self, subject: Option<&str>, level: &str) -> RegistryResult<String> {
+        self.do_request::<ConfigUpdateResp>(
+            ureq::put,
+            &format!("/config{}", subject_path(subject)),
+            Some(json!({ "compatibility": level })),
+            true,
+        )
+        .map(|resp| resp.compatibility)
+    }
+
+    fn get_mode(&self, subject: Option<&str>) -> RegistryResult<String> {
+        let path = match subject {
+            Some(subject) => format!("/mode/{}?defaultToGlobal=true", encode_segment(subject)),
+            None => "/mode".to_string(),
+        };
+        self.do_request::<ModeResp>(ureq::get, &path, None, true)
+            .map(|resp| resp.mode)
+    }
+
+    fn set_mode(&self, subject: Option<&str>, mode: &str, force: bool) -> RegistryResult<String> {
+        self.do_request::<ModeResp>(
+            ureq::put,
+            &format!(
+                "/mode{}{}",
+                subject_path(subject),
+                if force { "?force=true" } else { "" }
+            ),
+            Some(json!({ "mode": mode })),
+            true,
+        )
+        .map(|resp| resp.mode)
+    }
+}
+
+fn subject_path(subject: Option<&str>) -> String {
+    subject
+        .map(|s| format!("/{}", encode_segment(s)))
+        .unwrap_or_default()
+}
+
+fn permanent_query(permanent: bool) -> &'static str {
+    if permanent {
+        "?permanent=true"
+    } else {
+        ""
+    }
+}
+
+#[derive(Deserialize)]
+struct PostResp {
+    id: u32,
+}
+
+#[derive(Deserialize)]
+struct SubjectResp {
+    subject: String,
+    version: u32,
+    id: u32,
+    schema: String,
+    /// Avro schemas come without a type
+    #[serde(rename = "schemaType", default = "avro_schema_type")]
+    schema_type: String,
+    #[serde(default)]
+    references: Vec<SchemaReference>,
+}
+
+fn avro_schema_type() -> String {
+    Format::Avro.schema_type().to_string()
+}
+
+#[derive(Deserialize)]
+struct ConfigResp {
+    #[serde(rename = "compatibilityLevel")]
+    compatibility_level: String,
+}
+
+#[derive(Deserialize)]
+struct ConfigUpdateResp {
+    compatibility: String,
+}
+
+#[derive(Deserialize)]
+struct ModeResp {
+    mode: String,
+}
@@ -0,0 +1,358 @@
+use crate::encoder::Format;
+
+use super::{
+    ApiError, Backend, Http, IdSchema, RegistryError, RegistryResult, SchemaId, SchemaReference,
+    SubjectSchema,
+};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+const DEFAULT_REGISTRY: &str = "default-registry";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+/// AWS Glue Schema Registry JSON API. Subjects are schemas of a registry,
+/// ids are schema version UUIDs.
+/// Requests are not signed, so either a local Glue-compatible registry or a signing proxy is expected.
+pub struct Glue {
+    http: Http,
+    registry: String,
+}
+
+impl Glue {
+    pub fn new(http: Http, registry: Option<String>) -> Glue {
+        Glue {
+            http,
+            registry: registry.unwrap_or_else(|| DEFAULT_REGISTRY.to_string()),
+        }
+    }
+
+    /// Calls an operation of the API. Errors are reported by exception types.
+    /// All operations are POSTs, those which are not `idempotent` are not retried once sent
+    fn call<T: DeserializeOwned>(
+        &self,
+        operation: &str,
+        request: JsonValue,
+        idempotent: bool,
+    ) -> RegistryResult<T> {
+        let target = format!("AWSGlue.{}", operation);
+        let resp = self.http.exchange(
+            ureq::post,
+            "/",
+            &[("Content-Type", CONTENT_TYPE), ("X-Amz-Target", &target)],
+            Some(&request.to_string()),
+            idempotent,
+        )?;
+
+        let status = resp.status();
+        if status >= 400 {
+            let body = resp.into_string()?;
+            let error = match serde_json::from_str::<ErrorResp>(&body) {
+                Ok(error) => error,
+                Err(_) => return Err(RegistryError::of_status(ApiError::new(status, body.trim()))),
+            };
+            let error_type = error.error_type.rsplit('#').next().unwrap_or_default();
+            let api_error = ApiError::new(
+                status,
+                &format!("{}: {}", error_type, error.message.unwrap_or_default()),
+            );
+            return Err(match error_type {
+                "EntityNotFoundException" => RegistryError::NotFound(api_error),
+                "AlreadyExistsException" => RegistryError::Conflict(api_error),
+                "InvalidInputException" => RegistryError::Invalid(api_error),
+                "AccessDeniedException" => RegistryError::Forbidden(api_error),
+                _ => RegistryError::of_status(api_error),
+            });
+        }
+
+        resp.into_json_deserialize::<T>().map_err(|e| e.into())
+    }
+
+    fn schema_id(&self, subject: &str) -> JsonValue {
+        json!({ "RegistryName": self.registry, "SchemaName": subject })
+    }
+
+    fn get_version(&self, subject: &str, version: &str) -> RegistryResult<VersionResp> {
+        let version_number = match version {
+            "latest" | "-1" => json!({ "LatestVersion": true }),
+            version => json!({ "VersionNumber": parse_version(version)? }),
+        };
+        self.call(
+            "GetSchemaVersion",
+            json!({ "SchemaId": self.schema_id(subject), "SchemaVersionNumber": version_number }),
+            true,
+        )
+    }
+}
+
+impl Backend for Glue {
+    /// Registers a new version, the schema is created with its first version if there is none
+    fn register_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SchemaId> {
+        if !references.is_empty() {
+            return Err(RegistryError::Unsupported("schema references"));
+        }
+
+        // the same definition registered again gets the same version
+        let registered = match self.call::<RegisterResp>(
+            "RegisterSchemaVersion",
+            json!({ "SchemaId": self.schema_id(subject), "SchemaDefinition": raw_schema }),
+            true,
+        ) {
+            Err(RegistryError::NotFound(_)) => self.call::<RegisterResp>(
+                "CreateSchema",
+                json!({
+                    "RegistryId": { "RegistryName": self.registry },
+                    "SchemaName": subject,
+                    "DataFormat": format.schema_type(),
+                    "SchemaDefinition": raw_schema,
+                }),
+                false,
+            )?,
+            other => other?,
+        };
+
+        parse_uuid(&registered.schema_version_id)
+    }
+
+    fn get_schema_by_version(&self, subject: &str, version: &str) -> RegistryResult<SubjectSchema> {
+        let version = self.get_version(subject, version)?;
+
+        Ok(SubjectSchema {
+            subject: subject.to_string(),
+            version: version.version_number,
+            id: parse_uuid(&version.schema_version_id)?,
+            schema: version.schema_definition,
+            schema_type: version.data_format,
+            references: Vec::new(),
+        })
+    }
+
+    fn get_schema_by_id(&self, id: SchemaId) -> RegistryResult<IdSchema> {
+        let version_id = match id {
+            SchemaId::Uuid(version_id) => version_id,
+            SchemaId::Number(_) => {
+                return Err(RegistryError::NotFound(ApiError::new(
+                    404,
+                    "schema version ids of Glue are UUIDs",
+                )))
+            }
+        };
+
+        self.call::<VersionResp>(
+            "GetSchemaVersion",
+            json!({ "SchemaVersionId": version_id.to_string() }),
+            true,
+        )
+        .map(|version| IdSchema {
+            schema: version.schema_definition,
+            references: Vec::new(),
+        })
+    }
+
+    fn list_subjects(&self) -> RegistryResult<Vec<String>> {
+        let mut subjects = Vec::new();
+        let mut next_token: Option<String> = None;
+        loop {
+            let page = self.call::<SchemasPage>(
+                "ListSchemas",
+                json!({ "RegistryId": { "RegistryName": self.registry }, "NextToken": next_token }),
+                true,
+            )?;
+            subjects.extend(page.schemas.into_iter().map(|s| s.schema_name));
+
+            match page.next_token {
+                Some(token) => next_token = Some(token),
+                None => return Ok(subjects),
+            }
+        }
+    }
+
+    fn list_versions(&self, subject: &str) -> RegistryResult<Vec<u32>> {
+        let mut versions = Vec::new();
+        let mut next_token: Option<String> = None;
+        loop {
+            let page = self.call::<VersionsPage>(
+                "ListSchemaVersions",
+                json!({ "SchemaId": self.schema_id(subject), "NextToken": next_token }),
+                true,
+            )?;
+            versions.extend(page.schemas.into_iter().map(|v| v.version_number));
+
+            match page.next_token {
+                Some(token) => next_token = Some(token),
+                None => {
+                    versions.sort_unstable();
+                    return Ok(versions);
+                }
+            }
+        }
+    }
+
+    /// Schemas are always deleted permanently
+    fn delete_subject(&self, subject: &str, _permanent: bool) -> RegistryResult<Vec<u32>> {
+        let versions = self.list_versions(subject)?;
+        self.call::<JsonValue>(
+            "DeleteSchema",
+            json!({ "SchemaId": self.schema_id(subject) }),
+            false,
+        )?;
+        Ok(versions)
+    }
+
+    fn delete_version(
+        &self,
+        subject: &str,
+        version: &str,
+        _permanent: bool,
+    ) -> RegistryResult<u32> {
+        let version_number = self.get_version(subject, version)?.version_number;
+        let resp = self.call::<DeleteVersionsResp>(
+            "DeleteSchemaVersions",
+            json!({ "SchemaId": self.schema_id(subject), "Versions": version_number.to_string() }),
+            false,
+        )?;
+
+        match resp.schema_version_errors.into_iter().next() {
+            Some(error) => Err(RegistryError::Internal(error.error_details.error_message)),
+            None => Ok(version_number),
+        }
+    }
+
+    /// Compatibility is set per schema, there is no global one
+    fn get_compatibility(&self, subject: Option<&str>) -> RegistryResult<String> {
+        let subject = subject.ok_or(RegistryError::Unsupported("global compatibility"))?;
+        self.call::<SchemaResp>(
+            "GetSchema",
+            json!({ "SchemaId": self.schema_id(subject) }),
+            true,
+        )
+        .map(|schema| of_glue_level(&schema.compatibility))
+    }
+
+    fn set_compatibility(&self, subject: Option<&str>, level: &str) -> RegistryResult<String> {
+        let subject = subject.ok_or(RegistryError::Unsupported("global compatibility"))?;
+        self.call::<JsonValue>(
+            "UpdateSchema",
+            json!({ "SchemaId": self.schema_id(subject), "Compatibility": to_glue_level(level) }),
+            true,
+        )?;
+        Ok(level.to_string())
+    }
+}
+
+/// Transitive levels are named `_ALL` in Glue, e.g. `BACKWARD_TRANSITIVE` is `BACKWARD_ALL`
+fn to_glue_level(level: &str) -> String {
+    level.replace("_TRANSITIVE", "_ALL")
+}
+
+fn of_glue_level(level: &str) -> String {
+    level.replace("_ALL", "_TRANSITIVE")
+}
+
+fn parse_version(version: &str) -> RegistryResult<u32> {
+    version
+        .parse()
+        .map_err(|_| RegistryError::Internal(format!("version `{}` is not a number", version)))
+}
+
+fn parse_uuid(raw: &str) -> RegistryResult<SchemaId> {
+    Uuid::parse_str(raw)
+        .map(SchemaId::Uuid)
+        .map_err(|_| RegistryError::Internal(format!("`{}` is not a schema version id", raw)))
+}
+
+#[derive(Deserialize)]
+struct ErrorResp {
+    #[serde(rename = "__type")]
+    error_type: String,
+    #[serde(alias = "Message")]
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RegisterResp {
+    schema_version_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VersionResp {
+    schema_version_id: String,
+    schema_definition: String,
+    version_number: u32,
+    data_format: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SchemaResp {
+    compatibility: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SchemasPage {
+    schemas: Vec<SchemaListItem>,
+    next_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SchemaListItem {
+    schema_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VersionsPage {
+    schemas: Vec<VersionListItem>,
+    next_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VersionListItem {
+    version_number: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeleteVersionsResp {
+    #[serde(default)]
+    schema_version_errors: Vec<VersionError>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VersionError {
+    error_details: ErrorDetails,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ErrorDetails {
+    error_message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_transitive_levels_as_glue() {
+        assert_eq!(to_glue_level("BACKWARD_TRANSITIVE"), "BACKWARD_ALL");
+        assert_eq!(to_glue_level("FULL"), "FULL");
+        assert_eq!(of_glue_level("FORWARD_ALL"), "FORWARD_TRANSITIVE");
+        assert_eq!(of_glue_level("DISABLED"), "DISABLED");
+    }
+}
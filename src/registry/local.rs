@@ -0,0 +1,885 @@
+use crate::avro;
+use crate::compat::{self, Level};
+use crate::encoder::Format;
+use crate::protobuf;
+
+use super::{
+    ApiError, Backend, IdSchema, RegistryError, RegistryResult, SchemaId, SchemaReference,
+    SubjectSchema,
+};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use std::{fs, io, thread};
+
+const DEFAULT_COMPATIBILITY: &str = "BACKWARD";
+const DEFAULT_MODE: &str = "READWRITE";
+const COMPATIBILITY_LEVELS: [&str; 7] = [
+    "NONE",
+    "BACKWARD",
+    "BACKWARD_TRANSITIVE",
+    "FORWARD",
+    "FORWARD_TRANSITIVE",
+    "FULL",
+    "FULL_TRANSITIVE",
+];
+const MODES: [&str; 4] = ["READWRITE", "READONLY", "READONLY_OVERRIDE", "IMPORT"];
+/// Created in the directory by the process which reads and changes the files
+const LOCK_FILE: &str = ".lock";
+const LOCK_RETRY: Duration = Duration::from_millis(10);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Registry of its own, which assigns ids and versions the way Confluent Schema Registry does.
+/// State is kept in memory, and in JSON files of the directory if there is one:
+/// `config.json`, `schemas/<id>.json` and `subjects/<subject>.json`.
+/// Processes sharing the directory lock it and read the files again before each request.
+pub struct Local {
+    dir: Option<PathBuf>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    config: Config,
+    /// Schema of id N is at N - 1
+    schemas: Vec<StoredSchema>,
+    subjects: BTreeMap<String, Subject>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Config {
+    #[serde(
+        rename = "compatibilityLevel",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    compatibility: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct StoredSchema {
+    pub schema: String,
+    /// Not present for Avro
+    #[serde(
+        rename = "schemaType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub schema_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<SchemaReference>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Subject {
+    subject: String,
+    versions: Vec<Version>,
+    #[serde(flatten)]
+    config: Config,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Version {
+    version: u32,
+    id: u64,
+    #[serde(default, skip_serializing_if = "is_false")]
+    deleted: bool,
+}
+
+impl Subject {
+    fn live_versions(&self) -> impl Iterator<Item = &Version> {
+        self.versions.iter().filter(|version| !version.deleted)
+    }
+}
+
+impl Local {
+    /// Registry kept in memory only
+    pub fn in_memory() -> Local {
+        Local {
+            dir: None,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Registry stored in the directory, which is created if missing
+    pub fn open(dir: &Path) -> RegistryResult<Local> {
+        fs::create_dir_all(dir.join("schemas"))?;
+        fs::create_dir_all(dir.join("subjects"))?;
+
+        let state = {
+            let _dir_lock = DirLock::acquire(dir)?;
+            State::read(dir)?
+        };
+        Ok(Local {
+            dir: Some(dir.to_path_buf()),
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Schema of the id with its type
+    pub fn stored_schema(&self, id: SchemaId) -> RegistryResult<StoredSchema> {
+        let state = self.lock()?;
+        match id {
+            SchemaId::Number(id) => state.schema(id).cloned(),
+            SchemaId::Uuid(_) => Err(error(404, 40403, "Schema not found")),
+        }
+    }
+
+    /// Version of the subject the schema is registered as
+    pub fn lookup_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SubjectSchema> {
+        let state = self.lock()?;
+        let schema = StoredSchema {
+            schema: normalize(raw_schema, format)?,
+            schema_type: schema_type(format),
+            references: references.to_vec(),
+        };
+        let subject_entry = state.live_subject(subject)?;
+        for version in subject_entry.live_versions() {
+            if state.schema(version.id)? == &schema {
+                return state.subject_schema(subject, version);
+            }
+        }
+        Err(error(404, 40403, "Schema not found"))
+    }
+
+    /// Whether the schema can read and be read by the version at the level of the subject
+    pub fn is_compatible(
+        &self,
+        subject: &str,
+        version: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<bool> {
+        let state = self.lock()?;
+        let schema = StoredSchema {
+            schema: normalize(raw_schema, format)?,
+            schema_type: schema_type(format),
+            references: references.to_vec(),
+        };
+        validate(&state, &schema, format)?;
+
+        let level = match Level::parse(state.compatibility(Some(subject))) {
+            Ok(level) => level,
+            Err(_) => return Ok(true),
+        };
+        let previous = state.find_version(state.live_subject(subject)?, version)?;
+        let previous = state.schema(previous.id)?;
+        Ok(state
+            .incompatibilities(&[previous], &schema, level)?
+            .is_none())
+    }
+
+    /// State as it is in the directory, which stays locked until the state is dropped
+    fn lock(&self) -> RegistryResult<Locked<'_>> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| RegistryError::Internal("registry state is poisoned".to_string()))?;
+        let dir_lock = match self.dir {
+            Some(ref dir) => {
+                let dir_lock = DirLock::acquire(dir)?;
+                *state = State::read(dir)?;
+                Some(dir_lock)
+            }
+            None => None,
+        };
+        Ok(Locked {
+            state,
+            _dir_lock: dir_lock,
+        })
+    }
+
+    fn save_config(&self, state: &State) -> RegistryResult<()> {
+        match self.dir {
+            Some(ref dir) => write_json(&dir.join("config.json"), &state.config),
+            None => Ok(()),
+        }
+    }
+
+    fn save_schema(&self, state: &State, id: u64) -> RegistryResult<()> {
+        match self.dir {
+            Some(ref dir) => write_json(
+                &dir.join("schemas").join(format!("{}.json", id)),
+                state.schema(id)?,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the subject, or removes its file if it is gone
+    fn save_subject(&self, state: &State, subject: &str) -> RegistryResult<()> {
+        let dir = match self.dir {
+            Some(ref dir) => dir,
+            None => return Ok(()),
+        };
+        let path = dir
+            .join("subjects")
+            .join(format!("{}.json", file_name(subject)));
+        match state.subjects.get(subject) {
+            Some(subject) => write_json(&path, subject),
+            None if path.exists() => fs::remove_file(path).map_err(|e| e.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Registry state locked in the process and in the directory, if there is one
+struct Locked<'a> {
+    state: MutexGuard<'a, State>,
+    _dir_lock: Option<DirLock>,
+}
+
+impl Deref for Locked<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        &self.state
+    }
+}
+
+impl DerefMut for Locked<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+}
+
+/// Lock file of a registry directory, removed when dropped
+struct DirLock {
+    path: PathBuf,
+}
+
+impl DirLock {
+    /// Waits for other processes to release the directory
+    fn acquire(dir: &Path) -> RegistryResult<DirLock> {
+        let path = dir.join(LOCK_FILE);
+        let started = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(DirLock { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if started.elapsed() >= LOCK_TIMEOUT {
+                        return Err(RegistryError::Internal(format!(
+                            "{} is locked, remove {} if no other process uses it",
+                            dir.display(),
+                            path.display()
+                        )));
+                    }
+                    thread::sleep(LOCK_RETRY);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl State {
+    /// Reads the files of the directory
+    fn read(dir: &Path) -> RegistryResult<State> {
+        let mut state = State::default();
+        let config_path = dir.join("config.json");
+        if config_path.exists() {
+            state.config = read_json(&config_path)?;
+        }
+
+        let mut schemas = BTreeMap::new();
+        for path in json_files(&dir.join("schemas"))? {
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+                .ok_or_else(|| {
+                    RegistryError::Internal(format!("{} is not named by an id", path.display()))
+                })?;
+            schemas.insert(id, read_json::<StoredSchema>(&path)?);
+        }
+        for (index, (id, schema)) in schemas.into_iter().enumerate() {
+            if id != index as u64 + 1 {
+                return Err(RegistryError::Internal(format!(
+                    "schema {} is missing in {}",
+                    index + 1,
+                    dir.display()
+                )));
+            }
+            state.schemas.push(schema);
+        }
+
+        for path in json_files(&dir.join("subjects"))? {
+            let subject: Subject = read_json(&path)?;
+            state.subjects.insert(subject.subject.clone(), subject);
+        }
+
+        Ok(state)
+    }
+
+    fn schema(&self, id: u64) -> RegistryResult<&StoredSchema> {
+        (id as usize)
+            .checked_sub(1)
+            .and_then(|index| self.schemas.get(index))
+            .ok_or_else(|| error(404, 40403, "Schema not found"))
+    }
+
+    /// Subject which has versions that are not deleted
+    fn live_subject(&self, subject: &str) -> RegistryResult<&Subject> {
+        self.subjects
+            .get(subject)
+            .filter(|s| s.live_versions().next().is_some())
+            .ok_or_else(|| subject_not_found(subject))
+    }
+
+    /// Version is a number, `latest` or -1
+    fn find_version<'a>(&self, subject: &'a Subject, version: &str) -> RegistryResult<&'a Version> {
+        match version {
+            "latest" | "-1" => subject.live_versions().last(),
+            version => {
+                let number = parse_version(version)?;
+                subject.live_versions().find(|v| v.version == number)
+            }
+        }
+        .ok_or_else(|| error(404, 40402, "Version not found."))
+    }
+
+    fn subject_schema(&self, subject: &str, version: &Version) -> RegistryResult<SubjectSchema> {
+        let stored = self.schema(version.id)?;
+        Ok(SubjectSchema {
+            subject: subject.to_string(),
+            version: version.version,
+            id: SchemaId::Number(version.id),
+            schema: stored.schema.clone(),
+            schema_type: stored
+                .schema_type
+                .clone()
+                .unwrap_or_else(|| Format::Avro.schema_type().to_string()),
+            references: stored.references.clone(),
+        })
+    }
+
+    fn compatibility(&self, subject: Option<&str>) -> &str {
+        subject
+            .and_then(|subject| self.subjects.get(subject))
+            .and_then(|subject| subject.config.compatibility.as_deref())
+            .or(self.config.compatibility.as_deref())
+            .unwrap_or(DEFAULT_COMPATIBILITY)
+    }
+
+    fn mode(&self, subject: Option<&str>) -> &str {
+        subject
+            .and_then(|subject| self.subjects.get(subject))
+            .and_then(|subject| subject.config.mode.as_deref())
+            .or(self.config.mode.as_deref())
+            .unwrap_or(DEFAULT_MODE)
+    }
+
+    /// Schemas of the references, dependencies first
+    fn dependencies(&self, references: &[SchemaReference]) -> RegistryResult<Vec<String>> {
+        let mut dependencies = Vec::new();
+        for reference in references {
+            let subject = self
+                .subjects
+                .get(&reference.subject)
+                .ok_or_else(|| subject_not_found(&reference.subject))?;
+            let version = self.find_version(subject, &reference.version.to_string())?;
+            let schema = self.schema(version.id)?;
+            dependencies.extend(self.dependencies(&schema.references)?);
+            dependencies.push(schema.schema.clone());
+        }
+        Ok(dependencies)
+    }
+
+    /// Avro schema with named types of its references inlined
+    fn standalone(&self, schema: &StoredSchema) -> RegistryResult<JsonValue> {
+        let dependencies = self.dependencies(&schema.references)?;
+        let standalone = avro::standalone_schema(&schema.schema, &dependencies)
+            .map_err(|e| error(422, 42201, &e.to_string()))?;
+        serde_json::from_str(&standalone).map_err(|e| RegistryError::Internal(e.to_string()))
+    }
+
+    /// Incompatibilities of Avro schemas at the level, other formats are not checked
+    fn incompatibilities(
+        &self,
+        previous: &[&StoredSchema],
+        new: &StoredSchema,
+        level: Level,
+    ) -> RegistryResult<Option<String>> {
+        if new.schema_type.is_some() || previous.iter().any(|s| s.schema_type.is_some()) {
+            return Ok(None);
+        }
+
+        let previous = previous
+            .iter()
+            .map(|schema| self.standalone(schema))
+            .collect::<RegistryResult<Vec<JsonValue>>>()?;
+        let incompatibilities = compat::check(&previous, &self.standalone(new)?, level);
+        if incompatibilities.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            incompatibilities
+                .iter()
+                .map(|i| format!("{}: {}: {}", i.direction, i.path, i.message))
+                .collect::<Vec<String>>()
+                .join("; "),
+        ))
+    }
+}
+
+impl Backend for Local {
+    fn register_schema(
+        &self,
+        subject: &str,
+        raw_schema: &str,
+        format: Format,
+        references: &[SchemaReference],
+    ) -> RegistryResult<SchemaId> {
+        let mut state = self.lock()?;
+        if state.mode(Some(subject)).starts_with("READONLY") {
+            return Err(error(
+                422,
+                42205,
+                &format!("Subject {} is in read-only mode", subject),
+            ));
+        }
+
+        let new = StoredSchema {
+            schema: normalize(raw_schema, format)?,
+            schema_type: schema_type(format),
+            references: references.to_vec(),
+        };
+        validate(&state, &new, format)?;
+
+        // the same schema is not registered twice
+        if let Some(subject) = state.subjects.get(subject) {
+            for version in subject.live_versions() {
+                if state.schema(version.id)? == &new {
+                    return Ok(SchemaId::Number(version.id));
+                }
+            }
+        }
+
+        // NONE is the only level which is not parsed
+        if let Ok(level) = Level::parse(state.compatibility(Some(subject))) {
+            let previous = match state.subjects.get(subject) {
+                Some(subject) => subject
+                    .live_versions()
+                    .map(|version| state.schema(version.id))
+                    .collect::<RegistryResult<Vec<&StoredSchema>>>()?,
+                None => Vec::new(),
+            };
+            if let Some(details) = state.incompatibilities(&previous, &new, level)? {
+                return Err(error(
+                    409,
+                    409,
+                    &format!(
+                        "Schema being registered is incompatible with an earlier schema for subject \"{}\", details: {}",
+                        subject, details
+                    ),
+                ));
+            }
+        }
+
+        let (id, is_new) = match state.schemas.iter().position(|schema| schema == &new) {
+            Some(index) => (index as u64 + 1, false),
+            None => {
+                state.schemas.push(new);
+                (state.schemas.len() as u64, true)
+            }
+        };
+
+        let entry = state
+            .subjects
+            .entry(subject.to_string())
+            .or_insert_with(|| Subject {
+                subject: subject.to_string(),
+                versions: Vec::new(),
+                config: Config::default(),
+            });
+        let version = entry.versions.last().map(|v| v.version).unwrap_or(0) + 1;
+        entry.versions.push(Version {
+            version,
+            id,
+            deleted: false,
+        });
+
+        if is_new {
+            self.save_schema(&state, id)?;
+        }
+        self.save_subject(&state, subject)?;
+        Ok(SchemaId::Number(id))
+    }
+
+    fn get_schema_by_version(&self, subject: &str, version: &str) -> RegistryResult<SubjectSchema> {
+        let state = self.lock()?;
+        let found = state.find_version(state.live_subject(subject)?, version)?;
+        state.subject_schema(subject, found)
+    }
+
+    fn get_schema_by_id(&self, id: SchemaId) -> RegistryResult<IdSchema> {
+        let stored = self.stored_schema(id)?;
+        Ok(IdSchema {
+            schema: stored.schema,
+            references: stored.references,
+        })
+    }
+
+    fn list_subjects(&self) -> RegistryResult<Vec<String>> {
+        let state = self.lock()?;
+        Ok(state
+            .subjects
+            .values()
+            .filter(|subject| subject.live_versions().next().is_some())
+            .map(|subject| subject.subject.clone())
+            .collect())
+    }
+
+    fn list_versions(&self, subject: &str) -> RegistryResult<Vec<u32>> {
+        let state = self.lock()?;
+        Ok(state
+            .live_subject(subject)?
+            .live_versions()
+            .map(|version| version.version)
+            .collect())
+    }
+
+    fn delete_subject(&self, subject: &str, permanent: bool) -> RegistryResult<Vec<u32>> {
+        let mut state = self.lock()?;
+        let entry = state
+            .subjects
+            .get_mut(subject)
+            .ok_or_else(|| subject_not_found(subject))?;
+        let is_live = entry.live_versions().next().is_some();
+
+        let deleted = if permanent {
+            if is_live {
+                return Err(error(
+                    404,
+                    40405,
+                    &format!(
+                        "Subject '{}' was not deleted first before being permanently deleted",
+                        subject
+                    ),
+                ));
+            }
+            let versions = entry.versions.iter().map(|v| v.version).collect();
+            state.subjects.remove(subject);
+            versions
+        } else {
+            if !is_live {
+                return Err(error(
+                    404,
+                    40404,
+                    &format!(
+                        "Subject '{}' was soft deleted.Set permanent=true to delete permanently",
+                        subject
+                    ),
+                ));
+            }
+            let mut versions = Vec::new();
+            for version in entry.versions.iter_mut().filter(|v| !v.deleted) {
+                version.deleted = true;
+                versions.push(version.version);
+            }
+            versions
+        };
+
+        self.save_subject(&state, subject)?;
+        Ok(deleted)
+    }
+
+    fn delete_version(&self, subject: &str, version: &str, permanent: bool) -> RegistryResult<u32> {
+        let mut state = self.lock()?;
+        let entry = state
+            .subjects
+            .get_mut(subject)
+            .ok_or_else(|| subject_not_found(subject))?;
+
+        let index = match version {
+            "latest" | "-1" if permanent => entry.versions.len().checked_sub(1),
+            "latest" | "-1" => entry.versions.iter().rposition(|v| !v.deleted),
+            version => {
+                let number = parse_version(version)?;
+                entry.versions.iter().position(|v| v.version == number)
+            }
+        }
+        .ok_or_else(|| error(404, 40402, "Version not found."))?;
+
+        let number = entry.versions[index].version;
+        match (permanent, entry.versions[index].deleted) {
+            (false, false) => entry.versions[index].deleted = true,
+            (false, true) => {
+                return Err(error(
+                    404,
+                    40406,
+                    &format!(
+                        "Subject '{}' Version {} was soft deleted.Set permanent=true to delete permanently",
+                        subject, number
+                    ),
+                ))
+            }
+            (true, true) => {
+                entry.versions.remove(index);
+                if entry.versions.is_empty() {
+                    state.subjects.remove(subject);
+                }
+            }
+            (true, false) => {
+                return Err(error(
+                    404,
+                    40407,
+                    &format!(
+                        "Subject '{}' Version {} was not deleted first before being permanently deleted",
+                        subject, number
+                    ),
+                ))
+            }
+        }
+
+        self.save_subject(&state, subject)?;
+        Ok(number)
+    }
+
+    fn get_compatibility(&self, subject: Option<&str>) -> RegistryResult<String> {
+        let state = self.lock()?;
+        Ok(state.compatibility(subject).to_string())
+    }
+
+    fn set_compatibility(&self, subject: Option<&str>, level: &str) -> RegistryResult<String> {
+        let level = level.to_uppercase();
+        if !COMPATIBILITY_LEVELS.contains(&level.as_str()) {
+            return Err(error(
+                422,
+                42203,
+                &format!("Invalid compatibility level {}", level),
+            ));
+        }
+
+        let mut state = self.lock()?;
+        match subject {
+            Some(subject) => {
+                state
+                    .subjects
+                    .get_mut(subject)
+                    .ok_or_else(|| subject_not_found(subject))?
+                    .config
+                    .compatibility = Some(level.clone());
+                self.save_subject(&state, subject)?;
+            }
+            None => {
+                state.config.compatibility = Some(level.clone());
+                self.save_config(&state)?;
+            }
+        }
+        Ok(level)
+    }
+
+    fn get_mode(&self, subject: Option<&str>) -> RegistryResult<String> {
+        let state = self.lock()?;
+        Ok(state.mode(subject).to_string())
+    }
+
+    fn set_mode(&self, subject: Option<&str>, mode: &str, _force: bool) -> RegistryResult<String> {
+        let mode = mode.to_uppercase();
+        if !MODES.contains(&mode.as_str()) {
+            return Err(error(422, 42204, &format!("Invalid mode {}", mode)));
+        }
+
+        let mut state = self.lock()?;
+        match subject {
+            Some(subject) => {
+                state
+                    .subjects
+                    .get_mut(subject)
+                    .ok_or_else(|| subject_not_found(subject))?
+                    .config
+                    .mode = Some(mode.clone());
+                self.save_subject(&state, subject)?;
+            }
+            None => {
+                state.config.mode = Some(mode.clone());
+                self.save_config(&state)?;
+            }
+        }
+        Ok(mode)
+    }
+}
+
+/// Error of the status with a Confluent error code
+pub(super) fn error(status: u16, error_code: i64, message: &str) -> RegistryError {
+    RegistryError::of_status(ApiError {
+        status,
+        error_code: Some(error_code),
+        message: message.to_string(),
+    })
+}
+
+fn subject_not_found(subject: &str) -> RegistryError {
+    error(404, 40401, &format!("Subject '{}' not found.", subject))
+}
+
+fn parse_version(version: &str) -> RegistryResult<u32> {
+    version.parse::<u32>().map_err(|_| {
+        error(
+            422,
+            42202,
+            &format!(
+                "The specified version '{}' is not a valid version id. Allowed values are between [1, 2^31-1] and the string \"latest\"",
+                version
+            ),
+        )
+    })
+}
+
+fn schema_type(format: Format) -> Option<String> {
+    match format {
+        Format::Avro => None,
+        format => Some(format.schema_type().to_string()),
+    }
+}
+
+/// JSON schemas are stored without whitespace
+fn normalize(raw_schema: &str, format: Format) -> RegistryResult<String> {
+    match format {
+        Format::Protobuf => Ok(raw_schema.to_string()),
+        _ => serde_json::from_str::<JsonValue>(raw_schema)
+            .map(|schema| schema.to_string())
+            .map_err(|e| error(422, 42201, &format!("Invalid schema: {}", e))),
+    }
+}
+
+fn validate(state: &State, schema: &StoredSchema, format: Format) -> RegistryResult<()> {
+    let result = match format {
+        Format::Avro => {
+            let dependencies = state.dependencies(&schema.references)?;
+            avro::parse_schema_with_references(&schema.schema, &dependencies).map(|_| ())
+        }
+        Format::JsonSchema => Ok(()),
+        Format::Protobuf => protobuf::parse_proto(&schema.schema).map(|_| ()),
+    };
+    result.map_err(|e| error(422, 42201, &format!("Invalid schema: {}", e)))
+}
+
+/// Subject names are kept as they are, except for characters not allowed in file names
+fn file_name(subject: &str) -> String {
+    subject
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'-' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn json_files(dir: &Path) -> RegistryResult<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> RegistryResult<T> {
+    let raw = fs::read_to_string(path)?;
+    serde_json::from_str(&raw)
+        .map_err(|e| RegistryError::Internal(format!("{}: {}", path.display(), e)))
+}
+
+/// Writes into a temporary file first, so that files are never half-written
+fn write_json<T: Serialize>(path: &Path, value: &T) -> RegistryResult<()> {
+    let tmp = path.with_extension("json.tmp");
+    let raw =
+        serde_json::to_string_pretty(value).map_err(|e| RegistryError::Internal(e.to_string()))?;
+    fs::write(&tmp, raw)?;
+    fs::rename(&tmp, path).map_err(|e| e.into())
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh directory of the test
+    fn registry_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("registry-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn register(registry: &Local, subject: &str, n: usize) -> SchemaId {
+        let schema = format!(r#"{{"type":"object","maxProperties":{}}}"#, n);
+        registry
+            .register_schema(subject, &schema, Format::JsonSchema, &[])
+            .unwrap()
+    }
+
+    #[test]
+    fn sees_changes_of_other_registries_of_the_directory() {
+        let dir = registry_dir("shared");
+        let first = Local::open(&dir).unwrap();
+        let second = Local::open(&dir).unwrap();
+
+        assert!(register(&first, "a", 1) == SchemaId::Number(1));
+        assert!(register(&second, "b", 2) == SchemaId::Number(2));
+        assert!(register(&first, "a", 3) == SchemaId::Number(3));
+
+        assert_eq!(first.list_subjects().unwrap(), vec!["a", "b"]);
+        assert_eq!(second.list_versions("a").unwrap(), vec![1, 2]);
+        assert!(!dir.join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn assigns_ids_once_across_registries() {
+        let dir = registry_dir("concurrent");
+        let handles: Vec<_> = (0..4)
+            .map(|n| {
+                let registry = Local::open(&dir).unwrap();
+                thread::spawn(move || {
+                    (0..5)
+                        .map(|i| register(&registry, &format!("s{}", n), n * 5 + i))
+                        .collect::<Vec<SchemaId>>()
+                })
+            })
+            .collect();
+        let mut ids: Vec<String> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .map(|id| id.to_string())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 20);
+
+        let registry = Local::open(&dir).unwrap();
+        assert_eq!(registry.list_subjects().unwrap().len(), 4);
+        assert!(registry.stored_schema(SchemaId::Number(20)).is_ok());
+    }
+}
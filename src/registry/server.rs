@@ -0,0 +1,794 @@
+use crate::encoder::Format;
+
+use super::local::{self, Local};
+use super::{Backend, RegistryError, RegistryResult, SchemaId, SchemaReference, SubjectSchema};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value as JsonValue;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{io, thread};
+
+const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+/// Idle keep-alive connections are closed after it
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Larger request bodies are rejected with 413 without reading them
+const MAX_BODY_SIZE: usize = 8 * 1024 * 1024;
+
+/// HTTP server of the Confluent Schema Registry API: subjects, versions, ids,
+/// compatibility config and modes of a registry kept in memory or in a directory
+pub struct RegistryServer {
+    listener: TcpListener,
+    registry: Arc<Local>,
+    /// Users of the basic authentication, anyone is served if there are none
+    users: Arc<Vec<User>>,
+}
+
+struct User {
+    user: String,
+    password: String,
+    /// Reads schemas only, other requests are forbidden
+    read_only: bool,
+}
+
+impl RegistryServer {
+    /// Listens on the address, e.g. `127.0.0.1:8081`, or `127.0.0.1:0` for a free port.
+    /// Schemas are stored in the directory if there is one.
+    pub fn bind(addr: &str, dir: Option<&Path>) -> RegistryResult<RegistryServer> {
+        let registry = match dir {
+            Some(dir) => Local::open(dir)?,
+            None => Local::in_memory(),
+        };
+        Ok(RegistryServer {
+            listener: TcpListener::bind(addr)?,
+            registry: Arc::new(registry),
+            users: Arc::new(Vec::new()),
+        })
+    }
+
+    /// Requires basic authentication, the user may do anything
+    pub fn basic_auth(self, user: &str, password: &str) -> RegistryServer {
+        self.add_user(user, password, false)
+    }
+
+    /// Requires basic authentication, the user may only read schemas and settings
+    pub fn read_only_basic_auth(self, user: &str, password: &str) -> RegistryServer {
+        self.add_user(user, password, true)
+    }
+
+    fn add_user(mut self, user: &str, password: &str, read_only: bool) -> RegistryServer {
+        Arc::get_mut(&mut self.users)
+            .expect("users are added before running")
+            .push(User {
+                user: user.to_string(),
+                password: password.to_string(),
+                read_only,
+            });
+        self
+    }
+
+    pub fn local_addr(&self) -> RegistryResult<SocketAddr> {
+        self.listener.local_addr().map_err(|e| e.into())
+    }
+
+    /// Serves requests until the process ends, each connection in a thread of its own
+    pub fn run(&self) -> RegistryResult<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let registry = self.registry.clone();
+            let users = self.users.clone();
+            thread::spawn(move || {
+                if let Err(e) = serve_connection(stream, &registry, &users) {
+                    eprintln!("connection error: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Request {
+    method: String,
+    /// Percent-decoded segments of the path
+    segments: Vec<String>,
+    query: String,
+    /// Value of the `Authorization` header
+    authorization: Option<String>,
+    body: String,
+    /// Body is larger than allowed and left unread, the connection is closed after the response
+    too_large: bool,
+    keep_alive: bool,
+}
+
+fn serve_connection(stream: TcpStream, registry: &Local, users: &[User]) -> io::Result<()> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    while let Some(request) = read_request(&mut reader)? {
+        let response = if request.too_large {
+            Err(local::error(
+                413,
+                413,
+                &format!("Request body is larger than {} bytes", MAX_BODY_SIZE),
+            ))
+        } else {
+            authorize(users, &request).and_then(|_| respond(registry, &request))
+        };
+        let (status, body) = match response {
+            Ok(body) => (200, body),
+            Err(e) => error_body(e),
+        };
+        let body = body.to_string();
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n{}",
+            status,
+            reason(status),
+            if status == 401 {
+                "WWW-Authenticate: Basic realm=\"schema-registry\"\r\n"
+            } else {
+                ""
+            },
+            CONTENT_TYPE,
+            body.len(),
+            if request.keep_alive {
+                "keep-alive"
+            } else {
+                "close"
+            },
+            body
+        )?;
+        writer.flush()?;
+
+        if !request.keep_alive {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a request, none if the connection is closed
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let mut parts = line.split_whitespace();
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) => (method, target, version),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed request line `{}`", line.trim()),
+            ))
+        }
+    };
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut keep_alive = version == "HTTP/1.1";
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some(colon) = header.find(':') {
+            let (name, value) = (header[..colon].trim(), header[colon + 1..].trim());
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "malformed content length")
+                })?;
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("connection") {
+                keep_alive = value.eq_ignore_ascii_case("keep-alive");
+            }
+        }
+    }
+
+    let too_large = content_length > MAX_BODY_SIZE;
+    let mut body = Vec::new();
+    if !too_large {
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body)?;
+    }
+
+    let (path, query) = match target.find('?') {
+        Some(index) => (&target[..index], &target[index + 1..]),
+        None => (target, ""),
+    };
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        segments: path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect(),
+        query: query.to_string(),
+        authorization,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        too_large,
+        keep_alive: keep_alive && !too_large,
+    }))
+}
+
+/// Checks the basic authentication of the request if there are users
+fn authorize(users: &[User], request: &Request) -> RegistryResult<()> {
+    if users.is_empty() {
+        return Ok(());
+    }
+
+    let credentials = request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| base64::decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+    let user = credentials.as_deref().and_then(|credentials| {
+        let colon = credentials.find(':')?;
+        users
+            .iter()
+            .find(|u| u.user == credentials[..colon] && u.password == credentials[colon + 1..])
+    });
+
+    match user {
+        None => Err(local::error(401, 401, "Unauthorized")),
+        Some(user) if user.read_only && !is_read(request) => Err(local::error(
+            403,
+            40301,
+            "User is denied operation on this server.",
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Whether the request only reads, lookups and compatibility checks post schemas but register none
+fn is_read(request: &Request) -> bool {
+    let segments: Vec<&str> = request.segments.iter().map(|s| s.as_str()).collect();
+    matches!(
+        (request.method.as_str(), segments.as_slice()),
+        ("GET", _) | ("POST", ["subjects", _]) | ("POST", ["compatibility", ..])
+    )
+}
+
+/// Routes the request to the registry and returns the JSON response
+fn respond(registry: &Local, request: &Request) -> RegistryResult<JsonValue> {
+    let segments: Vec<&str> = request.segments.iter().map(|s| s.as_str()).collect();
+    let permanent = has_param(&request.query, "permanent=true");
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", []) => Ok(json!({})),
+        ("GET", ["schemas", "types"]) => Ok(json!(["AVRO", "JSON", "PROTOBUF"])),
+        ("GET", ["schemas", "ids", id]) => {
+            let stored = registry.stored_schema(parse_id(id)?)?;
+            let mut body = json!({ "schema": stored.schema });
+            if let Some(schema_type) = stored.schema_type {
+                body["schemaType"] = json!(schema_type);
+            }
+            if !stored.references.is_empty() {
+                body["references"] = json!(stored.references);
+            }
+            Ok(body)
+        }
+        ("GET", ["schemas", "ids", id, "schema"]) => {
+            raw_schema(&registry.stored_schema(parse_id(id)?)?.schema)
+        }
+        ("GET", ["subjects"]) => Ok(json!(registry.list_subjects()?)),
+        ("POST", ["subjects", subject]) => {
+            let request: SchemaRequest = parse_body(&request.body)?;
+            let registered = registry.lookup_schema(
+                subject,
+                &request.schema,
+                request.format()?,
+                &request.references,
+            )?;
+            subject_schema(registry, registered)
+        }
+        ("DELETE", ["subjects", subject]) => {
+            Ok(json!(registry.delete_subject(subject, permanent)?))
+        }
+        ("GET", ["subjects", subject, "versions"]) => Ok(json!(registry.list_versions(subject)?)),
+        ("POST", ["subjects", subject, "versions"]) => {
+            let request: SchemaRequest = parse_body(&request.body)?;
+            let id = registry.register_schema(
+                subject,
+                &request.schema,
+                request.format()?,
+                &request.references,
+            )?;
+            Ok(json!({ "id": id_number(id) }))
+        }
+        ("GET", ["subjects", subject, "versions", version]) => {
+            subject_schema(registry, registry.get_schema_by_version(subject, version)?)
+        }
+        ("GET", ["subjects", subject, "versions", version, "schema"]) => {
+            raw_schema(&registry.get_schema_by_version(subject, version)?.schema)
+        }
+        ("DELETE", ["subjects", subject, "versions", version]) => {
+            Ok(json!(registry.delete_version(subject, version, permanent)?))
+        }
+        ("POST", ["compatibility", "subjects", subject, "versions", version]) => {
+            let request: SchemaRequest = parse_body(&request.body)?;
+            let is_compatible = registry.is_compatible(
+                subject,
+                version,
+                &request.schema,
+                request.format()?,
+                &request.references,
+            )?;
+            Ok(json!({ "is_compatible": is_compatible }))
+        }
+        ("GET", ["config"]) => {
+            Ok(json!({ "compatibilityLevel": registry.get_compatibility(None)? }))
+        }
+        ("GET", ["config", subject]) => {
+            Ok(json!({ "compatibilityLevel": registry.get_compatibility(Some(subject))? }))
+        }
+        ("PUT", ["config"]) => {
+            let request: ConfigRequest = parse_body(&request.body)?;
+            Ok(
+                json!({ "compatibility": registry.set_compatibility(None, &request.compatibility)? }),
+            )
+        }
+        ("PUT", ["config", subject]) => {
+            let request: ConfigRequest = parse_body(&request.body)?;
+            let level = registry.set_compatibility(Some(subject), &request.compatibility)?;
+            Ok(json!({ "compatibility": level }))
+        }
+        ("GET", ["mode"]) => Ok(json!({ "mode": registry.get_mode(None)? })),
+        ("GET", ["mode", subject]) => Ok(json!({ "mode": registry.get_mode(Some(subject))? })),
+        ("PUT", ["mode"]) => {
+            let request: ModeRequest = parse_body(&request.body)?;
+            Ok(json!({ "mode": registry.set_mode(None, &request.mode, false)? }))
+        }
+        ("PUT", ["mode", subject]) => {
+            let request: ModeRequest = parse_body(&request.body)?;
+            Ok(json!({ "mode": registry.set_mode(Some(subject), &request.mode, false)? }))
+        }
+        _ => Err(local::error(404, 404, "HTTP 404 Not Found")),
+    }
+}
+
+#[derive(Deserialize)]
+struct SchemaRequest {
+    schema: String,
+    #[serde(rename = "schemaType")]
+    schema_type: Option<String>,
+    #[serde(default)]
+    references: Vec<SchemaReference>,
+}
+
+impl SchemaRequest {
+    fn format(&self) -> RegistryResult<Format> {
+        match self.schema_type {
+            Some(ref schema_type) => Format::of_schema_type(schema_type)
+                .map_err(|e| local::error(422, 42201, &format!("Invalid schema: {}", e))),
+            None => Ok(Format::Avro),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ConfigRequest {
+    compatibility: String,
+}
+
+#[derive(Deserialize)]
+struct ModeRequest {
+    mode: String,
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> RegistryResult<T> {
+    serde_json::from_str(body)
+        .map_err(|e| local::error(400, 400, &format!("Unrecognized request: {}", e)))
+}
+
+fn parse_id(raw: &str) -> RegistryResult<SchemaId> {
+    raw.parse::<u64>()
+        .map(SchemaId::Number)
+        .map_err(|_| local::error(404, 40403, "Schema not found"))
+}
+
+fn id_number(id: SchemaId) -> JsonValue {
+    match id {
+        SchemaId::Number(id) => json!(id),
+        SchemaId::Uuid(uuid) => json!(uuid.to_string()),
+    }
+}
+
+fn subject_schema(registry: &Local, registered: SubjectSchema) -> RegistryResult<JsonValue> {
+    let stored = registry.stored_schema(registered.id)?;
+    let mut body = json!({
+        "subject": registered.subject,
+        "version": registered.version,
+        "id": id_number(registered.id),
+        "schema": registered.schema,
+    });
+    if let Some(schema_type) = stored.schema_type {
+        body["schemaType"] = json!(schema_type);
+    }
+    if !registered.references.is_empty() {
+        body["references"] = json!(registered.references);
+    }
+    Ok(body)
+}
+
+/// JSON schemas as they are, Protobuf ones as strings
+fn raw_schema(schema: &str) -> RegistryResult<JsonValue> {
+    Ok(serde_json::from_str(schema).unwrap_or_else(|_| json!(schema)))
+}
+
+/// Status and the Confluent error body
+fn error_body(error: RegistryError) -> (u16, JsonValue) {
+    match error {
+        RegistryError::Conflict(e)
+        | RegistryError::Invalid(e)
+        | RegistryError::NotFound(e)
+        | RegistryError::Unauthorized(e)
+        | RegistryError::Forbidden(e)
+        | RegistryError::RegistryInternal(e)
+        | RegistryError::Rejected(e) => (
+            e.status,
+            json!({ "error_code": e.error_code.unwrap_or(e.status as i64), "message": e.message }),
+        ),
+        e => (
+            500,
+            json!({ "error_code": 50001, "message": e.to_string() }),
+        ),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+fn has_param(query: &str, param: &str) -> bool {
+    query.split('&').any(|p| p == param)
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // two hex digits, `from_str_radix` would take a sign too
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// Server of an in-memory registry on a free port
+    fn serve() -> SocketAddr {
+        run(RegistryServer::bind("127.0.0.1:0", None).unwrap())
+    }
+
+    fn run(server: RegistryServer) -> SocketAddr {
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    /// Sends the request on a connection of its own, returns the status and the JSON body
+    fn send(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, JsonValue) {
+        parse_response(&exchange(addr, &request(method, path, None, body)))
+    }
+
+    /// Request with the credentials in `USER:PASSWORD` form if there are some
+    fn request(method: &str, path: &str, credentials: Option<&str>, body: &str) -> String {
+        let authorization = credentials
+            .map(|c| format!("Authorization: Basic {}\r\n", base64::encode(c)))
+            .unwrap_or_default();
+        format!(
+            "{} {} HTTP/1.1\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            authorization,
+            body.len(),
+            body
+        )
+    }
+
+    /// Sends the request on a connection of its own, returns the whole response
+    fn exchange(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn parse_response(response: &str) -> (u16, JsonValue) {
+        let status = response[9..12].parse().unwrap();
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    const SCHEMA: &str = r#"{"schema":"{\"type\":\"string\"}"}"#;
+
+    #[test]
+    fn serves_subjects_and_schemas() {
+        let addr = serve();
+
+        assert_eq!(send(addr, "GET", "/", ""), (200, json!({})));
+        assert_eq!(send(addr, "GET", "/subjects", ""), (200, json!([])));
+        assert_eq!(
+            send(addr, "POST", "/subjects/a%2Fb/versions", SCHEMA),
+            (200, json!({"id": 1}))
+        );
+        assert_eq!(send(addr, "GET", "/subjects", ""), (200, json!(["a/b"])));
+        assert_eq!(
+            send(addr, "GET", "/subjects/a%2Fb/versions", ""),
+            (200, json!([1]))
+        );
+        assert_eq!(
+            send(addr, "GET", "/schemas/ids/1", ""),
+            (200, json!({"schema": "{\"type\":\"string\"}"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/subjects/a%2Fb/versions/latest/schema", ""),
+            (200, json!({"type": "string"}))
+        );
+
+        let expected = json!({
+            "subject": "a/b",
+            "version": 1,
+            "id": 1,
+            "schema": "{\"type\":\"string\"}"
+        });
+        assert_eq!(
+            send(addr, "GET", "/subjects/a%2Fb/versions/1", ""),
+            (200, expected.clone())
+        );
+        assert_eq!(
+            send(addr, "POST", "/subjects/a%2Fb", SCHEMA),
+            (200, expected)
+        );
+        assert_eq!(
+            send(
+                addr,
+                "POST",
+                "/compatibility/subjects/a%2Fb/versions/latest",
+                SCHEMA
+            ),
+            (200, json!({"is_compatible": true}))
+        );
+
+        assert_eq!(
+            send(addr, "DELETE", "/subjects/a%2Fb/versions/1", ""),
+            (200, json!(1))
+        );
+        assert_eq!(send(addr, "DELETE", "/subjects/a%2Fb", "").0, 404);
+        assert_eq!(send(addr, "GET", "/subjects", ""), (200, json!([])));
+    }
+
+    #[test]
+    fn serves_config_and_modes() {
+        let addr = serve();
+
+        assert_eq!(
+            send(addr, "PUT", "/config", r#"{"compatibility":"full"}"#),
+            (200, json!({"compatibility": "FULL"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/config", ""),
+            (200, json!({"compatibilityLevel": "FULL"}))
+        );
+        assert_eq!(
+            send(addr, "PUT", "/mode", r#"{"mode":"READONLY"}"#),
+            (200, json!({"mode": "READONLY"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/mode", ""),
+            (200, json!({"mode": "READONLY"}))
+        );
+        assert_eq!(send(addr, "POST", "/subjects/a/versions", SCHEMA).0, 422);
+    }
+
+    #[test]
+    fn serves_config_and_modes_of_subjects() {
+        let addr = serve();
+        assert_eq!(
+            send(addr, "POST", "/subjects/a%2Fb/versions", SCHEMA).0,
+            200
+        );
+
+        assert_eq!(
+            send(addr, "PUT", "/config/a%2Fb", r#"{"compatibility":"NONE"}"#),
+            (200, json!({"compatibility": "NONE"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/config/a%2Fb", ""),
+            (200, json!({"compatibilityLevel": "NONE"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/config", ""),
+            (200, json!({"compatibilityLevel": "BACKWARD"}))
+        );
+        assert_eq!(
+            send(
+                addr,
+                "PUT",
+                "/config/missing",
+                r#"{"compatibility":"NONE"}"#
+            )
+            .0,
+            404
+        );
+
+        assert_eq!(
+            send(addr, "PUT", "/mode/a%2Fb", r#"{"mode":"READONLY"}"#),
+            (200, json!({"mode": "READONLY"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/mode/a%2Fb", ""),
+            (200, json!({"mode": "READONLY"}))
+        );
+        assert_eq!(
+            send(addr, "GET", "/mode", ""),
+            (200, json!({"mode": "READWRITE"}))
+        );
+        assert_eq!(
+            send(addr, "POST", "/subjects/a%2Fb/versions", SCHEMA).0,
+            422
+        );
+        assert_eq!(send(addr, "POST", "/subjects/c/versions", SCHEMA).0, 200);
+    }
+
+    #[test]
+    fn answers_errors_with_error_codes() {
+        let addr = serve();
+
+        assert_eq!(
+            send(addr, "GET", "/subjects/missing/versions", ""),
+            (
+                404,
+                json!({"error_code": 40401, "message": "Subject 'missing' not found."})
+            )
+        );
+        assert_eq!(send(addr, "GET", "/schemas/ids/7", "").0, 404);
+        assert_eq!(send(addr, "GET", "/unknown", "").0, 404);
+        assert_eq!(send(addr, "POST", "/subjects/a/versions", "{").0, 400);
+        assert_eq!(
+            send(addr, "POST", "/subjects/a/versions", r#"{"schema":"{"}"#).0,
+            422
+        );
+        assert_eq!(
+            send(addr, "PUT", "/config", r#"{"compatibility":"SOME"}"#).0,
+            422
+        );
+    }
+
+    #[test]
+    fn rejects_large_bodies() {
+        let addr = serve();
+        let response = exchange(
+            addr,
+            &format!(
+                "POST /subjects/a/versions HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_BODY_SIZE + 1
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert_eq!(parse_response(&response).1["error_code"], json!(413));
+    }
+
+    #[test]
+    fn requires_basic_auth() {
+        let server = RegistryServer::bind("127.0.0.1:0", None)
+            .unwrap()
+            .basic_auth("admin", "secret")
+            .read_only_basic_auth("reader", "secret");
+        let addr = run(server);
+
+        for credentials in &[None, Some("admin:wrong"), Some("nobody:secret")] {
+            let response = exchange(addr, &request("GET", "/subjects", *credentials, ""));
+            assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+            assert!(response.contains("WWW-Authenticate: Basic"));
+            assert_eq!(parse_response(&response).1["error_code"], json!(401));
+        }
+
+        let register = |credentials| {
+            exchange(
+                addr,
+                &request("POST", "/subjects/a/versions", Some(credentials), SCHEMA),
+            )
+        };
+        let response = register("reader:secret");
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        assert_eq!(parse_response(&response).1["error_code"], json!(40301));
+        assert_eq!(
+            parse_response(&register("admin:secret")),
+            (200, json!({"id": 1}))
+        );
+
+        let read = |method, path, body| {
+            parse_response(&exchange(
+                addr,
+                &request(method, path, Some("reader:secret"), body),
+            ))
+        };
+        assert_eq!(read("GET", "/subjects", ""), (200, json!(["a"])));
+        assert_eq!(read("POST", "/subjects/a", SCHEMA).0, 200);
+        assert_eq!(
+            read("POST", "/compatibility/subjects/a/versions/1", SCHEMA),
+            (200, json!({"is_compatible": true}))
+        );
+        assert_eq!(read("PUT", "/mode", r#"{"mode":"READONLY"}"#).0, 403);
+        assert_eq!(read("DELETE", "/subjects/a", "").0, 403);
+    }
+
+    #[test]
+    fn keeps_connections_alive() {
+        let addr = serve();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        for _ in 0..2 {
+            stream.write_all(b"GET /subjects HTTP/1.1\r\n\r\n").unwrap();
+            let mut status_line = String::new();
+            reader.read_line(&mut status_line).unwrap();
+            assert_eq!(status_line, "HTTP/1.1 200 OK\r\n");
+
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header == "\r\n" {
+                    break;
+                }
+                if let Some(length) = header.strip_prefix("Content-Length: ") {
+                    content_length = length.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            assert_eq!(body, b"[]");
+        }
+    }
+
+    #[test]
+    fn decodes_percent_encoded_segments() {
+        assert_eq!(percent_decode("a%2Fb%20c"), "a/b c");
+        assert_eq!(percent_decode("%C3%A9"), "é");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("%+1%-1"), "%+1%-1");
+    }
+}
@@ -0,0 +1,30 @@
+/// TLS settings of connections to brokers or the schema registry
+#[derive(Clone)]
+pub struct SslConfig {
+    pub cert_validate: bool,
+    pub host_validate: bool,
+    /// PEM file with CA certificates
+    pub ca_location: Option<String>,
+    /// PEM file with the client certificate, used with the key
+    pub certificate_location: Option<String>,
+    /// PEM file with the PKCS#8 private key of the client certificate
+    pub key_location: Option<String>,
+    /// PKCS#12 keystore with the client certificate and key, if there is no PEM certificate
+    pub keystore_location: Option<String>,
+    pub keystore_password: Option<String>,
+}
+
+impl Default for SslConfig {
+    /// TLS with certificate validation, without hostname validation
+    fn default() -> Self {
+        SslConfig {
+            cert_validate: true,
+            host_validate: false,
+            ca_location: None,
+            certificate_location: None,
+            key_location: None,
+            keystore_location: None,
+            keystore_password: None,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// Substitutes placeholders in a raw payload.
+/// Supported placeholders:
+/// * `{{seq}}` - sequence number of the emitted record
+/// * `{{timestamp}}` - current time in milliseconds since the Unix epoch
+/// * `{{uuid}}` - random UUID (v4), different for each occurrence
+///
+/// Unknown placeholders are left as is.
+pub fn render(raw: &str, seq: u64) -> String {
+    let mut result = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(start) = rest.find(OPEN) {
+        let (head, tail) = rest.split_at(start);
+        result.push_str(head);
+
+        match tail[OPEN.len()..].find(CLOSE) {
+            Some(end) => {
+                let name = &tail[OPEN.len()..OPEN.len() + end];
+                match substitute(name.trim(), seq) {
+                    Some(value) => result.push_str(&value),
+                    None => result.push_str(&tail[..OPEN.len() + end + CLOSE.len()]),
+                }
+                rest = &tail[OPEN.len() + end + CLOSE.len()..];
+            }
+            None => {
+                rest = tail;
+                break;
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+fn substitute(name: &str, seq: u64) -> Option<String> {
+    match name {
+        "seq" => Some(seq.to_string()),
+        "timestamp" => Some(now_millis().to_string()),
+        "uuid" => Some(Uuid::new_v4().to_string()),
+        _ => None,
+    }
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_placeholders() {
+        assert_eq!(render("{\"seq\": {{seq}}}", 42), "{\"seq\": 42}");
+        assert_eq!(render("{{ seq }}-{{seq}}", 7), "7-7");
+
+        let before = now_millis();
+        let timestamp: u128 = render("{{timestamp}}", 0).parse().unwrap();
+        assert!(timestamp >= before && timestamp <= now_millis());
+    }
+
+    #[test]
+    fn leaves_unknown_placeholders() {
+        assert_eq!(render("{{name}} {{seq}}", 1), "{{name}} 1");
+        assert_eq!(render("{{}}", 1), "{{}}");
+    }
+
+    #[test]
+    fn leaves_unterminated_placeholders() {
+        assert_eq!(render("{{seq}} {{seq", 3), "3 {{seq");
+        assert_eq!(render("}} {{", 3), "}} {{");
+    }
+
+    #[test]
+    fn renders_distinct_uuids() {
+        let rendered = render("{{uuid}} {{uuid}}", 0);
+        let uuids: Vec<Uuid> = rendered
+            .split(' ')
+            .map(|uuid| Uuid::parse_str(uuid).unwrap())
+            .collect();
+        assert_eq!(uuids.len(), 2);
+        assert_ne!(uuids[0], uuids[1]);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::canonical;
+use crate::error::CliError;
+use crate::registry::{RegistryType, SchemaId};
+
+use serde_json::Value as JsonValue;
+use std::fmt;
+use uuid::Uuid;
+
+/// Marker of the Avro single-object encoding
+const SINGLE_OBJECT_MAGIC: [u8; 2] = [0xc3, 0x01];
+/// Marker of the Confluent and Apicurio formats
+const MAGIC_BYTE: u8 = 0;
+const GLUE_HEADER_VERSION: u8 = 3;
+const GLUE_NO_COMPRESSION: u8 = 0;
+
+/// How encoded datums are framed in Kafka messages
+#[derive(Clone, Copy)]
+pub enum WireFormat {
+    /// Magic byte and the 4-byte schema id
+    Confluent,
+    /// Magic byte and the 8-byte global id
+    Apicurio,
+    /// Header version byte, compression byte and the 16-byte schema version UUID
+    Glue,
+    /// `C3 01` and the 8-byte little-endian CRC-64-AVRO fingerprint of the schema
+    SingleObject,
+    /// Datum only
+    Raw,
+}
+
+impl WireFormat {
+    pub fn parse(raw: &str) -> Result<WireFormat, String> {
+        match raw {
+            "confluent" => Ok(WireFormat::Confluent),
+            "apicurio" => Ok(WireFormat::Apicurio),
+            "glue" => Ok(WireFormat::Glue),
+            "single-object" => Ok(WireFormat::SingleObject),
+            "raw" => Ok(WireFormat::Raw),
+            _ => Err(format!("unknown wire format `{}`", raw)),
+        }
+    }
+
+    /// Format the serializers of the registry use
+    pub fn of_registry(registry_type: RegistryType) -> WireFormat {
+        match registry_type {
+            RegistryType::Confluent => WireFormat::Confluent,
+            RegistryType::Apicurio => WireFormat::Apicurio,
+            RegistryType::Glue => WireFormat::Glue,
+        }
+    }
+}
+
+impl fmt::Display for WireFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WireFormat::Confluent => "confluent",
+            WireFormat::Apicurio => "apicurio",
+            WireFormat::Glue => "glue",
+            WireFormat::SingleObject => "single-object",
+            WireFormat::Raw => "raw",
+        })
+    }
+}
+
+/// What precedes a datum and identifies its writer schema
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Header {
+    Confluent(u32),
+    Apicurio(u64),
+    Glue(Uuid),
+    Fingerprint(u64),
+    Raw,
+}
+
+impl Header {
+    /// Header of a registered schema in one of the registry formats
+    pub fn with_schema_id(format: WireFormat, id: SchemaId) -> Result<Header, CliError> {
+        match (format, id) {
+            (WireFormat::Confluent, SchemaId::Number(number)) if number <= u32::MAX as u64 => {
+                Ok(Header::Confluent(number as u32))
+            }
+            (WireFormat::Apicurio, SchemaId::Number(number)) => Ok(Header::Apicurio(number)),
+            (WireFormat::Glue, SchemaId::Uuid(uuid)) => Ok(Header::Glue(uuid)),
+            _ => Err(CliError::Schema(format!(
+                "schema id {} does not fit the wire format",
+                id
+            ))),
+        }
+    }
+
+    /// Id of the schema in the registry, if the header refers to one
+    pub fn schema_id(self) -> Option<SchemaId> {
+        match self {
+            Header::Confluent(id) => Some(SchemaId::Number(id as u64)),
+            Header::Apicurio(id) => Some(SchemaId::Number(id)),
+            Header::Glue(uuid) => Some(SchemaId::Uuid(uuid)),
+            Header::Fingerprint(_) | Header::Raw => None,
+        }
+    }
+}
+
+/// CRC-64-AVRO fingerprint of the Parsing Canonical Form of a standalone schema
+pub fn fingerprint(raw_schema: &str) -> Result<u64, CliError> {
+    let schema: JsonValue = serde_json::from_str(raw_schema)?;
+    Ok(canonical::fingerprint64(
+        canonical::canonical_form(&schema).as_bytes(),
+    ))
+}
+
+/// Prepends the header to the encoded datum
+pub fn frame(header: Header, datum: Vec<u8>) -> Vec<u8> {
+    let mut result = match header {
+        Header::Confluent(id) => {
+            let mut result = vec![MAGIC_BYTE];
+            result.extend_from_slice(&id.to_be_bytes());
+            result
+        }
+        Header::Apicurio(id) => {
+            let mut result = vec![MAGIC_BYTE];
+            result.extend_from_slice(&id.to_be_bytes());
+            result
+        }
+        Header::Glue(uuid) => {
+            let mut result = vec![GLUE_HEADER_VERSION, GLUE_NO_COMPRESSION];
+            result.extend_from_slice(uuid.as_bytes());
+            result
+        }
+        Header::Fingerprint(fingerprint) => {
+            let mut result = SINGLE_OBJECT_MAGIC.to_vec();
+            result.extend_from_slice(&fingerprint.to_le_bytes());
+            result
+        }
+        Header::Raw => Vec::new(),
+    };
+    result.extend(datum);
+    result
+}
+
+/// Detects the wire format of a message and splits it into the header and the datum.
+/// Schema-registry headers are detected only with a registry: ids after the magic byte are
+/// as long as the registry writes them, Glue headers are detected with the Glue registry.
+/// Messages which start with no known header are raw datums.
+pub fn split(
+    message: &[u8],
+    registry_type: Option<RegistryType>,
+) -> Result<(Header, &[u8]), CliError> {
+    let detected = match (registry_type, message) {
+        (_, [0xc3, 0x01, _, _, _, _, _, _, _, _, ..]) => WireFormat::SingleObject,
+        (Some(RegistryType::Apicurio), [MAGIC_BYTE, _, _, _, _, _, _, _, _, ..]) => {
+            WireFormat::Apicurio
+        }
+        (Some(RegistryType::Glue), [GLUE_HEADER_VERSION, _, rest @ ..]) if rest.len() >= 16 => {
+            WireFormat::Glue
+        }
+        (Some(_), [MAGIC_BYTE, _, _, _, _, ..]) => WireFormat::Confluent,
+        _ => WireFormat::Raw,
+    };
+    split_as(message, detected)
+}
+
+/// Splits a message of the wire format into the header and the datum
+pub fn split_as(message: &[u8], format: WireFormat) -> Result<(Header, &[u8]), CliError> {
+    match (format, message) {
+        (WireFormat::Confluent, [MAGIC_BYTE, a, b, c, d, datum @ ..]) => Ok((
+            Header::Confluent(u32::from_be_bytes([*a, *b, *c, *d])),
+            datum,
+        )),
+        (WireFormat::Apicurio, [MAGIC_BYTE, a, b, c, d, e, f, g, h, datum @ ..]) => {
+            let id = u64::from_be_bytes([*a, *b, *c, *d, *e, *f, *g, *h]);
+            Ok((Header::Apicurio(id), datum))
+        }
+        (WireFormat::Glue, [GLUE_HEADER_VERSION, compression, rest @ ..]) if rest.len() >= 16 => {
+            if *compression != GLUE_NO_COMPRESSION {
+                return Err(CliError::Schema(
+                    "compressed Glue messages are not supported".to_string(),
+                ));
+            }
+            let (uuid, datum) = rest.split_at(16);
+            let uuid = Uuid::from_slice(uuid).expect("16 bytes expected");
+            Ok((Header::Glue(uuid), datum))
+        }
+        (WireFormat::SingleObject, [0xc3, 0x01, a, b, c, d, e, f, g, h, datum @ ..]) => {
+            let fingerprint = u64::from_le_bytes([*a, *b, *c, *d, *e, *f, *g, *h]);
+            Ok((Header::Fingerprint(fingerprint), datum))
+        }
+        (WireFormat::Raw, _) => Ok((Header::Raw, message)),
+        (format, _) => Err(CliError::Schema(format!(
+            "message has no {} header",
+            format
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATUM: [u8; 3] = [0x02, 0x06, 0x66];
+
+    fn round_trip(header: Header, format: WireFormat, registry_type: Option<RegistryType>) {
+        let message = frame(header, DATUM.to_vec());
+
+        let (split_header, datum) = split_as(&message, format).unwrap();
+        assert!(split_header == header);
+        assert_eq!(datum, DATUM);
+
+        let (detected, datum) = split(&message, registry_type).unwrap();
+        assert!(detected == header);
+        assert_eq!(datum, DATUM);
+    }
+
+    #[test]
+    fn splits_framed_messages() {
+        round_trip(
+            Header::Confluent(0x0102_0304),
+            WireFormat::Confluent,
+            Some(RegistryType::Confluent),
+        );
+        round_trip(
+            Header::Apicurio(0x0102_0304_0506_0708),
+            WireFormat::Apicurio,
+            Some(RegistryType::Apicurio),
+        );
+        round_trip(
+            Header::Glue(Uuid::new_v4()),
+            WireFormat::Glue,
+            Some(RegistryType::Glue),
+        );
+        round_trip(
+            Header::Fingerprint(0xfedc_ba98_7654_3210),
+            WireFormat::SingleObject,
+            None,
+        );
+        round_trip(Header::Raw, WireFormat::Raw, None);
+    }
+
+    #[test]
+    fn frames_headers() {
+        assert_eq!(
+            frame(Header::Confluent(7), vec![0xaa]),
+            vec![0, 0, 0, 0, 7, 0xaa]
+        );
+        assert_eq!(
+            frame(Header::Fingerprint(1), vec![]),
+            vec![0xc3, 0x01, 1, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(frame(Header::Raw, vec![0xaa]), vec![0xaa]);
+    }
+
+    #[test]
+    fn takes_messages_without_registry_as_raw() {
+        // a record of int 0 and a string, not a schema id
+        let message = [0x00, 0x06, 0x66, 0x6f, 0x6f];
+        let (header, datum) = split(&message, None).unwrap();
+        assert!(header == Header::Raw);
+        assert_eq!(datum, message);
+    }
+
+    #[test]
+    fn splits_raw_datums_as_told() {
+        let message = frame(Header::Fingerprint(1), DATUM.to_vec());
+        let (header, datum) = split_as(&message, WireFormat::Raw).unwrap();
+        assert!(header == Header::Raw);
+        assert_eq!(datum, message.as_slice());
+    }
+
+    #[test]
+    fn rejects_messages_without_the_header() {
+        assert!(split_as(&[0x00, 0x01], WireFormat::Confluent).is_err());
+        assert!(split_as(&DATUM, WireFormat::SingleObject).is_err());
+        assert!(split_as(&[GLUE_HEADER_VERSION, 5], WireFormat::Glue).is_err());
+
+        let mut compressed = vec![GLUE_HEADER_VERSION, 5];
+        compressed.extend_from_slice(&[0; 16]);
+        assert!(split(&compressed, Some(RegistryType::Glue)).is_err());
+    }
+}
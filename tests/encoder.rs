@@ -0,0 +1,95 @@
+use kafka_avro_cli::encoder::{Encoder, Format};
+use kafka_avro_cli::error::CliError;
+use kafka_avro_cli::registry::{RegistryClient, RegistryConfig, RegistryServer};
+use kafka_avro_cli::wire::WireFormat;
+use serde_json::json;
+use std::thread;
+
+const ORDER_JSON: &str = r#"{"type":"object","properties":{"id":{"type":"integer"}}}"#;
+const ORDER_PROTO: &str = r#"syntax = "proto3"; message Order { int32 id = 1; }"#;
+
+/// Client of a registry served in memory on a free port
+fn client() -> RegistryClient {
+    let server = RegistryServer::bind("127.0.0.1:0", None).unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    thread::spawn(move || server.run());
+    RegistryClient::new(RegistryConfig::new(&url)).unwrap()
+}
+
+#[test]
+fn frames_messages_with_registered_ids() {
+    let client = client();
+    let encoder = Encoder::from_registry(
+        &client,
+        "orders-value",
+        Some(ORDER_JSON),
+        &[],
+        Format::JsonSchema,
+        WireFormat::Confluent,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        encoder.encode(&json!({"id": 7})).unwrap(),
+        b"\x00\x00\x00\x00\x01{\"id\":7}".to_vec()
+    );
+    assert!(encoder.encode(&json!({"id": "7"})).is_err());
+}
+
+#[test]
+fn takes_the_latest_registered_schema() {
+    let client = client();
+    client
+        .register_schema("orders-value", ORDER_PROTO, Format::Protobuf, &[])
+        .unwrap();
+
+    let encoder = Encoder::from_registry(
+        &client,
+        "orders-value",
+        None,
+        &[],
+        Format::Protobuf,
+        WireFormat::Confluent,
+        None,
+    )
+    .unwrap();
+
+    // the id, the index of the first message and the message
+    assert_eq!(
+        encoder.encode(&json!({"id": 7})).unwrap(),
+        vec![0, 0, 0, 0, 1, 0, 0x08, 0x07]
+    );
+}
+
+#[test]
+fn rejects_latest_schemas_of_another_type() {
+    let client = client();
+    client
+        .register_schema("orders-value", ORDER_PROTO, Format::Protobuf, &[])
+        .unwrap();
+
+    let encoder = Encoder::from_registry(
+        &client,
+        "orders-value",
+        None,
+        &[],
+        Format::JsonSchema,
+        WireFormat::Confluent,
+        None,
+    );
+    assert!(matches!(encoder, Err(CliError::Usage(_))));
+}
+
+#[test]
+fn needs_a_registry_for_schema_ids() {
+    let encoder =
+        Encoder::from_schema(ORDER_JSON, Format::JsonSchema, WireFormat::Raw, None).unwrap();
+    assert_eq!(
+        encoder.encode(&json!({"id": 7})).unwrap(),
+        b"{\"id\":7}".to_vec()
+    );
+
+    let encoder = Encoder::from_schema(ORDER_JSON, Format::JsonSchema, WireFormat::Confluent, None);
+    assert!(matches!(encoder, Err(CliError::Usage(_))));
+}
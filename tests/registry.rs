@@ -0,0 +1,167 @@
+use kafka_avro_cli::encoder::Format;
+use kafka_avro_cli::registry::{
+    RegistryClient, RegistryConfig, RegistryError, RegistryServer, SchemaId, SchemaReference,
+};
+use std::thread;
+
+const USER: &str = r#"{"type":"record","name":"User","fields":[{"name":"name","type":"string"}]}"#;
+const USER_WITH_AGE: &str = r#"{"type":"record","name":"User","fields":[{"name":"name","type":"string"},{"name":"age","type":"int","default":0}]}"#;
+const USER_WITH_EMAIL: &str = r#"{"type":"record","name":"User","fields":[{"name":"name","type":"string"},{"name":"email","type":"string"}]}"#;
+
+/// Client of a registry served in memory on a free port
+fn client() -> RegistryClient {
+    let server = RegistryServer::bind("127.0.0.1:0", None).unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    thread::spawn(move || server.run());
+    RegistryClient::new(RegistryConfig::new(&url)).unwrap()
+}
+
+fn register(client: &RegistryClient, subject: &str, schema: &str) -> SchemaId {
+    client
+        .register_schema(subject, schema, Format::Avro, &[])
+        .unwrap()
+}
+
+#[test]
+fn registers_and_gets_schemas() {
+    let client = client();
+
+    let id = register(&client, "users-value", USER);
+    assert!(register(&client, "users-value", USER) == id);
+    let newer_id = register(&client, "users-value", USER_WITH_AGE);
+    assert!(newer_id != id);
+
+    let by_id = client.get_schema_by_id(id).unwrap();
+    assert_eq!(by_id.schema, USER);
+
+    let first = client.get_schema_by_version("users-value", "1").unwrap();
+    assert_eq!(first.subject, "users-value");
+    assert_eq!(first.version, 1);
+    assert!(first.id == id);
+    assert_eq!(first.schema, USER);
+
+    let latest = client.get_schema_by_subject("users-value").unwrap();
+    assert_eq!(latest.version, 2);
+    assert!(latest.id == newer_id);
+    assert_eq!(latest.schema, USER_WITH_AGE);
+}
+
+#[test]
+fn lists_subjects_and_versions() {
+    let client = client();
+    assert!(client.list_subjects().unwrap().is_empty());
+
+    register(&client, "users-value", USER);
+    register(&client, "users-value", USER_WITH_AGE);
+    register(&client, "a/b c", USER);
+
+    assert_eq!(
+        client.list_subjects().unwrap(),
+        vec!["a/b c".to_string(), "users-value".to_string()]
+    );
+    assert_eq!(client.list_versions("users-value").unwrap(), vec![1, 2]);
+    assert_eq!(client.list_versions("a/b c").unwrap(), vec![1]);
+}
+
+#[test]
+fn deletes_versions_and_subjects() {
+    let client = client();
+    register(&client, "users-value", USER);
+    register(&client, "users-value", USER_WITH_AGE);
+
+    assert_eq!(client.delete_version("users-value", "1", false).unwrap(), 1);
+    assert_eq!(client.list_versions("users-value").unwrap(), vec![2]);
+
+    assert_eq!(
+        client.delete_subject("users-value", false).unwrap(),
+        vec![2]
+    );
+    assert!(client.list_subjects().unwrap().is_empty());
+    assert!(matches!(
+        client.get_schema_by_subject("users-value"),
+        Err(RegistryError::NotFound(_))
+    ));
+}
+
+#[test]
+fn checks_compatibility_on_registering() {
+    let client = client();
+    register(&client, "users-value", USER);
+    assert_eq!(
+        client
+            .set_compatibility(Some("users-value"), "BACKWARD")
+            .unwrap(),
+        "BACKWARD"
+    );
+    assert_eq!(
+        client.get_compatibility(Some("users-value")).unwrap(),
+        "BACKWARD"
+    );
+
+    register(&client, "users-value", USER_WITH_AGE);
+    assert!(matches!(
+        client.register_schema("users-value", USER_WITH_EMAIL, Format::Avro, &[]),
+        Err(RegistryError::Conflict(_))
+    ));
+
+    client
+        .set_compatibility(Some("users-value"), "NONE")
+        .unwrap();
+    register(&client, "users-value", USER_WITH_EMAIL);
+    assert_eq!(client.list_versions("users-value").unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn resolves_references() {
+    let client = client();
+    register(&client, "user", USER);
+    let reference = SchemaReference {
+        name: "User".to_string(),
+        subject: "user".to_string(),
+        version: 1,
+    };
+    let event = r#"{"type":"record","name":"Event","fields":[{"name":"user","type":"User"}]}"#;
+    client
+        .register_schema(
+            "events-value",
+            event,
+            Format::Avro,
+            std::slice::from_ref(&reference),
+        )
+        .unwrap();
+
+    let registered = client.get_schema_by_subject("events-value").unwrap();
+    assert!(registered.references == vec![reference]);
+    assert_eq!(
+        client.resolve_references(&registered.references).unwrap(),
+        vec![USER.to_string()]
+    );
+}
+
+#[test]
+fn rejects_invalid_schemas() {
+    let client = client();
+    assert!(matches!(
+        client.register_schema("users-value", "{", Format::Avro, &[]),
+        Err(RegistryError::Invalid(_))
+    ));
+}
+
+#[test]
+fn tells_schema_types() {
+    let client = client();
+    register(&client, "users-value", USER);
+    client
+        .register_schema(
+            "orders-value",
+            r#"{"type":"object"}"#,
+            Format::JsonSchema,
+            &[],
+        )
+        .unwrap();
+
+    let avro = client.get_schema_by_subject("users-value").unwrap();
+    assert_eq!(avro.schema_type, "AVRO");
+    let json = client.get_schema_by_subject("orders-value").unwrap();
+    assert_eq!(json.schema_type, "JSON");
+}